@@ -0,0 +1,42 @@
+-- Databases set up by hand before migrations existed already have these tables,
+-- so they are only created when missing.
+CREATE TABLE IF NOT EXISTS unit (
+    id SERIAL PRIMARY KEY,
+    unit_number INTEGER NOT NULL,
+    full_name VARCHAR(255) NOT NULL,
+    short_name VARCHAR(64) NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS tech (
+    id SERIAL PRIMARY KEY,
+    inventory_number INTEGER NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    model VARCHAR(255) NOT NULL,
+    acquisition_date DATE NOT NULL,
+    price INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS employee (
+    id SERIAL PRIMARY KEY,
+    first_name VARCHAR(100) NOT NULL,
+    middle_name VARCHAR(100) NOT NULL,
+    last_name VARCHAR(100) NOT NULL,
+    is_supervisor BOOLEAN NOT NULL,
+    is_accountable BOOLEAN NOT NULL,
+    job_title VARCHAR(255) NOT NULL,
+    unit_id INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS room (
+    id SERIAL PRIMARY KEY,
+    room_number INTEGER NOT NULL,
+    squares INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS transfer (
+    id SERIAL PRIMARY KEY,
+    transfer_date DATE NOT NULL,
+    tech_id INTEGER NOT NULL,
+    room_id INTEGER NOT NULL,
+    employee_id INTEGER NOT NULL
+);
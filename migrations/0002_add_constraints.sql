@@ -0,0 +1,45 @@
+-- Constraint names match the ones Postgres picks by default, so a database that
+-- already has them is left untouched.
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_constraint WHERE conname = 'unit_unit_number_key') THEN
+        ALTER TABLE unit ADD CONSTRAINT unit_unit_number_key UNIQUE (unit_number);
+    END IF;
+
+    IF NOT EXISTS (SELECT 1 FROM pg_constraint WHERE conname = 'tech_inventory_number_key') THEN
+        ALTER TABLE tech ADD CONSTRAINT tech_inventory_number_key UNIQUE (inventory_number);
+    END IF;
+
+    IF NOT EXISTS (SELECT 1 FROM pg_constraint WHERE conname = 'room_room_number_key') THEN
+        ALTER TABLE room ADD CONSTRAINT room_room_number_key UNIQUE (room_number);
+    END IF;
+
+    IF NOT EXISTS (SELECT 1 FROM pg_constraint WHERE conname = 'employee_unit_id_fkey') THEN
+        ALTER TABLE employee ADD CONSTRAINT employee_unit_id_fkey
+            FOREIGN KEY (unit_id) REFERENCES unit (id);
+    END IF;
+
+    -- An employee is either the unit's supervisor or an accountable person, never both.
+    IF NOT EXISTS (SELECT 1 FROM pg_constraint WHERE conname = 'employee_role_check') THEN
+        ALTER TABLE employee ADD CONSTRAINT employee_role_check
+            CHECK (is_supervisor <> is_accountable);
+    END IF;
+
+    IF NOT EXISTS (SELECT 1 FROM pg_constraint WHERE conname = 'transfer_tech_id_fkey') THEN
+        ALTER TABLE transfer ADD CONSTRAINT transfer_tech_id_fkey
+            FOREIGN KEY (tech_id) REFERENCES tech (id);
+    END IF;
+
+    IF NOT EXISTS (SELECT 1 FROM pg_constraint WHERE conname = 'transfer_room_id_fkey') THEN
+        ALTER TABLE transfer ADD CONSTRAINT transfer_room_id_fkey
+            FOREIGN KEY (room_id) REFERENCES room (id);
+    END IF;
+
+    IF NOT EXISTS (SELECT 1 FROM pg_constraint WHERE conname = 'transfer_employee_id_fkey') THEN
+        ALTER TABLE transfer ADD CONSTRAINT transfer_employee_id_fkey
+            FOREIGN KEY (employee_id) REFERENCES employee (id);
+    END IF;
+END
+$$;
+
+CREATE INDEX IF NOT EXISTS transfer_tech_id_date_idx ON transfer (tech_id, transfer_date);
@@ -0,0 +1 @@
+ALTER TABLE room ADD CONSTRAINT room_squares_check CHECK (squares > 0);
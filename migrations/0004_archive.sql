@@ -0,0 +1,4 @@
+-- Archived rows stay in place so the transfers that reference them keep their history.
+ALTER TABLE tech ADD COLUMN archived_at TIMESTAMPTZ;
+ALTER TABLE employee ADD COLUMN archived_at TIMESTAMPTZ;
+ALTER TABLE unit ADD COLUMN archived_at TIMESTAMPTZ;
@@ -0,0 +1,20 @@
+-- A written off item keeps its tech row and history; this records when, why and
+-- under which act it left the books. An item is written off at most once.
+CREATE TABLE write_off (
+    id SERIAL PRIMARY KEY,
+    tech_id INTEGER NOT NULL,
+    write_off_date DATE NOT NULL,
+    disposal VARCHAR(16) NOT NULL,
+    reason TEXT NOT NULL,
+    act_number VARCHAR(64) NOT NULL,
+    employee_id INTEGER NOT NULL,
+    residual_value INTEGER NOT NULL,
+    CONSTRAINT write_off_tech_id_key UNIQUE (tech_id),
+    CONSTRAINT write_off_act_number_key UNIQUE (act_number),
+    CONSTRAINT write_off_tech_id_fkey FOREIGN KEY (tech_id) REFERENCES tech (id),
+    CONSTRAINT write_off_employee_id_fkey FOREIGN KEY (employee_id) REFERENCES employee (id),
+    CONSTRAINT write_off_disposal_check CHECK (disposal IN ('written_off', 'sold', 'scrapped')),
+    CONSTRAINT write_off_residual_value_check CHECK (residual_value >= 0)
+);
+
+CREATE INDEX write_off_date_idx ON write_off (write_off_date);
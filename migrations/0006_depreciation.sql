@@ -0,0 +1,15 @@
+-- Categories say how their tech loses value. Tech without a category keeps its
+-- purchase price as its book value.
+CREATE TABLE tech_category (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    method VARCHAR(32) NOT NULL,
+    useful_life_years INTEGER NOT NULL,
+    CONSTRAINT tech_category_name_key UNIQUE (name),
+    CONSTRAINT tech_category_method_check CHECK (method IN ('straight_line', 'declining_balance')),
+    CONSTRAINT tech_category_useful_life_years_check CHECK (useful_life_years > 0)
+);
+
+ALTER TABLE tech ADD COLUMN category_id INTEGER;
+ALTER TABLE tech ADD CONSTRAINT tech_category_id_fkey
+    FOREIGN KEY (category_id) REFERENCES tech_category (id);
@@ -0,0 +1,9 @@
+-- Prices come from invoices with kopecks, so they are kept exactly, each in its own
+-- currency. Everything bought so far was paid in roubles.
+ALTER TABLE tech ALTER COLUMN price TYPE NUMERIC(14, 2);
+ALTER TABLE tech ADD COLUMN currency CHAR(3) NOT NULL DEFAULT 'RUB';
+ALTER TABLE tech ALTER COLUMN currency DROP DEFAULT;
+ALTER TABLE tech ADD CONSTRAINT tech_currency_check CHECK (currency ~ '^[A-Z]{3}$');
+
+-- The residual value is in the currency of the tech it belongs to.
+ALTER TABLE write_off ALTER COLUMN residual_value TYPE NUMERIC(14, 2);
@@ -0,0 +1,34 @@
+-- A stocktaking session for one unit or one room. Its items are copied from the
+-- holdings when it is opened, so the report keeps what the books said that day.
+CREATE TABLE audit (
+    id SERIAL PRIMARY KEY,
+    unit_id INTEGER,
+    room_id INTEGER,
+    audit_date DATE NOT NULL,
+    opened_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    closed_at TIMESTAMPTZ,
+    CONSTRAINT audit_unit_id_fkey FOREIGN KEY (unit_id) REFERENCES unit (id),
+    CONSTRAINT audit_room_id_fkey FOREIGN KEY (room_id) REFERENCES room (id),
+    CONSTRAINT audit_scope_check CHECK ((unit_id IS NULL) <> (room_id IS NULL))
+);
+
+-- Items the books expected are listed up front; items found that the books place
+-- somewhere else, or do not know at all, are added with expected = false.
+CREATE TABLE audit_item (
+    id SERIAL PRIMARY KEY,
+    audit_id INTEGER NOT NULL,
+    inventory_number INTEGER NOT NULL,
+    tech_id INTEGER,
+    name VARCHAR(255) NOT NULL,
+    model VARCHAR(255) NOT NULL,
+    expected BOOLEAN NOT NULL,
+    book_room_number INTEGER,
+    book_holder VARCHAR(255) NOT NULL,
+    finding VARCHAR(16) NOT NULL,
+    location TEXT NOT NULL,
+    CONSTRAINT audit_item_audit_id_fkey FOREIGN KEY (audit_id) REFERENCES audit (id),
+    CONSTRAINT audit_item_tech_id_fkey FOREIGN KEY (tech_id) REFERENCES tech (id) ON DELETE SET NULL,
+    CONSTRAINT audit_item_inventory_number_key UNIQUE (audit_id, inventory_number),
+    CONSTRAINT audit_item_finding_check
+        CHECK (finding IN ('pending', 'found', 'missing', 'found_elsewhere'))
+);
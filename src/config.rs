@@ -0,0 +1,471 @@
+//! Startup settings. Every setting can come from a default, the config file, an
+//! environment variable or a command line flag, the later ones winning.
+use crate::logger::Level;
+use crate::model::money::Locale;
+use postgres::config::SslMode;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+const DEFAULT_CONFIG_FILE: &str = "pbz.toml";
+
+pub const USAGE: &str = r#"Usage: pbz [OPTIONS]
+
+Options:
+    --config <path>        Config file (default: pbz.toml when present, env PBZ_CONFIG)
+    --db-host <host>       Database host (env POSTGRES_HOST, default: db)
+    --db-port <port>       Database port (env POSTGRES_PORT, default: 5432)
+    --db-user <user>       Database user (env POSTGRES_USER)
+    --db-password <pass>   Database password (env POSTGRES_PASSWORD)
+    --db-name <name>       Database name (env POSTGRES_DB)
+    --db-sslmode <mode>    disable, prefer or require (env POSTGRES_SSLMODE, default: disable)
+    --db-timeout <secs>    Connection timeout (env POSTGRES_CONNECT_TIMEOUT, default: 5)
+    --pool-size <n>        Database connections kept open (env POSTGRES_POOL_SIZE, default: 15)
+    --bind <addr>          Address to listen on (env BIND_ADDRESS, default: 0.0.0.0:5000)
+    --workers <n>          Request worker threads (env WORKERS, default: 8)
+    --log-level <level>    error, warn, info or debug (env LOG_LEVEL, default: info)
+    --locale <lang>        How amounts are written: en, ru or de (env LOCALE, default: ru)
+    --pdf-font <path>      TrueType font PDF documents are set in (env PDF_FONT,
+                           default: /usr/share/fonts/truetype/dejavu/DejaVuSans.ttf)
+    --in-memory            Run on a throwaway in-memory store instead of Postgres
+    --migrate-only         Apply database migrations and exit
+    --help                 Show this message"#;
+
+struct Setting {
+    key: &'static str,
+    env: &'static str,
+    flag: &'static str,
+    default: Option<&'static str>,
+}
+
+const SETTINGS: &[Setting] = &[
+    Setting {
+        key: "database.host",
+        env: "POSTGRES_HOST",
+        flag: "--db-host",
+        default: Some("db"),
+    },
+    Setting {
+        key: "database.port",
+        env: "POSTGRES_PORT",
+        flag: "--db-port",
+        default: Some("5432"),
+    },
+    Setting {
+        key: "database.user",
+        env: "POSTGRES_USER",
+        flag: "--db-user",
+        default: None,
+    },
+    Setting {
+        key: "database.password",
+        env: "POSTGRES_PASSWORD",
+        flag: "--db-password",
+        default: None,
+    },
+    Setting {
+        key: "database.name",
+        env: "POSTGRES_DB",
+        flag: "--db-name",
+        default: None,
+    },
+    Setting {
+        key: "database.sslmode",
+        env: "POSTGRES_SSLMODE",
+        flag: "--db-sslmode",
+        default: Some("disable"),
+    },
+    Setting {
+        key: "database.connect_timeout",
+        env: "POSTGRES_CONNECT_TIMEOUT",
+        flag: "--db-timeout",
+        default: Some("5"),
+    },
+    Setting {
+        key: "database.pool_size",
+        env: "POSTGRES_POOL_SIZE",
+        flag: "--pool-size",
+        default: Some("15"),
+    },
+    Setting {
+        key: "server.bind",
+        env: "BIND_ADDRESS",
+        flag: "--bind",
+        default: Some("0.0.0.0:5000"),
+    },
+    Setting {
+        key: "server.workers",
+        env: "WORKERS",
+        flag: "--workers",
+        default: Some("8"),
+    },
+    Setting {
+        key: "log.level",
+        env: "LOG_LEVEL",
+        flag: "--log-level",
+        default: Some("info"),
+    },
+    Setting {
+        key: "display.locale",
+        env: "LOCALE",
+        flag: "--locale",
+        default: Some("ru"),
+    },
+    Setting {
+        key: "documents.font",
+        env: "PDF_FONT",
+        flag: "--pdf-font",
+        default: Some("/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf"),
+    },
+];
+
+/// Where a value came from, so errors can point at the right place to fix it.
+#[derive(Debug, Clone)]
+enum Source {
+    Default,
+    File(PathBuf),
+    Env(&'static str),
+    Flag(&'static str),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "the default"),
+            Source::File(path) => write!(f, "config file {}", path.display()),
+            Source::Env(name) => write!(f, "environment variable {}", name),
+            Source::Flag(flag) => write!(f, "flag {}", flag),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Missing {
+        key: &'static str,
+        env: &'static str,
+        flag: &'static str,
+    },
+    Invalid {
+        key: &'static str,
+        value: String,
+        source: String,
+        expected: &'static str,
+    },
+    File {
+        path: PathBuf,
+        message: String,
+    },
+    Flag(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Missing { key, env, flag } => write!(
+                f,
+                "{} is not set: set {}, pass {} or add it to the config file",
+                key, env, flag
+            ),
+            ConfigError::Invalid {
+                key,
+                value,
+                source,
+                expected,
+            } => write!(
+                f,
+                "Invalid value \"{}\" for {} (from {}): expected {}",
+                value, key, source, expected
+            ),
+            ConfigError::File { path, message } => {
+                write!(f, "Config file {}: {}", path.display(), message)
+            }
+            ConfigError::Flag(message) => write!(f, "{}\n\n{}", message, USAGE),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[derive(Debug)]
+pub struct DatabaseConfig {
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+    pub password: String,
+    pub name: String,
+    pub ssl_mode: SslMode,
+    pub connect_timeout: Duration,
+    pub pool_size: u32,
+}
+
+#[derive(Debug)]
+pub struct ServerConfig {
+    pub bind_address: String,
+    pub workers: usize,
+}
+
+#[derive(Debug)]
+pub struct Config {
+    /// Left empty when running in memory, so no database settings are required.
+    pub database: Option<DatabaseConfig>,
+    pub server: ServerConfig,
+    pub log_level: Level,
+    pub locale: Locale,
+    pub pdf_font: PathBuf,
+    pub migrate_only: bool,
+    pub help: bool,
+}
+
+struct Values {
+    values: HashMap<&'static str, (String, Source)>,
+}
+
+impl Values {
+    fn set(&mut self, key: &'static str, value: &str, source: Source) {
+        self.values.insert(key, (value.to_string(), source));
+    }
+
+    fn text(&self, key: &'static str) -> Result<String, ConfigError> {
+        self.values
+            .get(key)
+            .map(|(value, _)| value.clone())
+            .ok_or_else(|| missing(key))
+    }
+
+    fn parse<T>(&self, key: &'static str, expected: &'static str) -> Result<T, ConfigError>
+    where
+        T: FromStr,
+    {
+        let (value, source) = self.values.get(key).ok_or_else(|| missing(key))?;
+        value.parse().map_err(|_| ConfigError::Invalid {
+            key,
+            value: value.clone(),
+            source: source.to_string(),
+            expected,
+        })
+    }
+
+    fn positive<T>(&self, key: &'static str, expected: &'static str) -> Result<T, ConfigError>
+    where
+        T: FromStr + Default + PartialEq,
+    {
+        let value: T = self.parse(key, expected)?;
+        if value == T::default() {
+            let (raw, source) = &self.values[key];
+            return Err(ConfigError::Invalid {
+                key,
+                value: raw.clone(),
+                source: source.to_string(),
+                expected,
+            });
+        }
+        Ok(value)
+    }
+
+    fn ssl_mode(&self, key: &'static str) -> Result<SslMode, ConfigError> {
+        let (value, source) = &self.values[key];
+        match value.to_lowercase().as_str() {
+            "disable" => Ok(SslMode::Disable),
+            "prefer" => Ok(SslMode::Prefer),
+            "require" => Ok(SslMode::Require),
+            _ => Err(ConfigError::Invalid {
+                key,
+                value: value.clone(),
+                source: source.to_string(),
+                expected: "one of disable, prefer or require",
+            }),
+        }
+    }
+}
+
+fn missing(key: &str) -> ConfigError {
+    let setting = SETTINGS
+        .iter()
+        .find(|setting| setting.key == key)
+        .expect("Every config key has a setting");
+    ConfigError::Missing {
+        key: setting.key,
+        env: setting.env,
+        flag: setting.flag,
+    }
+}
+
+impl Config {
+    /// Builds the config from `args` (without the program name) and the process environment.
+    pub fn load(args: &[String]) -> Result<Config, ConfigError> {
+        let mut values = Values {
+            values: HashMap::new(),
+        };
+        for setting in SETTINGS {
+            if let Some(default) = setting.default {
+                values.set(setting.key, default, Source::Default);
+            }
+        }
+
+        let mut flags: Vec<(&'static Setting, &str)> = vec![];
+        let mut config_path = std::env::var("PBZ_CONFIG").ok().map(PathBuf::from);
+        let mut in_memory = false;
+        let mut migrate_only = false;
+        let mut help = false;
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (arg.as_str(), None),
+            };
+            match name {
+                "--in-memory" => in_memory = true,
+                "--migrate-only" => migrate_only = true,
+                "--help" | "-h" => help = true,
+                _ => {
+                    let mut value = || match inline_value {
+                        Some(value) => Ok(value),
+                        None => args
+                            .next()
+                            .map(|x| x.as_str())
+                            .ok_or_else(|| ConfigError::Flag(format!("{} needs a value", name))),
+                    };
+                    if name == "--config" {
+                        config_path = Some(PathBuf::from(value()?));
+                    } else if let Some(setting) = SETTINGS.iter().find(|x| x.flag == name) {
+                        flags.push((setting, value()?));
+                    } else {
+                        return Err(ConfigError::Flag(format!("Unknown option {}", arg)));
+                    }
+                }
+            }
+        }
+        if in_memory && migrate_only && !help {
+            return Err(ConfigError::Flag(
+                "--migrate-only migrates the database and cannot be combined with --in-memory"
+                    .to_string(),
+            ));
+        }
+
+        let config_path = match config_path {
+            Some(path) => Some(path),
+            None => Some(PathBuf::from(DEFAULT_CONFIG_FILE)).filter(|path| path.exists()),
+        };
+        if let Some(path) = config_path {
+            for (key, value) in read_file(&path)? {
+                values.set(key, &value, Source::File(path.clone()));
+            }
+        }
+        for setting in SETTINGS {
+            if let Ok(value) = std::env::var(setting.env) {
+                values.set(setting.key, &value, Source::Env(setting.env));
+            }
+        }
+        for (setting, value) in flags {
+            values.set(setting.key, value, Source::Flag(setting.flag));
+        }
+
+        let database = if in_memory || help {
+            None
+        } else {
+            Some(DatabaseConfig {
+                host: values.text("database.host")?,
+                port: values.positive("database.port", "a port number")?,
+                user: values.text("database.user")?,
+                password: values.text("database.password")?,
+                name: values.text("database.name")?,
+                ssl_mode: values.ssl_mode("database.sslmode")?,
+                connect_timeout: Duration::from_secs(
+                    values.positive("database.connect_timeout", "a number of seconds")?,
+                ),
+                pool_size: values.positive("database.pool_size", "a positive number")?,
+            })
+        };
+
+        Ok(Config {
+            database,
+            server: ServerConfig {
+                bind_address: values.text("server.bind")?,
+                workers: values.positive("server.workers", "a positive number")?,
+            },
+            log_level: values.parse("log.level", "one of error, warn, info or debug")?,
+            locale: values.parse("display.locale", "one of en, ru or de")?,
+            pdf_font: PathBuf::from(values.text("documents.font")?),
+            migrate_only,
+            help,
+        })
+    }
+}
+
+/// Reads the small part of TOML the config needs: `[section]` headers and
+/// `key = value` lines holding strings, integers or booleans.
+fn read_file(path: &Path) -> Result<Vec<(&'static str, String)>, ConfigError> {
+    let file_error = |message: String| ConfigError::File {
+        path: path.to_path_buf(),
+        message,
+    };
+    let contents = std::fs::read_to_string(path).map_err(|e| file_error(e.to_string()))?;
+
+    let mut section = String::new();
+    let mut values = vec![];
+    for (number, line) in contents.lines().enumerate() {
+        let line_error = |message: &str| file_error(format!("line {}: {}", number + 1, message));
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[') {
+            let name = strip_comment(name)
+                .strip_suffix(']')
+                .ok_or_else(|| line_error("unclosed section header"))?;
+            section = name.trim().to_string();
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| line_error("expected key = value"))?;
+        let key = format!("{}.{}", section, key.trim());
+        let setting = SETTINGS
+            .iter()
+            .find(|x| x.key == key)
+            .ok_or_else(|| line_error(&format!("unknown setting {}", key)))?;
+        let value = parse_value(value.trim()).ok_or_else(|| line_error("invalid value"))?;
+        values.push((setting.key, value));
+    }
+    Ok(values)
+}
+
+fn strip_comment(text: &str) -> &str {
+    match text.find('#') {
+        Some(index) => text[..index].trim(),
+        None => text.trim(),
+    }
+}
+
+fn parse_value(text: &str) -> Option<String> {
+    let Some(quoted) = text.strip_prefix('"') else {
+        return Some(strip_comment(text).to_string()).filter(|x| !x.is_empty());
+    };
+
+    let mut value = String::new();
+    let mut chars = quoted.chars();
+    loop {
+        match chars.next()? {
+            '"' => break,
+            '\\' => match chars.next()? {
+                '"' => value.push('"'),
+                '\\' => value.push('\\'),
+                'n' => value.push('\n'),
+                't' => value.push('\t'),
+                _ => return None,
+            },
+            c => value.push(c),
+        }
+    }
+    let rest = chars.as_str().trim();
+    if rest.is_empty() || rest.starts_with('#') {
+        Some(value)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,231 @@
+use super::export::{sheets, Export};
+use super::form::FormData;
+use super::pages::*;
+use super::request::{Method, Request};
+use super::Response;
+use crate::model::context::AppContext;
+use crate::model::dao::audit::{AuditItem, AuditScope, Finding};
+use crate::model::error::AppError;
+use crate::model::stocktaking;
+use chrono::Local;
+
+/// The list of audits above the forms that open new ones.
+fn list_page(ctx: &AppContext, message: Message) -> Response {
+    let audit_dao = &ctx.audit;
+    let audits = match audit_dao.get_all() {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+    let unit_dao = &ctx.unit;
+    let units = match unit_dao.get_all() {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+    let room_dao = &ctx.room;
+    let rooms = match room_dao.get_all() {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+    Response::ok(audit_pages::list_page(audits, units, rooms, message))
+}
+
+/// The audit page; `draft` replaces the stored items after a rejected submit.
+fn audit_page(
+    ctx: &AppContext,
+    id: i32,
+    draft: Option<Vec<AuditItem>>,
+    message: Message,
+) -> Response {
+    let audit_dao = &ctx.audit;
+    let summary = match audit_dao.get_id(id) {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+    let items = match draft {
+        Some(x) => x,
+        None => match audit_dao.get_items(id) {
+            Ok(x) => x,
+            Err(e) => return e.into(),
+        },
+    };
+    Response::ok(audit_pages::audit_page(&summary, &items, message))
+}
+
+/// Mistakes the auditor can fix are shown on the page they came from.
+fn correctable(e: &AppError) -> bool {
+    matches!(e, AppError::Validation(_) | AppError::Conflict(_))
+}
+
+pub fn handle_audit_list(req: &Request, ctx: &AppContext) -> Response {
+    let export = match Export::requested(req) {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+    let Some(export) = export else {
+        return list_page(ctx, Message::none());
+    };
+
+    let audit_dao = &ctx.audit;
+    match audit_dao.get_all() {
+        Ok(audits) => export.respond(&sheets::audits(&audits)),
+        Err(e) => e.into(),
+    }
+}
+
+fn scope_from_form(body: &FormData) -> Result<AuditScope, AppError> {
+    match (body.optional("unit")?, body.optional("room")?) {
+        (Some(unit_id), None) => Ok(AuditScope::Unit(unit_id)),
+        (None, Some(room_id)) => Ok(AuditScope::Room(room_id)),
+        _ => Err(AppError::validation(
+            "Choose either a unit or a room to audit",
+        )),
+    }
+}
+
+pub fn handle_audit_open(req: &Request, ctx: &AppContext) -> Response {
+    let opened = scope_from_form(&req.body)
+        .and_then(|scope| stocktaking::open_audit(ctx, scope, Local::now().date_naive()));
+    match opened {
+        Ok(audit) => {
+            info!("Opened audit {} of {:?}", audit.id(), audit.scope);
+            Response::found(&format!("/audit/{}", audit.id()))
+        }
+        Err(e) if correctable(&e) => list_page(ctx, Message::new(&e.to_string(), MK::Error)),
+        Err(e) => e.into(),
+    }
+}
+
+/// The items with the findings submitted for them.
+fn findings_from_form(
+    mut items: Vec<AuditItem>,
+    body: &FormData,
+) -> Result<Vec<AuditItem>, AppError> {
+    for item in items.iter_mut().filter(|x| x.expected) {
+        if let Some(value) = body.get(&format!("finding-{}", item.id())) {
+            item.finding = Finding::parse(value)?;
+        }
+        if let Some(location) = body.get(&format!("location-{}", item.id())) {
+            item.location = location.to_string();
+        }
+    }
+    Ok(items)
+}
+
+pub fn handle_audit(req: &Request, ctx: &AppContext) -> Response {
+    let arg = match req.param::<i32>("id") {
+        Some(x) => x,
+        None => return Response::not_found(),
+    };
+
+    match req.method {
+        Method::Get => {
+            let export = match Export::requested(req) {
+                Ok(x) => x,
+                Err(e) => return e.into(),
+            };
+            let Some(export) = export else {
+                return audit_page(ctx, arg, None, Message::none());
+            };
+
+            let audit_dao = &ctx.audit;
+            let summary = match audit_dao.get_id(arg) {
+                Ok(x) => x,
+                Err(e) => return e.into(),
+            };
+            match audit_dao.get_items(arg) {
+                Ok(items) => export.respond(&sheets::audit(&summary, &items)),
+                Err(e) => e.into(),
+            }
+        }
+        Method::Post => {
+            let audit_dao = &ctx.audit;
+            let items = match audit_dao.get_items(arg) {
+                Ok(x) => x,
+                Err(e) => return e.into(),
+            };
+            let items = match findings_from_form(items, &req.body) {
+                Ok(x) => x,
+                Err(e) => return e.into(),
+            };
+
+            match stocktaking::save_findings(ctx, arg, &items) {
+                Ok(()) => audit_page(ctx, arg, None, Message::new("Saved", MK::Notify)),
+                Err(e) if correctable(&e) => audit_page(
+                    ctx,
+                    arg,
+                    Some(items),
+                    Message::new(&e.to_string(), MK::Error),
+                ),
+                Err(e) => e.into(),
+            }
+        }
+        _ => Response::method_not_allowed(),
+    }
+}
+
+pub fn handle_audit_unexpected(req: &Request, ctx: &AppContext) -> Response {
+    let arg = match req.param::<i32>("id") {
+        Some(x) => x,
+        None => return Response::not_found(),
+    };
+
+    let added = req
+        .body
+        .field("inventory_number")
+        .and_then(|inventory_number| {
+            stocktaking::add_unexpected(
+                ctx,
+                arg,
+                inventory_number,
+                req.body.get("location").unwrap_or("").trim(),
+            )
+        });
+    match added {
+        Ok(item) => {
+            info!(
+                "Added unexpected item {} to audit {}",
+                item.inventory_number, arg
+            );
+            Response::found(&format!("/audit/{}", arg))
+        }
+        Err(e) if correctable(&e) => {
+            audit_page(ctx, arg, None, Message::new(&e.to_string(), MK::Error))
+        }
+        Err(e) => e.into(),
+    }
+}
+
+pub fn handle_audit_item_remove(req: &Request, ctx: &AppContext) -> Response {
+    let (arg, item_id) = match (req.param::<i32>("id"), req.param::<i32>("item")) {
+        (Some(x), Some(y)) => (x, y),
+        _ => return Response::not_found(),
+    };
+
+    let audit_dao = &ctx.audit;
+    match audit_dao.remove_item(arg, item_id) {
+        Ok(()) => Response::found(&format!("/audit/{}", arg)),
+        Err(e) if correctable(&e) => {
+            audit_page(ctx, arg, None, Message::new(&e.to_string(), MK::Error))
+        }
+        Err(e) => e.into(),
+    }
+}
+
+pub fn handle_audit_close(req: &Request, ctx: &AppContext) -> Response {
+    let arg = match req.param::<i32>("id") {
+        Some(x) => x,
+        None => return Response::not_found(),
+    };
+
+    let audit_dao = &ctx.audit;
+    match audit_dao.close(arg) {
+        Ok(()) => {
+            info!("Closed audit {}", arg);
+            Response::found(&format!("/audit/{}", arg))
+        }
+        Err(e) if correctable(&e) => {
+            audit_page(ctx, arg, None, Message::new(&e.to_string(), MK::Error))
+        }
+        Err(e) => e.into(),
+    }
+}
@@ -0,0 +1,124 @@
+use super::export::{sheets, Export};
+use super::form::FormData;
+use super::pages::*;
+use super::request::{Method, Request};
+use super::Response;
+use crate::model::context::AppContext;
+use crate::model::dao::category::{DepreciationMethod, TechCategory};
+use crate::model::error::AppError;
+
+pub fn handle_category_table(req: &Request, ctx: &AppContext) -> Response {
+    let export = match Export::requested(req) {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+    let category_dao = &ctx.category;
+    match category_dao.get_all() {
+        Ok(categories) => match export {
+            Some(export) => export.respond(&sheets::categories(&categories)),
+            None => Response::ok(category_pages::table_page(categories)),
+        },
+        Err(e) => e.into(),
+    }
+}
+
+fn category_from_form(body: &FormData) -> Result<TechCategory, AppError> {
+    let name = body.text("name")?;
+    if name.is_empty() {
+        return Err(AppError::validation("Enter the category name"));
+    }
+    let useful_life_years: i32 = body.field("useful_life_years")?;
+    if useful_life_years <= 0 {
+        return Err(AppError::validation(
+            "Useful life has to be a positive number of years",
+        ));
+    }
+    Ok(TechCategory::new(
+        name,
+        DepreciationMethod::parse(body.text("method")?)?,
+        useful_life_years,
+    ))
+}
+
+fn save_error(action: &str, e: AppError) -> Message {
+    match &e {
+        AppError::Sql {
+            constraint: Some(constraint),
+            ..
+        } if constraint == "tech_category_name_key" => {
+            Message::new("A category with this name already exists", MK::Error)
+        }
+        _ => Message::new(&format!("Failed to {} category: {}", action, e), MK::Error),
+    }
+}
+
+pub fn handle_category_add(req: &Request, ctx: &AppContext) -> Response {
+    match req.method {
+        Method::Get => Response::ok(category_pages::add_form_get()),
+        Method::Post => {
+            let mut category = match category_from_form(&req.body) {
+                Ok(x) => x,
+                Err(e) => {
+                    let message = Message::new(&e.to_string(), MK::Error);
+                    return Response::ok(category_pages::add_form_post(message));
+                }
+            };
+
+            let category_dao = &ctx.category;
+            let message = match category_dao.insert(&mut category) {
+                Ok(()) => Message::new("Added category", MK::Notify),
+                Err(e) => save_error("add", e),
+            };
+
+            Response::ok(category_pages::add_form_post(message))
+        }
+        _ => Response::method_not_allowed(),
+    }
+}
+
+pub fn handle_category_edit(req: &Request, ctx: &AppContext) -> Response {
+    let arg = match req.param::<i32>("id") {
+        Some(x) => x,
+        None => return Response::not_found(),
+    };
+
+    let category_dao = &ctx.category;
+    let category = match category_dao.get_id(arg) {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+
+    match req.method {
+        Method::Get => Response::ok(category_pages::edit_form_get(&category)),
+        Method::Post => {
+            let mut category = match category_from_form(&req.body) {
+                Ok(x) => x,
+                Err(e) => {
+                    let message = Message::new(&e.to_string(), MK::Error);
+                    return Response::ok(category_pages::edit_form_post(&category, message));
+                }
+            };
+
+            category.set_id(arg);
+            let message = match category_dao.update(&category) {
+                Ok(()) => Message::new("Updated category", MK::Success),
+                Err(e) => save_error("update", e),
+            };
+            Response::ok(category_pages::edit_form_post(&category, message))
+        }
+        _ => Response::method_not_allowed(),
+    }
+}
+
+pub fn handle_category_delete(req: &Request, ctx: &AppContext) -> Response {
+    let arg = match req.param::<i32>("id") {
+        Some(x) => x,
+        None => return Response::not_found(),
+    };
+
+    let category_dao = &ctx.category;
+    match category_dao.delete(arg) {
+        Ok(()) => Response::found("/category"),
+        Err(e) => e.into(),
+    }
+}
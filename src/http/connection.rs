@@ -1,31 +1,35 @@
-use super::{controller, employee_controller, tech_controller, unit_controller, Response};
-use crate::model::pool::PostgrePool;
-use std::collections::HashMap;
-use std::rc::Rc;
+use super::request::Request;
+use super::router::Router;
+use super::workers::WorkerPool;
+use super::{
+    audit_controller, category_controller, controller, employee_controller, import_controller,
+    room_controller, static_controller, tech_controller, unit_controller, write_off_controller,
+    Response,
+};
+use crate::config::ServerConfig;
+use crate::model::context::AppContext;
+use std::sync::Arc;
 use std::{
     io::{prelude::*, BufReader},
     net::{TcpListener, TcpStream},
 };
 
-fn form_response(status_line: &str, contents: &str) -> String {
-    format!("{}\r\nAccess-Control-Allow-Origin: https://my-cool-site.com\r\nAccess-Control-Allow-Methods: GET, POST, OPTIONS\r\nContent-Length: {}\r\n\r\n{}", status_line, contents.len(), contents)
-}
+/// The largest request body read, CSV uploads included; bigger ones get a 413.
+const MAX_BODY_SIZE: u32 = 10 * 1024 * 1024;
 
-fn parse_form_data(data: &str) -> HashMap<String, String> {
-    data.split('&')
-        .filter_map(|pair| {
-            let mut split = pair.splitn(2, '=');
-            Some((
-                split.next()?.to_string(),
-                split
-                    .next()
-                    .unwrap_or("")
-                    .replace('+', " ")
-                    .trim()
-                    .to_string(),
-            ))
-        })
-        .collect()
+fn form_response(
+    status_line: &str,
+    content_type: &str,
+    headers: &[(String, String)],
+    contents: &[u8],
+) -> Vec<u8> {
+    let headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}: {}\r\n", name, value))
+        .collect();
+    let mut response = format!("{}\r\nAccess-Control-Allow-Origin: https://my-cool-site.com\r\nAccess-Control-Allow-Methods: GET, POST, OPTIONS\r\nContent-Type: {}\r\n{}Content-Length: {}\r\n\r\n", status_line, content_type, headers, contents.len()).into_bytes();
+    response.extend_from_slice(contents);
+    response
 }
 
 fn get_headers(
@@ -37,7 +41,7 @@ fn get_headers(
         let mut line = String::new();
         match buf_reader.read_line(&mut line) {
             Ok(0) => {
-                eprintln!("Connection closed by client.");
+                debug!("Connection closed by client");
                 return None;
             }
             Ok(_) => {
@@ -50,49 +54,159 @@ fn get_headers(
                 request.push_str(&line);
             }
             Err(e) => {
-                eprintln!("Error reading request: {}", e);
+                warn!("Error reading request: {}", e);
                 return None;
             }
         }
     }
-    let request = request.replace('+', " ");
-    println!("{}", request);
+    debug!("Received request:\n{}", request.trim_end());
     Some(request)
 }
 
-fn get_body(
-    buf_reader: &mut BufReader<&mut TcpStream>,
-    method: &str,
-    content_length: u32,
-) -> Option<HashMap<String, String>> {
-    let mut body = String::new();
-    if method != "POST" || content_length == 0 {
-        return None;
+fn get_body(buf_reader: &mut BufReader<&mut TcpStream>, content_length: u32) -> Vec<u8> {
+    let mut body = vec![];
+    if content_length == 0 {
+        return body;
     }
-    buf_reader
+    if let Err(e) = buf_reader
         .take(content_length as u64)
-        .read_to_string(&mut body)
-        .unwrap();
-    // println!("Received body:\n{}", body);
-    let form_data = parse_form_data(&body);
-    // println!("Parsed form data: {:?}", form_data);
-    return Some(form_data);
-}
-
-fn get_route(request: &str) -> (&str, &str) {
-    let mut lines = request.lines();
-    let request_line = lines.next().expect("Expected request line");
-    let mut split_request = request_line.split_whitespace();
-    let method = split_request.next().expect("Expected method");
-    let route = split_request.next().expect("Expected route");
-    (method, route)
+        .read_to_end(&mut body)
+    {
+        warn!("Error reading body: {}", e);
+    }
+    // println!("Received body:\n{}", String::from_utf8_lossy(&body));
+    body
 }
 
-fn opt_route(route: &str, pattern: &str) -> bool {
-    route.starts_with(pattern)
+fn build_router() -> Router {
+    Router::new()
+        .get("/", |_, _| controller::handle_welcome())
+        .get("/static/{*path}", static_controller::handle_static)
+        .get("/tech", tech_controller::handle_tech_table)
+        .get("/tech/{id}", tech_controller::handle_tech_detail)
+        .get("/add-tech", tech_controller::handle_tech_add)
+        .post("/add-tech", tech_controller::handle_tech_add)
+        .get("/edit-tech/{id}", tech_controller::handle_tech_edit)
+        .post("/edit-tech/{id}", tech_controller::handle_tech_edit)
+        .get("/delete-tech/{id}", tech_controller::handle_tech_delete)
+        .post("/delete-tech/{id}", tech_controller::handle_tech_delete)
+        .post("/archive-tech/{id}", tech_controller::handle_tech_archive)
+        .post("/restore-tech/{id}", tech_controller::handle_tech_restore)
+        .get("/holdings", tech_controller::handle_holdings)
+        .get(
+            "/write-offs",
+            write_off_controller::handle_write_off_register,
+        )
+        .get(
+            "/write-off-tech/{id}",
+            write_off_controller::handle_tech_write_off,
+        )
+        .post(
+            "/write-off-tech/{id}",
+            write_off_controller::handle_tech_write_off,
+        )
+        .get("/audits", audit_controller::handle_audit_list)
+        .post("/open-audit", audit_controller::handle_audit_open)
+        .get("/audit/{id}", audit_controller::handle_audit)
+        .post("/audit/{id}", audit_controller::handle_audit)
+        .post(
+            "/audit-unexpected/{id}",
+            audit_controller::handle_audit_unexpected,
+        )
+        .post(
+            "/remove-audit-item/{id}/{item}",
+            audit_controller::handle_audit_item_remove,
+        )
+        .post("/close-audit/{id}", audit_controller::handle_audit_close)
+        .get("/unit", unit_controller::handle_unit_table)
+        .get("/add-unit", unit_controller::handle_unit_add)
+        .post("/add-unit", unit_controller::handle_unit_add)
+        .get("/edit-unit/{id}", unit_controller::handle_unit_edit)
+        .post("/edit-unit/{id}", unit_controller::handle_unit_edit)
+        .get("/delete-unit/{id}", unit_controller::handle_unit_delete)
+        .post("/delete-unit/{id}", unit_controller::handle_unit_delete)
+        .post("/archive-unit/{id}", unit_controller::handle_unit_archive)
+        .post("/restore-unit/{id}", unit_controller::handle_unit_restore)
+        .get("/unit-tech", unit_controller::handle_unit_tech)
+        .get("/book-value", unit_controller::handle_book_value)
+        .get(
+            "/view-unit-tech/{id}",
+            unit_controller::handle_view_unit_tech,
+        )
+        .get("/employee", employee_controller::handle_employee_table)
+        .get("/add-employee", employee_controller::handle_employee_add)
+        .post("/add-employee", employee_controller::handle_employee_add)
+        .get(
+            "/edit-employee/{id}",
+            employee_controller::handle_employee_edit,
+        )
+        .post(
+            "/edit-employee/{id}",
+            employee_controller::handle_employee_edit,
+        )
+        .get(
+            "/delete-employee/{id}",
+            employee_controller::handle_employee_delete,
+        )
+        .post(
+            "/delete-employee/{id}",
+            employee_controller::handle_employee_delete,
+        )
+        .post(
+            "/archive-employee/{id}",
+            employee_controller::handle_employee_archive,
+        )
+        .post(
+            "/restore-employee/{id}",
+            employee_controller::handle_employee_restore,
+        )
+        .get("/employee-tech", employee_controller::handle_employee_tech)
+        .get(
+            "/view-employee-tech/{id}",
+            employee_controller::handle_view_employee_tech,
+        )
+        .get("/room", room_controller::handle_room_table)
+        .get("/add-room", room_controller::handle_room_add)
+        .post("/add-room", room_controller::handle_room_add)
+        .get("/edit-room/{id}", room_controller::handle_room_edit)
+        .post("/edit-room/{id}", room_controller::handle_room_edit)
+        .post("/delete-room/{id}", room_controller::handle_room_delete)
+        .get("/category", category_controller::handle_category_table)
+        .get("/add-category", category_controller::handle_category_add)
+        .post("/add-category", category_controller::handle_category_add)
+        .get(
+            "/edit-category/{id}",
+            category_controller::handle_category_edit,
+        )
+        .post(
+            "/edit-category/{id}",
+            category_controller::handle_category_edit,
+        )
+        .post(
+            "/delete-category/{id}",
+            category_controller::handle_category_delete,
+        )
+        .get("/transfer", controller::handle_transfer_table)
+        .get("/add-transfer", controller::handle_transfer_add)
+        .post("/add-transfer", controller::handle_transfer_add)
+        .get("/edit-transfer/{id}", controller::handle_transfer_edit)
+        .post("/edit-transfer/{id}", controller::handle_transfer_edit)
+        .get("/upcoming", controller::handle_transfer_upcoming)
+        .post("/cancel-transfer/{id}", controller::handle_transfer_cancel)
+        .post(
+            "/reschedule-transfer/{id}",
+            controller::handle_transfer_reschedule,
+        )
+        .get("/transfer/{id}/act", controller::handle_transfer_act)
+        .get(
+            "/transfer/{id}/act.pdf",
+            controller::handle_transfer_act_pdf,
+        )
+        .get("/import", import_controller::handle_import)
+        .post("/import", import_controller::handle_import)
 }
 
-fn handle_connection(mut stream: TcpStream, pool: Rc<PostgrePool>) {
+fn handle_connection(mut stream: TcpStream, router: &Router, ctx: &AppContext) {
     let mut buf_reader = BufReader::new(&mut stream);
     let mut content_length = 0;
 
@@ -102,65 +216,58 @@ fn handle_connection(mut stream: TcpStream, pool: Rc<PostgrePool>) {
     };
 
     // println!("Received headers:\n{}", request);
-    let (method, route) = get_route(&request);
-
-    let body = get_body(&mut buf_reader, method, content_length);
+    if content_length > MAX_BODY_SIZE {
+        warn!("Refusing a request body of {} bytes", content_length);
+        let reason = format!(
+            "The request is larger than {} MB",
+            MAX_BODY_SIZE / 1024 / 1024
+        );
+        return send_response(&mut stream, Response::payload_too_large(&reason));
+    }
+    let body = get_body(&mut buf_reader, content_length);
 
-    let response = match route {
-        "/" => controller::handle_welcome(method),
-        "/tech" => tech_controller::handle_tech_table(method, pool),
-        "/add-tech" => tech_controller::handle_tech_add(method, body, pool),
-        "/unit" => unit_controller::handle_unit_table(method, pool),
-        "/add-unit" => unit_controller::handle_unit_add(method, body, pool),
-        "/employee" => employee_controller::handle_employee_table(method, pool),
-        "/add-employee" => employee_controller::handle_employee_add(method, body, pool),
-        "/transfer" => controller::handle_transfer_table(method, pool),
-        "/add-transfer" => controller::handle_transfer_add(method, body, pool),
-        "/unit-tech" => unit_controller::handle_unit_tech(method, pool),
-        "/employee-tech" => employee_controller::handle_employee_tech(method, pool),
-        _ if opt_route(route, "/edit-tech/") => {
-            tech_controller::handle_tech_edit(method, route, body, pool)
-        }
-        _ if opt_route(route, "/delete-tech/") => {
-            tech_controller::handle_tech_delete(method, route, pool)
-        }
-        _ if opt_route(route, "/edit-unit/") => {
-            unit_controller::handle_unit_edit(method, route, body, pool)
-        }
-        _ if opt_route(route, "/delete-unit/") => {
-            unit_controller::handle_unit_delete(method, route, pool)
-        }
-        _ if opt_route(route, "/edit-employee/") => {
-            employee_controller::handle_employee_edit(method, route, body, pool)
-        }
-        _ if opt_route(route, "/delete-employee/") => {
-            employee_controller::handle_employee_delete(method, route, pool)
-        }
-        _ if opt_route(route, "/edit-transfer/") => {
-            controller::handle_transfer_edit(method, route, body, pool)
-        }
-        _ if opt_route(route, "/view-unit-tech/") => {
-            unit_controller::handle_view_unit_tech(method, route, pool)
-        }
-        _ if opt_route(route, "/view-employee-tech/") => {
-            employee_controller::handle_view_employee_tech(method, route, pool)
-        }
-        _ => {
-            println!("Not found");
-            Response::not_found()
-        }
+    let request = match Request::parse(&request, &body) {
+        Ok(x) => x,
+        Err(e) => return send_response(&mut stream, Response::bad_request(&e.to_string())),
     };
+    let response = router.dispatch(request, ctx);
 
-    let response = form_response(&response.get_status_str(), &response.page.into_string());
-    // println!("{}", response);
-    stream.write_all(response.as_bytes()).unwrap();
+    send_response(&mut stream, response);
 }
 
-pub fn open_connection(pool: Rc<PostgrePool>) {
-    let listener = TcpListener::bind("0.0.0.0:5000").unwrap();
+fn send_response(stream: &mut TcpStream, response: Response) {
+    let status = response.get_status_str();
+    let response = form_response(
+        &status,
+        &response.content_type,
+        &response.headers,
+        &response.body,
+    );
+    if let Err(e) = stream.write_all(&response) {
+        warn!("Error writing response: {}", e);
+    }
+}
+
+pub fn open_connection(ctx: Arc<AppContext>, config: &ServerConfig) -> std::io::Result<()> {
+    let listener = TcpListener::bind(&config.bind_address)?;
+    let worker_pool = WorkerPool::new(config.workers, config.workers * 4);
+    let router = Arc::new(build_router());
+    info!(
+        "Listening on {} with {} workers",
+        config.bind_address, config.workers
+    );
 
     for stream in listener.incoming() {
-        let stream = stream.unwrap();
-        handle_connection(stream, pool.clone());
+        let stream = match stream {
+            Ok(x) => x,
+            Err(e) => {
+                error!("Error accepting connection: {}", e);
+                continue;
+            }
+        };
+        let ctx = ctx.clone();
+        let router = router.clone();
+        worker_pool.execute(move || handle_connection(stream, &router, &ctx));
     }
+    Ok(())
 }
@@ -1,294 +1,494 @@
+use super::export::{sheets, Export};
+use super::form::FormData;
 use super::pages::*;
-use super::{get_route_arg, Response, Status};
+use super::request::{Method, Request};
+use super::Response;
+use crate::model::acts;
+use crate::model::context::AppContext;
+use crate::model::dao::transfer::TransferUnit;
 use crate::model::dao::*;
-use crate::model::pool::PostgrePool;
-use chrono::NaiveDate;
-use std::collections::HashMap;
-use std::rc::Rc;
-
-pub fn handle_welcome(method: &str) -> Response {
-    if method != "GET" {
-        return Response::method_not_allowed();
-    }
+use crate::model::error::AppError;
+use crate::model::validation::{FieldErrors, TransferValidator};
+use chrono::{Local, NaiveDate};
+use std::str::FromStr;
+
+pub fn handle_welcome() -> Response {
     Response::ok(welcome::page())
 }
 
-pub fn handle_transfer_table(method: &str, pool: Rc<PostgrePool>) -> Response {
-    if method != "GET" {
-        return Response::method_not_allowed();
-    }
-    let transfer_dao = transfer::TransferDAOImpl::new(pool);
+pub fn handle_transfer_table(req: &Request, ctx: &AppContext) -> Response {
+    let export = match Export::requested(req) {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+    let transfer_dao = &ctx.transfer;
 
     let detailed = match transfer_dao.get_detailed_transfers() {
         Ok(data) => data,
-        Err(e) => return Response::internal_server_error(&e.to_string()),
+        Err(e) => return e.into(),
     };
 
-    return Response::ok(transfer_pages::table_page(detailed));
+    let today = Local::now().date_naive();
+    match export {
+        Some(export) => export.respond(&sheets::transfers(&detailed, today)),
+        None => Response::ok(transfer_pages::table_page(detailed, today)),
+    }
 }
 
-pub fn handle_transfer_add(
-    method: &str,
-    body: Option<HashMap<String, String>>,
-    pool: Rc<PostgrePool>,
-) -> Response {
-    let tech_dao = tech::TechDaoImpl::new(pool.clone());
-    let unit_dao = unit::UnitDAOImpl::new(pool.clone());
-    let room_dao = room::RoomDAOImpl::new(pool.clone());
-    let tech = match tech_dao.get_all() {
+/// Reads one field of a form, recording a value that does not parse against that
+/// field.
+pub(super) fn read_field<T: FromStr>(
+    body: &FormData,
+    key: &'static str,
+    message: &str,
+    errors: &mut FieldErrors,
+) -> Option<T> {
+    match body.field(key) {
+        Ok(x) => Some(x),
+        Err(_) => {
+            errors.add(key, message);
+            None
+        }
+    }
+}
+
+/// The transfer described by the submitted form; the employee is only known once
+/// the unit has been chosen.
+fn transfer_from_form(body: &FormData, errors: &mut FieldErrors) -> Option<TransferUnit> {
+    let date = read_field(body, "date", "Not valid date value", errors);
+    let tech_id = read_field(body, "tech", "Invalid tech value", errors);
+    let unit_id = read_field(body, "unit", "Invalid unit value", errors);
+    let room_id = read_field(body, "room", "Invalid room value", errors);
+    let employee_id = match body.contains_key("unit_selected") {
+        true => read_field(body, "employee", "Invalid employee value", errors),
+        false => Some(0),
+    };
+
+    match (date, tech_id, unit_id, room_id, employee_id) {
+        (Some(date), Some(tech_id), Some(unit_id), Some(room_id), Some(employee_id)) => {
+            Some(TransferUnit {
+                inner: transfer::Transfer::new_date(date, tech_id, room_id, employee_id),
+                unit_id,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// The second step of the form, where an accountable employee of the unit is picked.
+fn session_for(
+    ctx: &AppContext,
+    draft: &TransferUnit,
+    errors: &mut FieldErrors,
+) -> Result<Option<Session>, AppError> {
+    let employee_dao = &ctx.employee;
+    let accountable_employees = employee_dao.get_all_accountable(draft.unit_id)?;
+    if accountable_employees.is_empty() {
+        errors.add("unit", "This unit has no accountable employees");
+        return Ok(None);
+    }
+
+    Ok(Some(Session {
+        date: draft.inner.transfer_date,
+        unit_id: draft.unit_id,
+        tech_id: draft.inner.tech_id,
+        room_id: draft.inner.room_id,
+        accountable_employees,
+    }))
+}
+
+/// Checks the complete transfer once more and saves it in the same unit of work,
+/// so nothing the rules depend on can change in between.
+fn save_transfer<F>(
+    ctx: &AppContext,
+    draft: &mut TransferUnit,
+    save: F,
+) -> Result<FieldErrors, AppError>
+where
+    F: FnOnce(&AppContext, &mut transfer::Transfer) -> Result<(), AppError>,
+{
+    ctx.with_transaction(|tx| {
+        let errors = TransferValidator::new(tx).check(draft)?;
+        if errors.is_empty() {
+            save(tx, &mut draft.inner)?;
+        }
+        Ok(errors)
+    })
+}
+
+fn invalid() -> Message {
+    Message::new("Please correct the highlighted fields", MK::Error)
+}
+
+pub fn handle_transfer_add(req: &Request, ctx: &AppContext) -> Response {
+    let tech_dao = &ctx.tech;
+    let unit_dao = &ctx.unit;
+    let room_dao = &ctx.room;
+    let tech = match tech_dao.get_in_service() {
         Ok(data) => data,
-        Err(e) => return Response::internal_server_error(&e.to_string()),
+        Err(e) => return e.into(),
     };
     let units = match unit_dao.get_all_acountable() {
         Ok(data) => data,
-        Err(e) => return Response::internal_server_error(&e.to_string()),
+        Err(e) => return e.into(),
     };
     let rooms = match room_dao.get_all() {
         Ok(data) => data,
-        Err(e) => return Response::internal_server_error(&e.to_string()),
+        Err(e) => return e.into(),
     };
-    println!("Tech: {:?}", tech);
-    println!("Units: {:?}", units);
-    println!("Room: {:?}", rooms);
+    debug!("Tech: {:?}", tech);
+    debug!("Units: {:?}", units);
+    debug!("Room: {:?}", rooms);
 
-    if method == "GET" {
-        return Response::ok(transfer_pages::add_form_get(
+    if req.method == Method::Get {
+        Response::ok(transfer_pages::add_form_get(
             tech,
             units,
             rooms,
             Message::none(),
-        ));
-    } else if method == "POST" {
-        let mut body = body.unwrap();
-        let unit_selected = body.get("unit_selected").is_some();
+        ))
+    } else if req.method == Method::Post {
+        let body = &req.body;
+        let unit_selected = body.contains_key("unit_selected");
 
-        let date: NaiveDate = match body["date"].parse() {
-            Ok(x) => x,
-            Err(_) => {
-                let message = Message::new("Not valid date value", MK::Error);
+        let mut errors = FieldErrors::new();
+        let draft = match transfer_from_form(body, &mut errors) {
+            Some(x) => x,
+            None => {
                 return Response::ok(transfer_pages::add_form_post(
-                    tech, units, rooms, None, message,
+                    tech,
+                    units,
+                    rooms,
+                    None,
+                    None,
+                    errors,
+                    invalid(),
                 ));
             }
         };
-        let tech_id: i32 = match body["tech"].parse() {
+
+        let validator = TransferValidator::new(ctx);
+        let mut errors = match validator.check_placement(&draft) {
             Ok(x) => x,
-            Err(_) => {
-                let message = Message::new("Invalid tech value", MK::Error);
-                return Response::ok(transfer_pages::add_form_post(
-                    tech, units, rooms, None, message,
-                ));
-            }
+            Err(e) => return e.into(),
         };
-        let unit_id: i32 = match body["unit"].parse() {
-            Ok(x) => x,
-            Err(_) => {
-                let message = Message::new("Invalid unit value", MK::Error);
-                return Response::ok(transfer_pages::add_form_post(
-                    tech, units, rooms, None, message,
-                ));
-            }
+        let session = match errors.is_empty() {
+            true => match session_for(ctx, &draft, &mut errors) {
+                Ok(x) => x,
+                Err(e) => return e.into(),
+            },
+            false => None,
         };
-
-        let room_id: i32 = match body["room"].parse() {
-            Ok(x) => x,
-            Err(_) => {
-                let message = Message::new("Invalid room value", MK::Error);
+        let session = match session {
+            Some(x) => x,
+            None => {
                 return Response::ok(transfer_pages::add_form_post(
-                    tech, units, rooms, None, message,
+                    tech,
+                    units,
+                    rooms,
+                    Some(draft),
+                    None,
+                    errors,
+                    invalid(),
                 ));
             }
         };
 
         if !unit_selected {
-            let employee_dao = employee::EmployeeDAOImpl::new(pool.clone());
-            let accountable_employees = match employee_dao.get_all_accountable(unit_id) {
-                Ok(x) => x,
-                Err(e) => return Response::internal_server_error(&e.to_string()),
-            };
-
-            let session = Session {
-                date,
-                unit_id,
-                tech_id,
-                room_id,
-                accountable_employees,
-            };
             let message = Message::new("Select an accountable employee", MK::Notify);
-            let response = Response::ok(transfer_pages::add_form_post(
+
+            Response::ok(transfer_pages::add_form_post(
                 tech,
                 units,
                 rooms,
+                None,
                 Some(session),
+                errors,
                 message,
-            ));
-            return response;
+            ))
         } else {
-            let employee_id: i32 = match body["employee"].parse() {
-                Ok(x) => x,
-                Err(_) => {
-                    let message = Message::new("Invalid employee value", MK::Error);
+            let mut draft = draft;
+            let date = draft.inner.transfer_date;
+            let message = match save_transfer(ctx, &mut draft, |tx, x| tx.transfer.insert(x)) {
+                Ok(errors) if !errors.is_empty() => {
                     return Response::ok(transfer_pages::add_form_post(
-                        tech, units, rooms, None, message,
+                        tech,
+                        units,
+                        rooms,
+                        None,
+                        Some(session),
+                        errors,
+                        invalid(),
                     ));
                 }
-            };
-            let mut transfer = transfer::Transfer::new_date(date, tech_id, room_id, employee_id);
-            let transfer_dao = transfer::TransferDAOImpl::new(pool.clone());
-            let message = match transfer_dao.insert(&mut transfer) {
-                Ok(()) => Message::new("Added new transfer", MK::Notify),
+                Ok(_) if transfer::is_pending(date, Local::now().date_naive()) => {
+                    Message::new(&format!("Scheduled transfer for {}", date), MK::Notify)
+                }
+                Ok(_) => Message::new("Added new transfer", MK::Notify),
                 Err(e) => Message::new(&format!("Erorr on adding new transfer: {}", e), MK::Error),
             };
 
-            let response = Response::ok(transfer_pages::add_form_get(tech, units, rooms, message));
-            return response;
+            Response::ok(transfer_pages::add_form_get(tech, units, rooms, message))
         }
     } else {
-        return Response::method_not_allowed();
+        Response::method_not_allowed()
     }
 }
 
-pub fn handle_transfer_edit(
-    method: &str,
-    route: &str,
-    body: Option<HashMap<String, String>>,
-    pool: Rc<PostgrePool>,
-) -> Response {
-    let arg = match get_route_arg::<i32>(route, 2) {
+pub fn handle_transfer_edit(req: &Request, ctx: &AppContext) -> Response {
+    let arg = match req.param::<i32>("id") {
         Some(x) => x,
-        None => {
-            return Response::internal_server_error("Couldn't get arguemnts from url");
-        }
+        None => return Response::not_found(),
     };
 
-    let tech_dao = tech::TechDaoImpl::new(pool.clone());
-    let unit_dao = unit::UnitDAOImpl::new(pool.clone());
-    let room_dao = room::RoomDAOImpl::new(pool.clone());
-    let transfer_dao = transfer::TransferDAOImpl::new(pool.clone());
-    let tech = match tech_dao.get_all() {
+    let tech_dao = &ctx.tech;
+    let unit_dao = &ctx.unit;
+    let room_dao = &ctx.room;
+    let transfer_dao = &ctx.transfer;
+    let mut tech = match tech_dao.get_in_service() {
         Ok(data) => data,
-        Err(e) => return Response::internal_server_error(&e.to_string()),
+        Err(e) => return e.into(),
     };
-    let units = match unit_dao.get_all_acountable() {
+    let mut units = match unit_dao.get_all_acountable() {
         Ok(data) => data,
-        Err(e) => return Response::internal_server_error(&e.to_string()),
+        Err(e) => return e.into(),
     };
     let rooms = match room_dao.get_all() {
         Ok(data) => data,
-        Err(e) => return Response::internal_server_error(&e.to_string()),
+        Err(e) => return e.into(),
     };
     let transfer = match transfer_dao.get_id_with_unit(arg) {
         Ok(data) => data,
-        Err(e) => return Response::internal_server_error(&e.to_string()),
+        Err(e) => return e.into(),
     };
-    println!("Tech: {:?}", tech);
-    println!("Units: {:?}", units);
-    println!("Room: {:?}", rooms);
-    println!("Transfer: {:?}", transfer);
-    if method == "GET" {
-        return Response::ok(transfer_pages::edit_form_get(
+    // Archived or written off tech and archived units are not offered, but the
+    // transfer that already uses one still shows it instead of silently selecting
+    // another.
+    if !tech.iter().any(|x| x.id() == transfer.inner.tech_id) {
+        if let Ok(x) = tech_dao.get_id(transfer.inner.tech_id) {
+            tech.push(x);
+        }
+    }
+    if !units.iter().any(|x| x.id() == transfer.unit_id) {
+        if let Ok(x) = unit_dao.get_id(transfer.unit_id) {
+            units.push(x);
+        }
+    }
+    debug!("Tech: {:?}", tech);
+    debug!("Units: {:?}", units);
+    debug!("Room: {:?}", rooms);
+    debug!("Transfer: {:?}", transfer);
+    if req.method == Method::Get {
+        Response::ok(transfer_pages::edit_form_get(
             tech,
             units,
             rooms,
             transfer,
             Message::none(),
-        ));
-    } else if method == "POST" {
-        let mut body = body.unwrap();
-        let unit_selected = body.get("unit_selected").is_some();
+        ))
+    } else if req.method == Method::Post {
+        let body = &req.body;
+        let unit_selected = body.contains_key("unit_selected");
 
-        let date: NaiveDate = match body["date"].parse() {
-            Ok(x) => x,
-            Err(_) => {
-                let message = Message::new("Not valid date value", MK::Error);
+        let mut errors = FieldErrors::new();
+        let mut draft = match transfer_from_form(body, &mut errors) {
+            Some(x) => x,
+            None => {
                 return Response::ok(transfer_pages::edit_form_post(
-                    tech, units, rooms, transfer, None, message,
+                    tech,
+                    units,
+                    rooms,
+                    transfer,
+                    None,
+                    errors,
+                    invalid(),
                 ));
             }
         };
-        let tech_id: i32 = match body["tech"].parse() {
+        draft.inner.set_id(arg);
+
+        let validator = TransferValidator::new(ctx);
+        let mut errors = match validator.check_placement(&draft) {
             Ok(x) => x,
-            Err(_) => {
-                let message = Message::new("Invalid tech value", MK::Error);
-                return Response::ok(transfer_pages::edit_form_post(
-                    tech, units, rooms, transfer, None, message,
-                ));
-            }
+            Err(e) => return e.into(),
         };
-        let unit_id: i32 = match body["unit"].parse() {
-            Ok(x) => x,
-            Err(_) => {
-                let message = Message::new("Invalid unit value", MK::Error);
-                return Response::ok(transfer_pages::edit_form_post(
-                    tech, units, rooms, transfer, None, message,
-                ));
-            }
+        let session = match errors.is_empty() {
+            true => match session_for(ctx, &draft, &mut errors) {
+                Ok(x) => x,
+                Err(e) => return e.into(),
+            },
+            false => None,
         };
-
-        let room_id: i32 = match body["room"].parse() {
-            Ok(x) => x,
-            Err(_) => {
-                let message = Message::new("Invalid room value", MK::Error);
+        let session = match session {
+            Some(x) => x,
+            None => {
                 return Response::ok(transfer_pages::edit_form_post(
-                    tech, units, rooms, transfer, None, message,
+                    tech,
+                    units,
+                    rooms,
+                    draft,
+                    None,
+                    errors,
+                    invalid(),
                 ));
             }
         };
 
         if !unit_selected {
-            let employee_dao = employee::EmployeeDAOImpl::new(pool.clone());
-            let accountable_employees = match employee_dao.get_all_accountable(unit_id) {
-                Ok(x) => x,
-                Err(e) => return Response::internal_server_error(&e.to_string()),
-            };
-
-            let session = Session {
-                date,
-                unit_id,
-                tech_id,
-                room_id,
-                accountable_employees,
-            };
             let message = Message::new("Select an accountable employee", MK::Notify);
-            let response = Response::ok(transfer_pages::edit_form_post(
+
+            Response::ok(transfer_pages::edit_form_post(
                 tech,
                 units,
                 rooms,
                 transfer,
                 Some(session),
+                errors,
                 message,
-            ));
-            return response;
+            ))
         } else {
-            let employee_id: i32 = match body["employee"].parse() {
-                Ok(x) => x,
-                Err(_) => {
-                    let message = Message::new("Invalid employee value", MK::Error);
+            let message = match save_transfer(ctx, &mut draft, |tx, x| tx.transfer.update(x)) {
+                Ok(errors) if !errors.is_empty() => {
                     return Response::ok(transfer_pages::edit_form_post(
-                        tech, units, rooms, transfer, None, message,
+                        tech,
+                        units,
+                        rooms,
+                        transfer,
+                        Some(session),
+                        errors,
+                        invalid(),
                     ));
                 }
-            };
-            let transfer_dao = transfer::TransferDAOImpl::new(pool.clone());
-            let mut transfer = transfer::Transfer::new_date(date, tech_id, room_id, employee_id);
-            transfer.set_id(arg);
-            let message = match transfer_dao.update(&mut transfer) {
-                Ok(()) => Message::new("Updated transfer", MK::Notify),
+                Ok(_) => Message::new("Updated transfer", MK::Notify),
                 Err(e) => {
                     Message::new(&format!("Erorr on updating the transfer: {}", e), MK::Error)
                 }
             };
             let transfer = match transfer_dao.get_id_with_unit(arg) {
                 Ok(data) => data,
-                Err(e) => return Response::internal_server_error(&e.to_string()),
+                Err(e) => return e.into(),
             };
 
-            let response = Response::ok(transfer_pages::edit_form_get(
+            Response::ok(transfer_pages::edit_form_get(
                 tech, units, rooms, transfer, message,
-            ));
-            return response;
+            ))
         }
     } else {
-        return Response::method_not_allowed();
+        Response::method_not_allowed()
+    }
+}
+
+fn upcoming_page(ctx: &AppContext, message: Message) -> Response {
+    let transfer_dao = &ctx.transfer;
+    let transfers = match transfer_dao.get_pending() {
+        Ok(data) => data,
+        Err(e) => return e.into(),
+    };
+
+    let today = Local::now().date_naive();
+    Response::ok(transfer_pages::upcoming_page(transfers, today, message))
+}
+
+pub fn handle_transfer_upcoming(req: &Request, ctx: &AppContext) -> Response {
+    let export = match Export::requested(req) {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+    let Some(export) = export else {
+        return upcoming_page(ctx, Message::none());
+    };
+
+    let transfer_dao = &ctx.transfer;
+    match transfer_dao.get_pending() {
+        Ok(transfers) => export.respond(&sheets::upcoming(&transfers, Local::now().date_naive())),
+        Err(e) => e.into(),
+    }
+}
+
+pub fn handle_transfer_cancel(req: &Request, ctx: &AppContext) -> Response {
+    let arg = match req.param::<i32>("id") {
+        Some(x) => x,
+        None => return Response::not_found(),
+    };
+
+    let transfer_dao = &ctx.transfer;
+    let message = match transfer_dao.cancel(arg) {
+        Ok(()) => Message::new("Cancelled the transfer", MK::Success),
+        Err(AppError::NotFound(_)) => return Response::not_found(),
+        Err(e) => Message::new(&e.to_string(), MK::Error),
+    };
+    upcoming_page(ctx, message)
+}
+
+pub fn handle_transfer_reschedule(req: &Request, ctx: &AppContext) -> Response {
+    let arg = match req.param::<i32>("id") {
+        Some(x) => x,
+        None => return Response::not_found(),
+    };
+
+    let date: NaiveDate = match req.body.field("date") {
+        Ok(x) => x,
+        Err(_) => {
+            let message = Message::new("Not valid date value", MK::Error);
+            return upcoming_page(ctx, message);
+        }
+    };
+    if !transfer::is_pending(date, Local::now().date_naive()) {
+        let message = Message::new("A transfer can only be moved to a future date", MK::Error);
+        return upcoming_page(ctx, message);
+    }
+
+    // The new date has to fit the tech's history just as an edited one would.
+    let transfer_dao = &ctx.transfer;
+    let result = transfer_dao.get_id_with_unit(arg).and_then(|mut draft| {
+        draft.inner.transfer_date = date;
+        save_transfer(ctx, &mut draft, |tx, transfer| {
+            tx.transfer
+                .reschedule(transfer.id(), transfer.transfer_date)
+        })
+    });
+    let message = match result {
+        Ok(errors) if errors.is_empty() => Message::new(
+            &format!("Rescheduled the transfer to {}", date),
+            MK::Success,
+        ),
+        Ok(errors) => Message::new(&errors.messages().collect::<Vec<_>>().join("; "), MK::Error),
+        Err(AppError::NotFound(_)) => return Response::not_found(),
+        Err(e) => Message::new(&e.to_string(), MK::Error),
+    };
+    upcoming_page(ctx, message)
+}
+
+pub fn handle_transfer_act(req: &Request, ctx: &AppContext) -> Response {
+    let arg = match req.param::<i32>("id") {
+        Some(x) => x,
+        None => return Response::not_found(),
+    };
+
+    match acts::transfer_act(ctx, arg) {
+        Ok(act) => Response::ok(act_pages::act_page(&act)),
+        Err(e) => e.into(),
+    }
+}
+
+pub fn handle_transfer_act_pdf(req: &Request, ctx: &AppContext) -> Response {
+    let arg = match req.param::<i32>("id") {
+        Some(x) => x,
+        None => return Response::not_found(),
+    };
+
+    let act = match acts::transfer_act(ctx, arg) {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+    match act_pages::act_pdf(&act) {
+        Ok(pdf) => Response::file("application/pdf", pdf).with_header(
+            "Content-Disposition",
+            &format!("inline; filename=\"act-{}.pdf\"", act.number),
+        ),
+        Err(e) => {
+            error!("Cannot render act {} as PDF: {}", act.number, e);
+            Response::internal_server_error(&e)
+        }
     }
 }
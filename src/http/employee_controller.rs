@@ -1,200 +1,253 @@
+use super::export::{sheets, Export};
+use super::form::FormData;
 use super::pages::*;
-use super::{get_route_arg, Response, Status};
+use super::request::{Method, Request};
+use super::Response;
+use crate::model::context::AppContext;
 use crate::model::dao::*;
-use crate::model::pool::PostgrePool;
-use maud::{html, Markup};
-use std::collections::HashMap;
-use std::rc::Rc;
-
-pub fn handle_employee_table(method: &str, pool: Rc<PostgrePool>) -> Response {
-    if method != "GET" {
-        return Response::method_not_allowed();
+use crate::model::deletion::{self, Removal};
+use crate::model::error::AppError;
+use chrono::Local;
+
+pub fn handle_employee_table(req: &Request, ctx: &AppContext) -> Response {
+    let export = match Export::requested(req) {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+    let employee_dao = &ctx.employee;
+    let archived = req.query.contains_key("archived");
+    let employees = match archived {
+        true => employee_dao.get_archived(),
+        false => employee_dao.get_all(),
+    };
+    match employees {
+        Ok(employees) => match export {
+            Some(export) => export.respond(&sheets::employees(&employees, archived)),
+            None => Response::ok(employee_pages::table_page(employees, archived)),
+        },
+        Err(e) => e.into(),
     }
+}
 
-    let employee_dao = employee::EmployeeDAOImpl::new(pool);
-    match employee_dao.get_all() {
-        Ok(tech) => Response::ok(employee_pages::table_page(tech)),
-        Err(e) => Response::internal_server_error(&e.to_string()),
+fn employee_from_form(body: &FormData) -> Result<employee::Employee, AppError> {
+    let is_supervisor: bool = body.contains_key("is_supervisor");
+    let is_accountable: bool = body.contains_key("is_accountable");
+    if is_supervisor == is_accountable {
+        return Err(AppError::validation(
+            "The employee has to be either a supervisor or an accoutanble",
+        ));
     }
+
+    let unit_id: i32 = body
+        .field("unit")
+        .map_err(|_| AppError::validation("The unit has to be selected"))?;
+
+    Ok(employee::Employee::new(
+        body.text("first_name")?,
+        body.text("middle_name")?,
+        body.text("last_name")?,
+        is_supervisor,
+        is_accountable,
+        body.text("job_title")?,
+        unit_id,
+    ))
 }
 
-pub fn handle_employee_add(
-    method: &str,
-    body: Option<HashMap<String, String>>,
-    pool: Rc<PostgrePool>,
-) -> Response {
-    if method == "GET" {
-        let unit_dao = unit::UnitDAOImpl::new(pool);
+pub fn handle_employee_add(req: &Request, ctx: &AppContext) -> Response {
+    if req.method == Method::Get {
+        let unit_dao = &ctx.unit;
         let units = match unit_dao.get_all() {
             Ok(x) => x,
-            Err(_) => return Response::internal_server_error("Tried accessing db"),
+            Err(e) => return e.into(),
         };
-        return Response::ok(employee_pages::add_form_get(units));
-    } else if method == "POST" {
-        let body = body.unwrap();
-        let employee_dao = employee::EmployeeDAOImpl::new(pool.clone());
-        let unit_dao = unit::UnitDAOImpl::new(pool.clone());
+        Response::ok(employee_pages::add_form_get(units))
+    } else if req.method == Method::Post {
+        let employee_dao = &ctx.employee;
+        let unit_dao = &ctx.unit;
         let units = match unit_dao.get_all() {
             Ok(x) => x,
-            Err(_) => return Response::internal_server_error("Tried accessing db"),
+            Err(e) => return e.into(),
         };
 
-        let is_supervisor: bool = body.get("is_supervisor").is_some();
-        let is_accountable: bool = body.get("is_accountable").is_some();
-        if is_supervisor == is_accountable {
-            let message = Message::new(
-                "The employee has to be either a supervisor or an accoutanble",
-                MK::Notify,
-            );
-            return Response::ok(employee_pages::add_form_post(units, message));
-        }
-
-        let unit_id: i32 = match body["unit"].parse() {
+        let mut employee = match employee_from_form(&req.body) {
             Ok(x) => x,
-            Err(_) => {
-                let message = Message::new("The unit has to be selected", MK::Error);
+            Err(e) => {
+                let message = Message::new(&e.to_string(), MK::Error);
                 return Response::ok(employee_pages::add_form_post(units, message));
             }
         };
 
-        let mut employee = employee::Employee::new(
-            &body["first_name"],
-            &body["middle_name"],
-            &body["last_name"],
-            is_supervisor,
-            is_accountable,
-            &body["job_title"],
-            unit_id,
-        );
-
         let message = match employee_dao.insert(&mut employee) {
             Ok(()) => Message::new("Added employee", MK::Notify),
-            Err(_) => Message::new("Error on adding new employee", MK::Error),
+            Err(e) => Message::new(&format!("Error on adding new employee: {}", e), MK::Error),
         };
-        let response = Response::ok(employee_pages::add_form_post(units, message));
-        return response;
+
+        Response::ok(employee_pages::add_form_post(units, message))
     } else {
-        return Response::method_not_allowed();
+        Response::method_not_allowed()
     }
 }
 
-pub fn handle_employee_edit(
-    method: &str,
-    route: &str,
-    body: Option<HashMap<String, String>>,
-    pool: Rc<PostgrePool>,
-) -> Response {
-    let arg = match get_route_arg::<i32>(route, 2) {
+pub fn handle_employee_edit(req: &Request, ctx: &AppContext) -> Response {
+    let arg = match req.param::<i32>("id") {
         Some(x) => x,
-        None => {
-            return Response::internal_server_error("Couldn't get arguments from url");
-        }
+        None => return Response::not_found(),
     };
 
-    let employee_dao = employee::EmployeeDAOImpl::new(pool.clone());
+    let employee_dao = &ctx.employee;
     let employee = match employee_dao.get_id(arg) {
         Ok(x) => x,
-        Err(e) => return Response::internal_server_error(&e.to_string()),
+        Err(e) => return e.into(),
     };
-    let unit_dao = unit::UnitDAOImpl::new(pool.clone());
-    let units = match unit_dao.get_all() {
+    let unit_dao = &ctx.unit;
+    let mut units = match unit_dao.get_all() {
         Ok(x) => x,
-        Err(e) => return Response::internal_server_error(&e.to_string()),
+        Err(e) => return e.into(),
     };
-    match method {
-        "GET" => Response::ok(employee_pages::edit_form_get(&employee, units)),
-        "POST" => {
-            let body = body.unwrap();
-            let unit_dao = unit::UnitDAOImpl::new(pool.clone());
-            let employee_dao = employee::EmployeeDAOImpl::new(pool);
-            let units = unit_dao.get_all().expect("Tried getting all units");
-            let is_supervisor: bool = body.get("is_supervisor").is_some();
-            let is_accountable: bool = body.get("is_accountable").is_some();
-            if is_supervisor == is_accountable {
-                let message = Message::new(
-                    "The employee has to be either a supervisor or an accoutanble",
-                    MK::Notify,
-                );
-                return Response::ok(employee_pages::add_form_post(units, message));
-            }
-
-            let unit_id: i32 = match body["unit"].parse() {
+    // An employee of an archived unit keeps it selected rather than being moved.
+    if !units.iter().any(|x| x.id() == employee.unit_id) {
+        if let Ok(x) = unit_dao.get_id(employee.unit_id) {
+            units.push(x);
+        }
+    }
+    match req.method {
+        Method::Get => Response::ok(employee_pages::edit_form_get(&employee, units)),
+        Method::Post => {
+            let mut employee = match employee_from_form(&req.body) {
                 Ok(x) => x,
-                Err(_) => {
-                    return Response::ok(employee_pages::add_form_post(
-                        units,
-                        Message::new("The unit has to be specified", MK::Error),
-                    ))
+                Err(e) => {
+                    let message = Message::new(&e.to_string(), MK::Error);
+                    return Response::ok(employee_pages::edit_form_post(&employee, units, message));
                 }
             };
-            let mut employee = employee::Employee::new(
-                &body["first_name"],
-                &body["middle_name"],
-                &body["last_name"],
-                is_supervisor,
-                is_accountable,
-                &body["job_title"],
-                unit_id,
-            );
 
             employee.set_id(arg);
-            let message = match employee_dao.update(&mut employee) {
+            let message = match employee_dao.update(&employee) {
                 Ok(()) => Message::new("Updated employee", MK::Notify),
-                Err(_) => Message::new("Error on adding new employee", MK::Error),
+                Err(e) => Message::new(&format!("Error on updating employee: {}", e), MK::Error),
             };
 
-            let response = Response::ok(employee_pages::edit_form_post(&employee, units, message));
-            return response;
+            Response::ok(employee_pages::edit_form_post(&employee, units, message))
         }
         _ => Response::method_not_allowed(),
     }
 }
 
-pub fn handle_employee_delete(method: &str, route: &str, pool: Rc<PostgrePool>) -> Response {
-    if method != "POST" {
-        return Response::method_not_allowed();
-    }
-    let arg = match get_route_arg::<i32>(route, 2) {
-        Some(x) => x,
-        None => {
-            return Response::internal_server_error("Couldn't get arguments from url");
-        }
+fn delete_page(ctx: &AppContext, id: i32, message: Message) -> Response {
+    let employee_dao = &ctx.employee;
+    let employee = match employee_dao.get_id(id) {
+        Ok(x) => x,
+        Err(e) => return e.into(),
     };
-
-    let employee_dao = employee::EmployeeDAOImpl::new(pool);
-    match employee_dao.delete(arg) {
+    let transfer_dao = &ctx.transfer;
+    let transfers = match transfer_dao.get_by_employee_id(id) {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+    let tech_dao = &ctx.tech;
+    let held = match tech_dao.get_by_employee_id(id, Local::now().date_naive()) {
         Ok(x) => x,
-        Err(e) => return Response::internal_server_error(&e.to_string()),
+        Err(e) => return e.into(),
+    };
+    let candidates = match employee_dao.get_all() {
+        Ok(x) => x
+            .into_iter()
+            .filter(|x| x.is_accountable && x.id() != id && x.unit_id == employee.unit_id)
+            .collect(),
+        Err(e) => return e.into(),
     };
 
-    Response::found("/employee")
+    Response::ok(employee_pages::delete_page(
+        &employee, transfers, held, candidates, message,
+    ))
 }
 
-pub fn handle_view_employee_tech(method: &str, route: &str, pool: Rc<PostgrePool>) -> Response {
-    if method != "GET" {
-        return Response::method_not_allowed();
-    }
-    let arg = match get_route_arg::<i32>(route, 2) {
+pub fn handle_employee_delete(req: &Request, ctx: &AppContext) -> Response {
+    let arg = match req.param::<i32>("id") {
         Some(x) => x,
-        None => {
-            return Response::internal_server_error("Couldn't get arguments from url");
-        }
+        None => return Response::not_found(),
     };
-    let tech_dao = tech::TechDaoImpl::new(pool);
-    match tech_dao.get_by_employee_id(arg) {
-        Ok(tech) => Response::ok(tech_pages::table_page_view_employee(tech)),
-        Err(e) => Response::internal_server_error(&e.to_string()),
+
+    match req.method {
+        Method::Get => delete_page(ctx, arg, Message::none()),
+        Method::Post => {
+            let hand_over_to = match req.body.optional("hand_over_to") {
+                Ok(x) => x,
+                Err(e) => return e.into(),
+            };
+            match deletion::delete_employee(ctx, arg, hand_over_to) {
+                Ok(Removal::Deleted) => Response::found("/employee"),
+                Ok(Removal::Archived) => delete_page(
+                    ctx,
+                    arg,
+                    Message::new(
+                        "The tech was handed over. Past transfers still name the employee, so they were archived instead of deleted.",
+                        MK::Success,
+                    ),
+                ),
+                Err(e @ (AppError::Conflict(_) | AppError::Validation(_))) => {
+                    delete_page(ctx, arg, Message::new(&e.to_string(), MK::Error))
+                }
+                Err(e) => e.into(),
+            }
+        }
+        _ => Response::method_not_allowed(),
     }
 }
 
-pub fn handle_employee_tech(method: &str, pool: Rc<PostgrePool>) -> Response {
-    if method != "GET" {
-        return Response::method_not_allowed();
+pub fn handle_view_employee_tech(req: &Request, ctx: &AppContext) -> Response {
+    let arg = match req.param::<i32>("id") {
+        Some(x) => x,
+        None => return Response::not_found(),
+    };
+    let date = match req.as_of_date() {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+    let export = match Export::requested(req) {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+    let tech_dao = &ctx.tech;
+    match tech_dao.get_by_employee_id(arg, date) {
+        Ok(tech) => match export {
+            Some(export) => export.respond(&sheets::employee_holdings(&tech, arg, date)),
+            None => Response::ok(tech_pages::table_page_view_employee(tech, arg, date)),
+        },
+        Err(e) => e.into(),
     }
+}
 
-    let employee_dao = employee::EmployeeDAOImpl::new(pool);
+pub fn handle_employee_tech(_req: &Request, ctx: &AppContext) -> Response {
+    let employee_dao = &ctx.employee;
     match employee_dao.get_all() {
         Ok(employees) => Response::ok(employee_pages::table_page_view(employees)),
-        Err(e) => Response::internal_server_error(&e.to_string()),
+        Err(e) => e.into(),
+    }
+}
+
+pub fn handle_employee_archive(req: &Request, ctx: &AppContext) -> Response {
+    let arg = match req.param::<i32>("id") {
+        Some(x) => x,
+        None => return Response::not_found(),
+    };
+
+    let employee_dao = &ctx.employee;
+    match employee_dao.archive(arg) {
+        Ok(()) => Response::found("/employee"),
+        Err(e) => e.into(),
+    }
+}
+
+pub fn handle_employee_restore(req: &Request, ctx: &AppContext) -> Response {
+    let arg = match req.param::<i32>("id") {
+        Some(x) => x,
+        None => return Response::not_found(),
+    };
+
+    let employee_dao = &ctx.employee;
+    match employee_dao.restore(arg) {
+        Ok(()) => Response::found("/employee?archived=true"),
+        Err(e) => e.into(),
     }
 }
@@ -0,0 +1,90 @@
+//! RFC 4180 CSV: comma separated, CRLF line ends, fields quoted when they need it.
+use super::{Cell, Sheet};
+
+const BOM: &str = "\u{feff}";
+
+/// Quotes `text` when it holds a separator, a quote or a line break, doubling any
+/// quotes inside.
+fn field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+fn cell(cell: &Cell) -> String {
+    match cell {
+        Cell::Text(text) => field(text),
+        Cell::Integer(value) => value.to_string(),
+        Cell::Bool(value) => value.to_string(),
+        Cell::Date(date) => date.to_string(),
+        Cell::Amount(minor) => {
+            let sign = if *minor < 0 { "-" } else { "" };
+            let minor = minor.unsigned_abs();
+            format!("{}{}.{:02}", sign, minor / 100, minor % 100)
+        }
+        Cell::Empty => String::new(),
+    }
+}
+
+fn line(out: &mut String, fields: impl Iterator<Item = String>) {
+    out.push_str(&fields.collect::<Vec<_>>().join(","));
+    out.push_str("\r\n");
+}
+
+pub fn write(sheet: &Sheet, bom: bool) -> Vec<u8> {
+    let mut out = String::new();
+    if bom {
+        out.push_str(BOM);
+    }
+    line(&mut out, sheet.columns.iter().map(|x| field(x)));
+    for row in &sheet.rows {
+        line(&mut out, row.iter().map(cell));
+    }
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_only_fields_that_need_it() {
+        assert_eq!(field("plain text"), "plain text");
+        assert_eq!(field("a,b"), "\"a,b\"");
+        assert_eq!(field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(field("cr\r"), "\"cr\r\"");
+        assert_eq!(field("semi;colon"), "semi;colon");
+        assert_eq!(field(""), "");
+    }
+
+    #[test]
+    fn writes_cells() {
+        let date = "2024-03-01".parse().unwrap();
+        assert_eq!(cell(&Cell::Date(date)), "2024-03-01");
+        assert_eq!(cell(&Cell::Amount(123_456)), "1234.56");
+        assert_eq!(cell(&Cell::Amount(-5)), "-0.05");
+        assert_eq!(cell(&Cell::Integer(-7)), "-7");
+        assert_eq!(cell(&Cell::Bool(true)), "true");
+        assert_eq!(cell(&Cell::Empty), "");
+    }
+
+    #[test]
+    fn writes_crlf_lines_without_totals() {
+        let mut sheet = Sheet::new("tech", &["Name", "Note"]);
+        sheet.row(vec![
+            Cell::Text("Laptop, 15\"".to_string()),
+            Cell::Text("line one\nline two".to_string()),
+        ]);
+        sheet.row(vec![Cell::Text("Mouse".to_string()), Cell::Empty]);
+        sheet.total(vec![Cell::Text("Total".to_string()), Cell::Empty]);
+
+        let expected = "Name,Note\r\n\"Laptop, 15\"\"\",\"line one\nline two\"\r\nMouse,\r\n";
+        assert_eq!(String::from_utf8(write(&sheet, false)).unwrap(), expected);
+        let with_bom = write(&sheet, true);
+        assert_eq!(&with_bom[..3], [0xef, 0xbb, 0xbf]);
+        assert_eq!(&with_bom[3..], expected.as_bytes());
+    }
+}
@@ -0,0 +1,152 @@
+//! Downloads of the list and report pages. A page handler builds the same data it
+//! renders as a `Sheet` when the query asks for `?format=csv` or `?format=xlsx`, so a
+//! download always matches the page and its filters.
+mod csv;
+pub(super) mod sheets;
+mod xlsx;
+mod zip;
+
+use super::request::Request;
+use super::Response;
+use crate::model::error::AppError;
+use crate::model::money::Amount;
+use chrono::NaiveDate;
+
+#[derive(Debug, Clone)]
+pub enum Cell {
+    Text(String),
+    Integer(i64),
+    Bool(bool),
+    Date(NaiveDate),
+    /// An amount in kopecks or cents; the currency goes in a column of its own. Wide
+    /// enough to hold a sum of any number of prices.
+    Amount(i128),
+    Empty,
+}
+
+impl Cell {
+    pub fn amount(amount: Amount) -> Cell {
+        Cell::Amount(amount.minor() as i128)
+    }
+}
+
+impl From<&str> for Cell {
+    fn from(value: &str) -> Self {
+        Cell::Text(value.to_string())
+    }
+}
+
+impl From<String> for Cell {
+    fn from(value: String) -> Self {
+        Cell::Text(value)
+    }
+}
+
+impl From<i32> for Cell {
+    fn from(value: i32) -> Self {
+        Cell::Integer(value as i64)
+    }
+}
+
+impl From<usize> for Cell {
+    fn from(value: usize) -> Self {
+        Cell::Integer(value as i64)
+    }
+}
+
+impl From<bool> for Cell {
+    fn from(value: bool) -> Self {
+        Cell::Bool(value)
+    }
+}
+
+impl From<NaiveDate> for Cell {
+    fn from(value: NaiveDate) -> Self {
+        Cell::Date(value)
+    }
+}
+
+impl<T: Into<Cell>> From<Option<T>> for Cell {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Cell::Empty, Into::into)
+    }
+}
+
+/// A table ready to be written out in any download format.
+#[derive(Debug)]
+pub struct Sheet {
+    /// Names the downloaded file, without the extension.
+    pub name: String,
+    pub columns: Vec<&'static str>,
+    pub rows: Vec<Vec<Cell>>,
+    /// Rows summing up the data, set apart below it. CSV leaves them out, so the file
+    /// holds nothing but data rows.
+    pub totals: Vec<Vec<Cell>>,
+}
+
+impl Sheet {
+    pub fn new(name: &str, columns: &[&'static str]) -> Sheet {
+        Sheet {
+            name: name.to_string(),
+            columns: columns.to_vec(),
+            rows: vec![],
+            totals: vec![],
+        }
+    }
+
+    pub fn row(&mut self, cells: Vec<Cell>) {
+        debug_assert_eq!(cells.len(), self.columns.len());
+        self.rows.push(cells);
+    }
+
+    pub fn total(&mut self, cells: Vec<Cell>) {
+        debug_assert_eq!(cells.len(), self.columns.len());
+        self.totals.push(cells);
+    }
+}
+
+/// A download of a page.
+#[derive(Debug, Clone, Copy)]
+pub enum Export {
+    /// With `bom` set the file starts with a byte order mark, which Excel needs to
+    /// read it as UTF-8.
+    Csv { bom: bool },
+    /// An Excel workbook with typed cells, a styled header and a totals row.
+    Xlsx,
+}
+
+impl Export {
+    /// The download asked for with `?format=`, or `None` for the page itself.
+    pub fn requested(req: &Request) -> Result<Option<Export>, AppError> {
+        match req.query.get("format") {
+            None | Some("html") => Ok(None),
+            Some("csv") => Ok(Some(Export::Csv {
+                bom: req.query.contains_key("bom"),
+            })),
+            Some("xlsx") => Ok(Some(Export::Xlsx)),
+            Some(other) => Err(AppError::BadRequest(format!(
+                "Unknown download format {}",
+                other
+            ))),
+        }
+    }
+
+    pub fn respond(&self, sheet: &Sheet) -> Response {
+        match self {
+            Export::Csv { bom } => {
+                Response::file("text/csv; charset=utf-8", csv::write(sheet, *bom)).with_header(
+                    "Content-Disposition",
+                    &format!("attachment; filename=\"{}.csv\"", sheet.name),
+                )
+            }
+            Export::Xlsx => Response::file(
+                "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+                xlsx::write(sheet),
+            )
+            .with_header(
+                "Content-Disposition",
+                &format!("attachment; filename=\"{}.xlsx\"", sheet.name),
+            ),
+        }
+    }
+}
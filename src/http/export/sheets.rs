@@ -0,0 +1,470 @@
+//! The downloadable form of each list and report page, column for column. Prices
+//! come as a plain amount with the currency next to it, so spreadsheets can sum them.
+use super::{Cell, Sheet};
+use crate::model::dao::audit::{AuditItem, AuditSummary};
+use crate::model::dao::category::TechCategory;
+use crate::model::dao::employee::Employee;
+use crate::model::dao::room::Room;
+use crate::model::dao::tech::{Tech, TechEmployee, TechHolding, TechUnit};
+use crate::model::dao::transfer::{is_pending, TransferDetails};
+use crate::model::dao::unit::Unit;
+use crate::model::dao::write_off::WriteOffDetails;
+use crate::model::money::{Currency, Money, Totals};
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+
+fn full_name(employee: &Employee) -> String {
+    format!(
+        "{} {} {}",
+        employee.last_name, employee.first_name, employee.middle_name
+    )
+}
+
+/// Adds a totals row for each currency, holding the sum of the `Price` column and
+/// `label` in the first one.
+fn price_totals(sheet: &mut Sheet, label: &str, prices: Totals) {
+    let column = |name| sheet.columns.iter().position(|x| *x == name);
+    let (Some(price), Some(currency)) = (column("Price"), column("Currency")) else {
+        return;
+    };
+    for (code, sum) in prices.iter() {
+        let mut cells = vec![Cell::Empty; sheet.columns.len()];
+        cells[0] = label.into();
+        cells[price] = Cell::Amount(sum);
+        cells[currency] = code.as_str().into();
+        sheet.total(cells);
+    }
+}
+
+/// `name` with `-archived` added for the archived list.
+fn list_name(name: &str, archived: bool) -> String {
+    match archived {
+        true => format!("{}-archived", name),
+        false => name.to_string(),
+    }
+}
+
+pub fn tech(tech_list: &[Tech], archived: bool) -> Sheet {
+    let mut sheet = Sheet::new(
+        &list_name("tech", archived),
+        &[
+            "ID",
+            "Inventory number",
+            "Name",
+            "Model",
+            "Acquisition date",
+            "Price",
+            "Currency",
+            "Written off on",
+            "Archived on",
+        ],
+    );
+    for tech in tech_list {
+        sheet.row(vec![
+            tech.id().into(),
+            tech.inventory_number.into(),
+            tech.name.as_str().into(),
+            tech.model.as_str().into(),
+            tech.acquisition_date.into(),
+            Cell::amount(tech.price.amount),
+            tech.price.currency.as_str().into(),
+            tech.written_off_on.into(),
+            tech.archived_at.map(|x| x.date_naive()).into(),
+        ]);
+    }
+    sheet
+}
+
+pub fn holdings(holdings: &[TechHolding], date: NaiveDate) -> Sheet {
+    let mut sheet = Sheet::new(
+        &format!("holdings-{}", date),
+        &[
+            "Unit",
+            "Employee",
+            "Room",
+            "Inventory number",
+            "Name",
+            "Model",
+            "Held since",
+            "Price",
+            "Book value",
+            "Currency",
+        ],
+    );
+    for holding in holdings {
+        sheet.row(vec![
+            holding.unit_name.as_str().into(),
+            format!(
+                "{} {} {}",
+                holding.last_name, holding.first_name, holding.middle_name
+            )
+            .into(),
+            holding.room_number.into(),
+            holding.inventory_number.into(),
+            holding.name.as_str().into(),
+            holding.model.as_str().into(),
+            holding.date.into(),
+            Cell::amount(holding.price.amount),
+            Cell::amount(holding.book_value(date).amount),
+            holding.price.currency.as_str().into(),
+        ]);
+    }
+    price_totals(
+        &mut sheet,
+        "Total",
+        holdings.iter().map(|x| x.price).collect(),
+    );
+    sheet
+}
+
+pub fn unit_holdings(tech_list: &[TechUnit], unit_id: i32, date: NaiveDate) -> Sheet {
+    let mut sheet = Sheet::new(
+        &format!("unit-{}-tech-{}", unit_id, date),
+        &[
+            "Unit name",
+            "Date",
+            "Inventory number",
+            "Name",
+            "Model",
+            "Price",
+            "Currency",
+        ],
+    );
+    for tech in tech_list {
+        sheet.row(vec![
+            tech.unit_name.as_str().into(),
+            tech.date.into(),
+            tech.inventory_number.into(),
+            tech.name.as_str().into(),
+            tech.model.as_str().into(),
+            Cell::amount(tech.price.amount),
+            tech.price.currency.as_str().into(),
+        ]);
+    }
+    price_totals(
+        &mut sheet,
+        "Total",
+        tech_list.iter().map(|x| x.price).collect(),
+    );
+    sheet
+}
+
+pub fn employee_holdings(tech_list: &[TechEmployee], employee_id: i32, date: NaiveDate) -> Sheet {
+    let mut sheet = Sheet::new(
+        &format!("employee-{}-tech-{}", employee_id, date),
+        &[
+            "Date",
+            "First name",
+            "Middle name",
+            "Last name",
+            "Job title",
+            "Inventory number",
+            "Name",
+            "Model",
+            "Price",
+            "Currency",
+        ],
+    );
+    for tech in tech_list {
+        sheet.row(vec![
+            tech.date.into(),
+            tech.first_name.as_str().into(),
+            tech.middle_name.as_str().into(),
+            tech.last_name.as_str().into(),
+            tech.job_title.as_str().into(),
+            tech.inventory_number.into(),
+            tech.name.as_str().into(),
+            tech.model.as_str().into(),
+            Cell::amount(tech.price.amount),
+            tech.price.currency.as_str().into(),
+        ]);
+    }
+    price_totals(
+        &mut sheet,
+        "Total",
+        tech_list.iter().map(|x| x.price).collect(),
+    );
+    sheet
+}
+
+#[derive(Default)]
+struct Sums {
+    items: usize,
+    price: i128,
+    book_value: i128,
+}
+
+/// One row per unit and currency, the holdings being sorted by unit.
+pub fn book_value(holdings: &[TechHolding], date: NaiveDate) -> Sheet {
+    let mut sheet = Sheet::new(
+        &format!("book-value-{}", date),
+        &["Unit", "Currency", "Items", "Price", "Book value"],
+    );
+    let mut units: Vec<(i32, &str, BTreeMap<Currency, Sums>)> = vec![];
+    for holding in holdings {
+        match units.last_mut() {
+            Some((unit_id, _, _)) if *unit_id == holding.unit_id => {}
+            _ => units.push((holding.unit_id, &holding.unit_name, BTreeMap::new())),
+        }
+        if let Some((_, _, sums)) = units.last_mut() {
+            let sums = sums.entry(holding.price.currency).or_default();
+            sums.items += 1;
+            sums.price += holding.price.amount.minor() as i128;
+            sums.book_value += holding.book_value(date).amount.minor() as i128;
+        }
+    }
+    for (_, unit_name, sums) in units {
+        for (currency, sums) in sums {
+            sheet.row(vec![
+                unit_name.into(),
+                currency.as_str().into(),
+                sums.items.into(),
+                Cell::Amount(sums.price),
+                Cell::Amount(sums.book_value),
+            ]);
+        }
+    }
+    sheet
+}
+
+pub fn units(unit_list: &[Unit], archived: bool) -> Sheet {
+    let mut sheet = Sheet::new(
+        &list_name("units", archived),
+        &[
+            "ID",
+            "Unit number",
+            "Full name",
+            "Short name",
+            "Archived on",
+        ],
+    );
+    for unit in unit_list {
+        sheet.row(vec![
+            unit.id().into(),
+            unit.unit_number.into(),
+            unit.full_name.as_str().into(),
+            unit.short_name.as_str().into(),
+            unit.archived_at.map(|x| x.date_naive()).into(),
+        ]);
+    }
+    sheet
+}
+
+pub fn employees(employee_list: &[Employee], archived: bool) -> Sheet {
+    let mut sheet = Sheet::new(
+        &list_name("employees", archived),
+        &[
+            "ID",
+            "First name",
+            "Middle name",
+            "Last name",
+            "Is supervisor",
+            "Is accountable",
+            "Job title",
+            "Unit ID",
+            "Archived on",
+        ],
+    );
+    for employee in employee_list {
+        sheet.row(vec![
+            employee.id().into(),
+            employee.first_name.as_str().into(),
+            employee.middle_name.as_str().into(),
+            employee.last_name.as_str().into(),
+            employee.is_supervisor.into(),
+            employee.is_accountable.into(),
+            employee.job_title.as_str().into(),
+            employee.unit_id.into(),
+            employee.archived_at.map(|x| x.date_naive()).into(),
+        ]);
+    }
+    sheet
+}
+
+pub fn rooms(room_list: &[Room]) -> Sheet {
+    let mut sheet = Sheet::new("rooms", &["ID", "Room number", "Squares"]);
+    for room in room_list {
+        sheet.row(vec![
+            room.id().into(),
+            room.room_number.into(),
+            room.squares.into(),
+        ]);
+    }
+    sheet
+}
+
+pub fn categories(category_list: &[TechCategory]) -> Sheet {
+    let mut sheet = Sheet::new(
+        "categories",
+        &["ID", "Name", "Depreciation", "Useful life, years"],
+    );
+    for category in category_list {
+        sheet.row(vec![
+            category.id().into(),
+            category.name.as_str().into(),
+            category.depreciation.method.label().into(),
+            category.depreciation.useful_life_years.into(),
+        ]);
+    }
+    sheet
+}
+
+pub fn write_offs(register: &[WriteOffDetails], year: i32) -> Sheet {
+    let mut sheet = Sheet::new(
+        &format!("write-offs-{}", year),
+        &[
+            "Act number",
+            "Date",
+            "Inventory number",
+            "Name",
+            "Model",
+            "Disposal",
+            "Reason",
+            "Signed by",
+            "Price",
+            "Residual value",
+            "Currency",
+        ],
+    );
+    for entry in register {
+        sheet.row(vec![
+            entry.write_off.act_number.as_str().into(),
+            entry.write_off.write_off_date.into(),
+            entry.tech.inventory_number.into(),
+            entry.tech.name.as_str().into(),
+            entry.tech.model.as_str().into(),
+            entry.write_off.disposal.label().into(),
+            entry.write_off.reason.as_str().into(),
+            full_name(&entry.employee).into(),
+            Cell::amount(entry.tech.price.amount),
+            Cell::amount(entry.write_off.residual_value),
+            entry.tech.price.currency.as_str().into(),
+        ]);
+    }
+    sheet
+}
+
+const TRANSFER_COLUMNS: [&str; 12] = [
+    "ID",
+    "Transfer date",
+    "Status",
+    "Inventory number",
+    "Tech",
+    "Model",
+    "Price",
+    "Currency",
+    "Room",
+    "Employee",
+    "Job title",
+    "Unit",
+];
+
+/// An item moved several times is on several rows, but its price counts once.
+fn transfer_totals(sheet: &mut Sheet, transfer_details: &[TransferDetails]) {
+    let mut tech: BTreeMap<i32, Money> = BTreeMap::new();
+    for transfer in transfer_details {
+        if let Some(x) = &transfer.tech {
+            tech.insert(x.id(), x.price);
+        }
+    }
+    price_totals(sheet, "Total, each item once", tech.into_values().collect());
+}
+
+fn transfer_row(transfer: &TransferDetails, today: NaiveDate) -> Vec<Cell> {
+    let status = match is_pending(transfer.transfer_date, today) {
+        true => "Pending",
+        false => "Done",
+    };
+    let tech = transfer.tech.as_ref();
+    let employee = transfer.employee.as_ref();
+    vec![
+        transfer.id.into(),
+        transfer.transfer_date.into(),
+        status.into(),
+        tech.map(|x| x.inventory_number).into(),
+        tech.map(|x| x.name.as_str()).into(),
+        tech.map(|x| x.model.as_str()).into(),
+        tech.map(|x| Cell::amount(x.price.amount)).into(),
+        tech.map(|x| x.price.currency.as_str()).into(),
+        transfer.room.as_ref().map(|x| x.room_number).into(),
+        employee.map(full_name).into(),
+        employee.map(|x| x.job_title.as_str()).into(),
+        transfer.unit.as_ref().map(|x| x.full_name.as_str()).into(),
+    ]
+}
+
+pub fn transfers(transfer_details: &[TransferDetails], today: NaiveDate) -> Sheet {
+    let mut sheet = Sheet::new("transfers", &TRANSFER_COLUMNS);
+    for transfer in transfer_details {
+        sheet.row(transfer_row(transfer, today));
+    }
+    transfer_totals(&mut sheet, transfer_details);
+    sheet
+}
+
+pub fn upcoming(transfer_details: &[TransferDetails], today: NaiveDate) -> Sheet {
+    let mut sheet = Sheet::new("upcoming-transfers", &TRANSFER_COLUMNS);
+    for transfer in transfer_details {
+        sheet.row(transfer_row(transfer, today));
+    }
+    transfer_totals(&mut sheet, transfer_details);
+    sheet
+}
+
+pub fn audits(audits: &[AuditSummary]) -> Sheet {
+    let mut sheet = Sheet::new(
+        "audits",
+        &[
+            "ID",
+            "Audited",
+            "Holdings as of",
+            "Opened on",
+            "Closed on",
+            "Items",
+            "Not checked",
+            "Discrepancies",
+        ],
+    );
+    for summary in audits {
+        sheet.row(vec![
+            summary.audit.id().into(),
+            summary.scope_name.as_str().into(),
+            summary.audit.audit_date.into(),
+            summary.audit.opened_at.date_naive().into(),
+            summary.audit.closed_at.map(|x| x.date_naive()).into(),
+            Cell::Integer(summary.items),
+            Cell::Integer(summary.pending),
+            Cell::Integer(summary.discrepancies),
+        ]);
+    }
+    sheet
+}
+
+pub fn audit(summary: &AuditSummary, items: &[AuditItem]) -> Sheet {
+    let mut sheet = Sheet::new(
+        &format!("audit-{}", summary.audit.id()),
+        &[
+            "Inventory number",
+            "Name",
+            "Model",
+            "On the list",
+            "Book room",
+            "Book holder",
+            "Finding",
+            "Where found",
+        ],
+    );
+    for item in items {
+        sheet.row(vec![
+            item.inventory_number.into(),
+            item.name.as_str().into(),
+            item.model.as_str().into(),
+            item.expected.into(),
+            item.book_room_number.into(),
+            item.book_holder.as_str().into(),
+            item.finding.label().into(),
+            item.location.as_str().into(),
+        ]);
+    }
+    sheet
+}
@@ -0,0 +1,330 @@
+//! Office Open XML workbooks with a single worksheet. Dates and amounts go in as
+//! numbers with a display format, so Excel can sort, filter and sum them; text goes
+//! in inline, which spares a shared string table.
+use super::zip::Zip;
+use super::{Cell, Sheet};
+use chrono::NaiveDate;
+use std::fmt::Write;
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/><Override PartName="/xl/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml"/></Types>"#;
+
+const ROOT_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#;
+
+const WORKBOOK_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/><Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/></Relationships>"#;
+
+/// The cell formats, referred to by their position: plain, header, date, amount,
+/// then the bold totals row as text and as an amount.
+const STYLES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><numFmts count="1"><numFmt numFmtId="164" formatCode="yyyy\-mm\-dd"/></numFmts><fonts count="2"><font><sz val="11"/><name val="Calibri"/></font><font><b/><sz val="11"/><name val="Calibri"/></font></fonts><fills count="3"><fill><patternFill patternType="none"/></fill><fill><patternFill patternType="gray125"/></fill><fill><patternFill patternType="solid"><fgColor rgb="FFD9E1F2"/><bgColor indexed="64"/></patternFill></fill></fills><borders count="2"><border><left/><right/><top/><bottom/><diagonal/></border><border><left/><right/><top style="thin"/><bottom style="thin"/><diagonal/></border></borders><cellStyleXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellStyleXfs><cellXfs count="6"><xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0"/><xf numFmtId="0" fontId="1" fillId="2" borderId="1" xfId="0" applyFont="1" applyFill="1" applyBorder="1"/><xf numFmtId="164" fontId="0" fillId="0" borderId="0" xfId="0" applyNumberFormat="1"/><xf numFmtId="4" fontId="0" fillId="0" borderId="0" xfId="0" applyNumberFormat="1"/><xf numFmtId="0" fontId="1" fillId="0" borderId="1" xfId="0" applyFont="1" applyBorder="1"/><xf numFmtId="4" fontId="1" fillId="0" borderId="1" xfId="0" applyNumberFormat="1" applyFont="1" applyBorder="1"/></cellXfs><cellStyles count="1"><cellStyle name="Normal" xfId="0" builtinId="0"/></cellStyles></styleSheet>"#;
+
+const HEADER_STYLE: u8 = 1;
+const DATE_STYLE: u8 = 2;
+const AMOUNT_STYLE: u8 = 3;
+const TOTAL_STYLE: u8 = 4;
+const TOTAL_AMOUNT_STYLE: u8 = 5;
+
+const MIN_WIDTH: usize = 8;
+const MAX_WIDTH: usize = 60;
+/// Excel will not take a longer sheet name.
+const MAX_SHEET_NAME: usize = 31;
+
+/// Escapes text for XML, dropping the control characters XML 1.0 cannot carry.
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\t' | '\n' | '\r' => out.push(c),
+            c if (c as u32) < 0x20 => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// `A`, `B`, ... `Z`, `AA`, ... for the zero-based `index`.
+fn column_name(index: usize) -> String {
+    let mut name = vec![];
+    let mut index = index + 1;
+    while index > 0 {
+        index -= 1;
+        name.push(b'A' + (index % 26) as u8);
+        index /= 26;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap_or_default()
+}
+
+/// Days since 1899-12-30, which is how Excel counts dates. Excel takes 1900 for a
+/// leap year, so before its made-up 1900-02-29 the count starts a day later.
+fn date_serial(date: NaiveDate) -> i64 {
+    let epoch = NaiveDate::from_ymd_opt(1899, 12, 30).unwrap_or_default();
+    let leap_day = NaiveDate::from_ymd_opt(1900, 3, 1).unwrap_or_default();
+    let days = (date - epoch).num_days();
+    match date < leap_day {
+        true => days - 1,
+        false => days,
+    }
+}
+
+fn amount(minor: i128) -> String {
+    let sign = if minor < 0 { "-" } else { "" };
+    let minor = minor.unsigned_abs();
+    format!("{}{}.{:02}", sign, minor / 100, minor % 100)
+}
+
+/// The characters a cell shows, to size its column by.
+fn shown_width(cell: &Cell) -> usize {
+    match cell {
+        Cell::Text(text) => text.lines().map(|x| x.chars().count()).max().unwrap_or(0),
+        Cell::Integer(value) => value.to_string().len(),
+        Cell::Bool(_) => 5,
+        Cell::Date(_) => 10,
+        // Room for the thousands separators.
+        Cell::Amount(minor) => amount(*minor).len() * 4 / 3,
+        Cell::Empty => 0,
+    }
+}
+
+fn widths(sheet: &Sheet) -> Vec<usize> {
+    let mut widths: Vec<usize> = sheet.columns.iter().map(|x| x.chars().count()).collect();
+    for row in sheet.rows.iter().chain(&sheet.totals) {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(shown_width(cell));
+        }
+    }
+    widths
+        .into_iter()
+        .map(|x| (x + 2).clamp(MIN_WIDTH, MAX_WIDTH))
+        .collect()
+}
+
+fn text_cell(out: &mut String, reference: &str, style: u8, text: &str) {
+    let space = match text.trim() == text {
+        true => "",
+        false => r#" xml:space="preserve""#,
+    };
+    let _ = write!(
+        out,
+        r#"<c r="{}" s="{}" t="inlineStr"><is><t{}>{}</t></is></c>"#,
+        reference,
+        style,
+        space,
+        escape(text)
+    );
+}
+
+fn cell(out: &mut String, reference: &str, cell: &Cell, total: bool) {
+    let (text_style, amount_style) = match total {
+        true => (TOTAL_STYLE, TOTAL_AMOUNT_STYLE),
+        false => (0, AMOUNT_STYLE),
+    };
+    let _ = match cell {
+        Cell::Text(text) => {
+            text_cell(out, reference, text_style, text);
+            Ok(())
+        }
+        Cell::Integer(value) => write!(
+            out,
+            r#"<c r="{}" s="{}"><v>{}</v></c>"#,
+            reference, text_style, value
+        ),
+        Cell::Bool(value) => write!(
+            out,
+            r#"<c r="{}" s="{}" t="b"><v>{}</v></c>"#,
+            reference, text_style, *value as u8
+        ),
+        Cell::Date(date) => write!(
+            out,
+            r#"<c r="{}" s="{}"><v>{}</v></c>"#,
+            reference,
+            DATE_STYLE,
+            date_serial(*date)
+        ),
+        Cell::Amount(minor) => write!(
+            out,
+            r#"<c r="{}" s="{}"><v>{}</v></c>"#,
+            reference,
+            amount_style,
+            amount(*minor)
+        ),
+        // Totals rows are bordered all the way across.
+        Cell::Empty if total => write!(out, r#"<c r="{}" s="{}"/>"#, reference, TOTAL_STYLE),
+        Cell::Empty => Ok(()),
+    };
+}
+
+fn worksheet(sheet: &Sheet) -> String {
+    let last_column = column_name(sheet.columns.len().saturating_sub(1));
+    let mut out = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main">"#,
+    );
+    // The header stays in view while scrolling.
+    out.push_str(r#"<sheetViews><sheetView workbookViewId="0"><pane ySplit="1" topLeftCell="A2" activePane="bottomLeft" state="frozen"/></sheetView></sheetViews>"#);
+
+    out.push_str("<cols>");
+    for (index, width) in widths(sheet).iter().enumerate() {
+        let _ = write!(
+            out,
+            r#"<col min="{0}" max="{0}" width="{1}" customWidth="1"/>"#,
+            index + 1,
+            width
+        );
+    }
+    out.push_str("</cols>");
+
+    out.push_str("<sheetData>");
+    out.push_str(r#"<row r="1">"#);
+    for (index, column) in sheet.columns.iter().enumerate() {
+        text_cell(
+            &mut out,
+            &format!("{}1", column_name(index)),
+            HEADER_STYLE,
+            column,
+        );
+    }
+    out.push_str("</row>");
+    let rows = sheet.rows.iter().map(|x| (x, false));
+    let totals = sheet.totals.iter().map(|x| (x, true));
+    for (number, (row, total)) in (2..).zip(rows.chain(totals)) {
+        let _ = write!(out, r#"<row r="{}">"#, number);
+        for (index, value) in row.iter().enumerate() {
+            let reference = format!("{}{}", column_name(index), number);
+            cell(&mut out, &reference, value, total);
+        }
+        out.push_str("</row>");
+    }
+    out.push_str("</sheetData>");
+
+    // Filters over the header and the data, leaving the totals out.
+    let _ = write!(
+        out,
+        r#"<autoFilter ref="A1:{}{}"/>"#,
+        last_column,
+        sheet.rows.len() + 1
+    );
+    out.push_str("</worksheet>");
+    out
+}
+
+/// The download name cut down to what Excel takes as a sheet name.
+fn sheet_name(name: &str) -> String {
+    name.chars()
+        .filter(|x| !matches!(x, '[' | ']' | ':' | '*' | '?' | '/' | '\\'))
+        .take(MAX_SHEET_NAME)
+        .collect()
+}
+
+fn workbook(sheet: &Sheet) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="{}" sheetId="1" r:id="rId1"/></sheets><definedNames><definedName name="_xlnm._FilterDatabase" localSheetId="0" hidden="1">'{}'!$A$1:${}${}</definedName></definedNames></workbook>"#,
+        escape(&sheet_name(&sheet.name)),
+        escape(&sheet_name(&sheet.name).replace('\'', "''")),
+        column_name(sheet.columns.len().saturating_sub(1)),
+        sheet.rows.len() + 1
+    )
+}
+
+pub fn write(sheet: &Sheet) -> Vec<u8> {
+    let mut zip = Zip::new();
+    zip.add("[Content_Types].xml", CONTENT_TYPES.as_bytes());
+    zip.add("_rels/.rels", ROOT_RELS.as_bytes());
+    zip.add("xl/workbook.xml", workbook(sheet).as_bytes());
+    zip.add("xl/_rels/workbook.xml.rels", WORKBOOK_RELS.as_bytes());
+    zip.add("xl/styles.xml", STYLES.as_bytes());
+    zip.add("xl/worksheets/sheet1.xml", worksheet(sheet).as_bytes());
+    zip.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::zip::tests::read;
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn column_names() {
+        let names: Vec<String> = [0, 1, 25, 26, 27, 51, 52, 701, 702]
+            .into_iter()
+            .map(column_name)
+            .collect();
+        assert_eq!(names, ["A", "B", "Z", "AA", "AB", "AZ", "BA", "ZZ", "AAA"]);
+    }
+
+    #[test]
+    fn date_serials_match_excel() {
+        assert_eq!(date_serial(date("1900-01-01")), 1);
+        assert_eq!(date_serial(date("1900-02-28")), 59);
+        // Serial 60 is Excel's 1900-02-29, which never was.
+        assert_eq!(date_serial(date("1900-03-01")), 61);
+        assert_eq!(date_serial(date("1900-03-02")), 62);
+        assert_eq!(date_serial(date("2024-01-01")), 45292);
+    }
+
+    #[test]
+    fn amounts_keep_two_decimals() {
+        assert_eq!(amount(0), "0.00");
+        assert_eq!(amount(5), "0.05");
+        assert_eq!(amount(-5), "-0.05");
+        assert_eq!(amount(123_456), "1234.56");
+    }
+
+    #[test]
+    fn escapes_text_for_xml() {
+        assert_eq!(escape("a<b & \"c\">"), "a&lt;b &amp; &quot;c&quot;&gt;");
+        assert_eq!(escape("tab\there\u{1}"), "tab\there");
+    }
+
+    #[test]
+    fn workbook_holds_the_sheet() {
+        let mut sheet = Sheet::new("Tech: list", &["Name", "Date", "Price"]);
+        sheet.row(vec![
+            Cell::Text("Laptop & dock".to_string()),
+            Cell::Date(date("2024-01-01")),
+            Cell::Amount(123_456),
+        ]);
+        sheet.total(vec![
+            Cell::Text("Total".to_string()),
+            Cell::Empty,
+            Cell::Amount(123_456),
+        ]);
+        let entries = read(&write(&sheet));
+
+        let names: Vec<&str> = entries.iter().map(|(x, _)| x.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "[Content_Types].xml",
+                "_rels/.rels",
+                "xl/workbook.xml",
+                "xl/_rels/workbook.xml.rels",
+                "xl/styles.xml",
+                "xl/worksheets/sheet1.xml",
+            ]
+        );
+        let workbook = String::from_utf8(entries[2].1.clone()).unwrap();
+        assert!(
+            workbook.contains(r#"<sheet name="Tech list""#),
+            "{}",
+            workbook
+        );
+        let worksheet = String::from_utf8(entries[5].1.clone()).unwrap();
+        assert!(worksheet
+            .contains(r#"<c r="A2" s="0" t="inlineStr"><is><t>Laptop &amp; dock</t></is></c>"#));
+        assert!(worksheet.contains(r#"<c r="B2" s="2"><v>45292</v></c>"#));
+        assert!(worksheet.contains(r#"<c r="C3" s="5"><v>1234.56</v></c>"#));
+        // The filter covers the data, not the totals.
+        assert!(worksheet.contains(r#"<autoFilter ref="A1:C2"/>"#));
+    }
+}
@@ -0,0 +1,209 @@
+//! Just enough of the ZIP format to package an XLSX file: deflated entries, a central
+//! directory and nothing else (no ZIP64, no encryption, no timestamps).
+use miniz_oxide::deflate::compress_to_vec;
+
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const END_OF_DIRECTORY: u32 = 0x0605_4b50;
+const VERSION: u16 = 20;
+/// Names are UTF-8.
+const FLAGS: u16 = 0x0800;
+const DEFLATE: u16 = 8;
+/// 1980-01-01, the earliest date the format can hold; entries carry no real time.
+const DOS_DATE: u16 = 0x0021;
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ 0xedb8_8320,
+                _ => crc >> 1,
+            };
+        }
+    }
+    !crc
+}
+
+struct Entry {
+    name: String,
+    crc: u32,
+    compressed_size: u32,
+    size: u32,
+    offset: u32,
+}
+
+pub struct Zip {
+    out: Vec<u8>,
+    entries: Vec<Entry>,
+}
+
+impl Zip {
+    pub fn new() -> Zip {
+        Zip {
+            out: vec![],
+            entries: vec![],
+        }
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.out.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.out.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /// The fields a local header and a central directory record have in common.
+    fn common(&mut self, index: usize) {
+        let (crc, compressed_size, size, name_len) = {
+            let entry = &self.entries[index];
+            (
+                entry.crc,
+                entry.compressed_size,
+                entry.size,
+                entry.name.len() as u16,
+            )
+        };
+        self.u16(VERSION);
+        self.u16(FLAGS);
+        self.u16(DEFLATE);
+        self.u16(0);
+        self.u16(DOS_DATE);
+        self.u32(crc);
+        self.u32(compressed_size);
+        self.u32(size);
+        self.u16(name_len);
+        self.u16(0);
+    }
+
+    pub fn add(&mut self, name: &str, data: &[u8]) {
+        let compressed = compress_to_vec(data, 6);
+        self.entries.push(Entry {
+            name: name.to_string(),
+            crc: crc32(data),
+            compressed_size: compressed.len() as u32,
+            size: data.len() as u32,
+            offset: self.out.len() as u32,
+        });
+        self.u32(LOCAL_HEADER);
+        self.common(self.entries.len() - 1);
+        self.out.extend_from_slice(name.as_bytes());
+        self.out.extend_from_slice(&compressed);
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        let directory_offset = self.out.len() as u32;
+        for index in 0..self.entries.len() {
+            self.u32(CENTRAL_HEADER);
+            self.u16(VERSION);
+            self.common(index);
+            // Comment length, disk number, internal and external attributes.
+            self.u16(0);
+            self.u16(0);
+            self.u16(0);
+            self.u32(0);
+            let offset = self.entries[index].offset;
+            self.u32(offset);
+            let name = std::mem::take(&mut self.entries[index].name);
+            self.out.extend_from_slice(name.as_bytes());
+        }
+        let directory_size = self.out.len() as u32 - directory_offset;
+        let count = self.entries.len() as u16;
+        self.u32(END_OF_DIRECTORY);
+        self.u16(0);
+        self.u16(0);
+        self.u16(count);
+        self.u16(count);
+        self.u32(directory_size);
+        self.u32(directory_offset);
+        self.u16(0);
+        self.out
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use miniz_oxide::inflate::decompress_to_vec;
+
+    fn u16_at(data: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes([data[at], data[at + 1]])
+    }
+
+    fn u32_at(data: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]])
+    }
+
+    /// Reads an archive back through its central directory, checking every entry
+    /// against its local header and its CRC.
+    pub fn read(archive: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let end = archive.len() - 22;
+        assert_eq!(u32_at(archive, end), END_OF_DIRECTORY);
+        let count = u16_at(archive, end + 10) as usize;
+        let mut at = u32_at(archive, end + 16) as usize;
+        assert_eq!(at + u32_at(archive, end + 12) as usize, end);
+
+        let mut entries = vec![];
+        for _ in 0..count {
+            assert_eq!(u32_at(archive, at), CENTRAL_HEADER);
+            let crc = u32_at(archive, at + 16);
+            let compressed_size = u32_at(archive, at + 20) as usize;
+            let size = u32_at(archive, at + 24) as usize;
+            let name_len = u16_at(archive, at + 28) as usize;
+            let offset = u32_at(archive, at + 42) as usize;
+            let name = String::from_utf8(archive[at + 46..at + 46 + name_len].to_vec()).unwrap();
+            at += 46 + name_len;
+
+            assert_eq!(u32_at(archive, offset), LOCAL_HEADER);
+            assert_eq!(u16_at(archive, offset + 8), DEFLATE);
+            assert_eq!(u32_at(archive, offset + 14), crc);
+            let local_name_len = u16_at(archive, offset + 26) as usize;
+            assert_eq!(
+                &archive[offset + 30..offset + 30 + local_name_len],
+                name.as_bytes()
+            );
+            let start = offset + 30 + local_name_len;
+            let data = decompress_to_vec(&archive[start..start + compressed_size]).unwrap();
+            assert_eq!(data.len(), size);
+            assert_eq!(crc32(&data), crc, "{}", name);
+            entries.push((name, data));
+        }
+        entries
+    }
+
+    #[test]
+    fn crc32_check_values() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"a"), 0xe8b7_be43);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(
+            crc32(b"The quick brown fox jumps over the lazy dog"),
+            0x414f_a339
+        );
+    }
+
+    #[test]
+    fn reads_back_what_was_written() {
+        let long = "row,".repeat(10_000);
+        let mut zip = Zip::new();
+        zip.add("empty.txt", b"");
+        zip.add("dir/data.csv", long.as_bytes());
+        zip.add("отчёт.xml", "<a>ё</a>".as_bytes());
+        let entries = read(&zip.finish());
+
+        let names: Vec<&str> = entries.iter().map(|(x, _)| x.as_str()).collect();
+        assert_eq!(names, ["empty.txt", "dir/data.csv", "отчёт.xml"]);
+        assert_eq!(entries[0].1, b"");
+        assert_eq!(entries[1].1, long.as_bytes());
+        assert_eq!(entries[2].1, "<a>ё</a>".as_bytes());
+    }
+
+    #[test]
+    fn empty_archive_is_only_the_directory_end() {
+        let archive = Zip::new().finish();
+        assert_eq!(archive.len(), 22);
+        assert!(read(&archive).is_empty());
+    }
+}
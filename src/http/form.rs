@@ -0,0 +1,312 @@
+use crate::model::error::AppError;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug)]
+pub enum FormError {
+    InvalidEscape(String),
+    InvalidUtf8,
+    InvalidMultipart(&'static str),
+}
+
+impl fmt::Display for FormError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormError::InvalidEscape(seq) => write!(f, "Invalid percent escape: {}", seq),
+            FormError::InvalidUtf8 => write!(f, "Form data is not valid UTF-8"),
+            FormError::InvalidMultipart(reason) => write!(f, "Invalid multipart body: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for FormError {}
+
+/// Decoded `application/x-www-form-urlencoded` pairs in the order they were sent.
+/// For a key sent several times, the first value is the one read.
+#[derive(Debug, Default)]
+pub struct FormData {
+    pairs: Vec<(String, String)>,
+}
+
+impl FormData {
+    pub fn parse(data: &str) -> Result<FormData, FormError> {
+        let mut pairs = vec![];
+        for pair in data.split('&').filter(|pair| !pair.is_empty()) {
+            let mut split = pair.splitn(2, '=');
+            let key = decode(split.next().unwrap_or(""), true)?;
+            let value = decode(split.next().unwrap_or(""), true)?;
+            pairs.push((key, value.trim().to_string()));
+        }
+        Ok(FormData { pairs })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.pairs
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.pairs.iter().any(|(k, _)| k == key)
+    }
+
+    /// The value of a field that the form must always send.
+    pub fn text(&self, key: &str) -> Result<&str, AppError> {
+        self.get(key)
+            .ok_or_else(|| AppError::BadRequest(format!("Missing form field: {}", key)))
+    }
+
+    pub fn field<T>(&self, key: &str) -> Result<T, AppError>
+    where
+        T: FromStr,
+    {
+        self.text(key)?
+            .parse()
+            .map_err(|_| AppError::Validation(format!("Invalid value for {}", key)))
+    }
+
+    /// Like `field`, but a missing or empty value is `None` rather than an error.
+    pub fn optional<T>(&self, key: &str) -> Result<Option<T>, AppError>
+    where
+        T: FromStr,
+    {
+        match self.get(key) {
+            None | Some("") => Ok(None),
+            Some(_) => self.field(key).map(Some),
+        }
+    }
+}
+
+/// A file sent with a `multipart/form-data` form.
+#[derive(Debug)]
+pub struct UploadedFile {
+    /// The name of the form field it was sent in.
+    pub name: String,
+    /// Empty when the field was submitted without choosing a file.
+    pub filename: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Splits a `multipart/form-data` body into its text fields and its files.
+pub fn parse_multipart(
+    body: &[u8],
+    boundary: &str,
+) -> Result<(FormData, Vec<UploadedFile>), FormError> {
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let next_part = [b"\r\n".as_slice(), &delimiter].concat();
+    let start = find(body, &delimiter).ok_or(FormError::InvalidMultipart("no boundary"))?;
+    let mut rest = &body[start + delimiter.len()..];
+
+    let mut pairs = vec![];
+    let mut files = vec![];
+    // Every part ends at the next delimiter; the one followed by `--` closes the body.
+    while !rest.starts_with(b"--") {
+        rest = rest
+            .strip_prefix(b"\r\n")
+            .ok_or(FormError::InvalidMultipart("malformed boundary line"))?;
+        let head_end =
+            find(rest, b"\r\n\r\n").ok_or(FormError::InvalidMultipart("part without headers"))?;
+        let head = std::str::from_utf8(&rest[..head_end]).map_err(|_| FormError::InvalidUtf8)?;
+        rest = &rest[head_end + 4..];
+        let end = find(rest, &next_part).ok_or(FormError::InvalidMultipart("unfinished part"))?;
+        let content = &rest[..end];
+        rest = &rest[end + next_part.len()..];
+
+        let mut name = None;
+        let mut filename = None;
+        let mut content_type = "text/plain".to_string();
+        for line in head.split("\r\n") {
+            let Some((header, value)) = line.split_once(':') else {
+                continue;
+            };
+            match header.trim().to_lowercase().as_str() {
+                "content-disposition" => {
+                    for param in value.split(';').skip(1) {
+                        let Some((key, value)) = param.split_once('=') else {
+                            continue;
+                        };
+                        let value = value.trim().trim_matches('"').to_string();
+                        match key.trim() {
+                            "name" => name = Some(value),
+                            "filename" => filename = Some(value),
+                            _ => {}
+                        }
+                    }
+                }
+                "content-type" => content_type = value.trim().to_string(),
+                _ => {}
+            }
+        }
+
+        let name = name.ok_or(FormError::InvalidMultipart("part without a name"))?;
+        match filename {
+            Some(filename) => files.push(UploadedFile {
+                name,
+                filename,
+                content_type,
+                data: content.to_vec(),
+            }),
+            None => {
+                let value = std::str::from_utf8(content).map_err(|_| FormError::InvalidUtf8)?;
+                pairs.push((name, value.trim().to_string()));
+            }
+        }
+    }
+    Ok((FormData { pairs }, files))
+}
+
+fn hex_value(byte: u8) -> Option<u8> {
+    match byte {
+        b'0'..=b'9' => Some(byte - b'0'),
+        b'a'..=b'f' => Some(byte - b'a' + 10),
+        b'A'..=b'F' => Some(byte - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Decodes `%XX` sequences into UTF-8. `+` means a space in form bodies and query
+/// strings but is a literal plus inside path segments.
+pub fn decode(input: &str, plus_as_space: bool) -> Result<String, FormError> {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let escape = bytes.get(i + 1..i + 3);
+                let value =
+                    escape.and_then(|hex| Some(hex_value(hex[0])? << 4 | hex_value(hex[1])?));
+                match value {
+                    Some(byte) => decoded.push(byte),
+                    None => {
+                        let end = (i + 3).min(bytes.len());
+                        let seq = String::from_utf8_lossy(&bytes[i..end]).into_owned();
+                        return Err(FormError::InvalidEscape(seq));
+                    }
+                }
+                i += 3;
+            }
+            b'+' if plus_as_space => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            byte => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).map_err(|_| FormError::InvalidUtf8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOUNDARY: &str = "----form7MA4YWxk";
+
+    /// A multipart body with a `kind` text field and a CSV file.
+    fn upload(csv: &[u8]) -> Vec<u8> {
+        [
+            b"preamble\r\n------form7MA4YWxk\r\n".as_slice(),
+            b"Content-Disposition: form-data; name=\"kind\"\r\n\r\ntech\r\n",
+            b"------form7MA4YWxk\r\n",
+            b"Content-Disposition: form-data; name=\"file\"; filename=\"tech.csv\"\r\n",
+            b"Content-Type: text/csv\r\n\r\n",
+            csv,
+            b"\r\n------form7MA4YWxk--\r\n",
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn decode_plus_and_escapes() {
+        assert_eq!(decode("a+b", true).unwrap(), "a b");
+        assert_eq!(decode("a+b", false).unwrap(), "a+b");
+        assert_eq!(decode("a%2Bb%20c", true).unwrap(), "a+b c");
+        assert_eq!(decode("%2f%2F", false).unwrap(), "//");
+        assert_eq!(decode("100%25", true).unwrap(), "100%");
+        assert_eq!(decode("", true).unwrap(), "");
+    }
+
+    #[test]
+    fn decode_multi_byte_utf8() {
+        assert_eq!(
+            decode("%D0%9F%D1%80%D0%B8%D0%B2%D0%B5%D1%82", true).unwrap(),
+            "Привет"
+        );
+        assert_eq!(decode("%E2%82%AC+5", true).unwrap(), "€ 5");
+        // Raw UTF-8 passes through untouched.
+        assert_eq!(decode("ёлка", true).unwrap(), "ёлка");
+    }
+
+    #[test]
+    fn decode_refuses_bad_escapes() {
+        let escape = |input| match decode(input, true) {
+            Err(FormError::InvalidEscape(seq)) => seq,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(escape("a%zzb"), "%zz");
+        assert_eq!(escape("a%2"), "%2");
+        assert_eq!(escape("a%"), "%");
+        assert_eq!(escape("%g1"), "%g1");
+        // Escapes that decode to bytes that are not UTF-8.
+        assert!(matches!(decode("%D0", true), Err(FormError::InvalidUtf8)));
+        assert!(matches!(
+            decode("%FF%FE", true),
+            Err(FormError::InvalidUtf8)
+        ));
+    }
+
+    #[test]
+    fn form_data_reads_the_first_value() {
+        let form = FormData::parse("a=1&b=+two+&a=3&&flag").unwrap();
+        assert_eq!(form.get("a"), Some("1"));
+        assert_eq!(form.get("b"), Some("two"));
+        assert_eq!(form.get("flag"), Some(""));
+        assert!(form.contains_key("flag"));
+        assert_eq!(form.get("c"), None);
+        assert!(FormData::parse("a=%zz").is_err());
+    }
+
+    #[test]
+    fn multipart_splits_fields_and_files() {
+        let csv = b"a,b\r\n1,2\r\n--not a boundary\r\n";
+        let (form, files) = parse_multipart(&upload(csv), BOUNDARY).unwrap();
+        assert_eq!(form.get("kind"), Some("tech"));
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name, "file");
+        assert_eq!(files[0].filename, "tech.csv");
+        assert_eq!(files[0].content_type, "text/csv");
+        assert_eq!(files[0].data, csv);
+    }
+
+    #[test]
+    fn multipart_keeps_binary_file_content() {
+        let data = [0xff, 0x00, b'\r', b'\n', 0xfe];
+        let (_, files) = parse_multipart(&upload(&data), BOUNDARY).unwrap();
+        assert_eq!(files[0].data, data);
+    }
+
+    #[test]
+    fn multipart_refuses_broken_bodies() {
+        let body = upload(b"a,b");
+        assert!(parse_multipart(&body, "other").is_err());
+        // Cut off before the closing delimiter.
+        assert!(parse_multipart(&body[..body.len() - 30], BOUNDARY).is_err());
+
+        let nameless =
+            b"--b\r\nContent-Disposition: form-data\r\n\r\nvalue\r\n--b--\r\n".as_slice();
+        assert!(parse_multipart(nameless, "b").is_err());
+        let headless = b"--b\r\nvalue\r\n--b--\r\n".as_slice();
+        assert!(parse_multipart(headless, "b").is_err());
+    }
+}
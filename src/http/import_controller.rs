@@ -0,0 +1,147 @@
+use super::form::FormData;
+use super::pages::*;
+use super::request::{Method, Request};
+use super::Response;
+use crate::model::context::AppContext;
+use crate::model::error::AppError;
+use crate::model::import::{self, CsvTable, ImportKind, Mapping};
+
+/// Mistakes in the file or the mapping are shown on the page, for the user to fix.
+fn correctable(e: &AppError) -> bool {
+    matches!(
+        e,
+        AppError::Validation(_) | AppError::Conflict(_) | AppError::Sql { .. }
+    )
+}
+
+fn upload_error(e: AppError) -> Response {
+    match correctable(&e) {
+        true => Response::ok(import_pages::upload_page(Message::new(
+            &e.to_string(),
+            MK::Error,
+        ))),
+        false => e.into(),
+    }
+}
+
+fn preview(
+    ctx: &AppContext,
+    kind: ImportKind,
+    csv: &str,
+    table: &CsvTable,
+    mapping: &Mapping,
+    message: Message,
+) -> Response {
+    match import::dry_run(ctx, kind, table, mapping) {
+        Ok(preview) => Response::ok(import_pages::preview_page(
+            &preview, table, csv, mapping, message,
+        )),
+        Err(e) => e.into(),
+    }
+}
+
+/// The mapping chosen on the preview page, one column (or none) per field.
+fn mapping_from_form(
+    kind: ImportKind,
+    table: &CsvTable,
+    body: &FormData,
+) -> Result<Mapping, AppError> {
+    let mut mapping = vec![];
+    for field in kind.fields() {
+        let column: Option<usize> = body.optional(&format!("map-{}", field.key))?;
+        if column.is_some_and(|x| x >= table.headers.len()) {
+            return Err(AppError::BadRequest(format!(
+                "No such column for {}",
+                field.label
+            )));
+        }
+        mapping.push(column);
+    }
+    Ok(mapping)
+}
+
+/// A freshly uploaded file, previewed with the columns matched by their headers.
+fn handle_upload(req: &Request, ctx: &AppContext) -> Response {
+    let kind = match req.body.text("kind").and_then(ImportKind::parse) {
+        Ok(x) => x,
+        Err(e) => return upload_error(e),
+    };
+    let file = match req.file("file") {
+        Some(x) => x,
+        None => return upload_error(AppError::validation("Choose a CSV file to import")),
+    };
+    let csv = match std::str::from_utf8(&file.data) {
+        Ok(x) => x,
+        Err(_) => {
+            return upload_error(AppError::validation(
+                "The file has to be saved as CSV in UTF-8",
+            ))
+        }
+    };
+    let table = match import::read_csv(csv) {
+        Ok(x) => x,
+        Err(e) => return upload_error(e),
+    };
+
+    info!(
+        "Previewing import of {} rows of {} from {} ({})",
+        table.rows.len(),
+        kind.as_str(),
+        file.filename,
+        file.content_type
+    );
+    let mapping = import::auto_mapping(kind, &table.headers);
+    preview(ctx, kind, csv, &table, &mapping, Message::none())
+}
+
+/// The preview form sent back, to check the rows again or to import them.
+fn handle_review(req: &Request, ctx: &AppContext) -> Response {
+    let kind = match req.body.text("kind").and_then(ImportKind::parse) {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+    let csv = match req.body.text("csv") {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+    let table = match import::read_csv(csv) {
+        Ok(x) => x,
+        Err(e) => return upload_error(e),
+    };
+    let mapping = match mapping_from_form(kind, &table, &req.body) {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+
+    if req.body.get("action") != Some("import") {
+        return preview(ctx, kind, csv, &table, &mapping, Message::none());
+    }
+    match import::import(ctx, kind, &table, &mapping) {
+        Ok(count) => {
+            info!("Imported {} rows of {}", count, kind.as_str());
+            let message = format!("Imported {} row(s) of {}", count, kind.label());
+            Response::ok(import_pages::upload_page(Message::new(
+                &message,
+                MK::Notify,
+            )))
+        }
+        Err(e) if correctable(&e) => preview(
+            ctx,
+            kind,
+            csv,
+            &table,
+            &mapping,
+            Message::new(&e.to_string(), MK::Error),
+        ),
+        Err(e) => e.into(),
+    }
+}
+
+pub fn handle_import(req: &Request, ctx: &AppContext) -> Response {
+    match req.method {
+        Method::Get => Response::ok(import_pages::upload_page(Message::none())),
+        Method::Post if !req.files.is_empty() => handle_upload(req, ctx),
+        Method::Post => handle_review(req, ctx),
+        _ => Response::method_not_allowed(),
+    }
+}
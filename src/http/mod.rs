@@ -1,84 +1,140 @@
+pub(super) mod audit_controller;
+pub(super) mod category_controller;
 pub mod connection;
 pub(super) mod controller;
 pub(super) mod employee_controller;
+pub(super) mod export;
+pub(super) mod form;
+pub(super) mod import_controller;
 pub(super) mod unit_controller;
 pub(super) mod tech_controller;
 pub(super) mod pages;
+pub(super) mod pdf;
+pub(super) mod request;
+pub(super) mod room_controller;
+mod router;
+pub(super) mod static_controller;
+mod workers;
+pub(super) mod write_off_controller;
+use crate::model::error::AppError;
 use maud::{html, Markup};
 use pages::base_page;
-use std::str::FromStr;
-
-pub fn get_route_arg<T>(route: &str, count: usize) -> Option<T>
-where
-    T: FromStr,
-{
-    let route = route.split('?').next()?;
-    let part = route.split('/').nth(count)?;
-    part.parse::<T>().ok()
-}
 
-struct Location {
+const HTML: &str = "text/html; charset=utf-8";
+
+pub(crate) struct Location {
     uri: String,
 }
 
-enum Status {
+pub(crate) enum Status {
     Ok,
+    NotModified,
+    BadRequest,
     NotFound,
     MethodNotAllowed,
+    Conflict,
+    PayloadTooLarge,
     Found(Location),
     InternalServerError,
 }
 
-struct Response {
+pub(crate) struct Response {
     status: Status,
-    page: Markup,
+    headers: Vec<(String, String)>,
+    content_type: String,
+    body: Vec<u8>,
 }
 
 impl Response {
+    fn html(status: Status, page: Markup) -> Response {
+        Response {
+            status,
+            headers: vec![],
+            content_type: HTML.to_string(),
+            body: page.into_string().into_bytes(),
+        }
+    }
+
     fn method_not_allowed() -> Response {
-        return Response {
-            status: Status::MethodNotAllowed,
-            page: base_page::method_not_allowed(),
-        };
+        Response::html(Status::MethodNotAllowed, base_page::method_not_allowed())
+    }
+
+    fn bad_request(reason: &str) -> Response {
+        Response::html(
+            Status::BadRequest,
+            base_page::base_error_template("Bad request", reason),
+        )
     }
 
     fn internal_server_error(reason: &str) -> Response {
-        return Response {
-            status: Status::InternalServerError,
-            page: base_page::base_error_template("Internal server error", reason),
-        };
+        Response::html(
+            Status::InternalServerError,
+            base_page::base_error_template("Internal server error", reason),
+        )
     }
 
     fn ok(page: Markup) -> Response {
-        return Response {
-            status: Status::Ok,
-            page,
-        };
+        Response::html(Status::Ok, page)
     }
 
     fn found(route: &str) -> Response {
         let location = Location {
             uri: route.to_string(),
         };
-        return Response {
-            status: Status::Found(location),
-            page: html! {},
-        };
+        Response::html(Status::Found(location), html! {})
     }
 
     fn not_found() -> Response {
-        return Response {
-            status: Status::NotFound,
-            page: base_page::base_error_template("Not found", "Not found"),
-        };
+        Response::html(Status::NotFound, base_page::not_found())
+    }
+
+    fn conflict(reason: &str) -> Response {
+        Response::html(
+            Status::Conflict,
+            base_page::base_error_template("Conflict", reason),
+        )
+    }
+
+    fn payload_too_large(reason: &str) -> Response {
+        Response::html(
+            Status::PayloadTooLarge,
+            base_page::base_error_template("Payload too large", reason),
+        )
+    }
+
+    fn not_modified() -> Response {
+        Response {
+            status: Status::NotModified,
+            headers: vec![],
+            content_type: HTML.to_string(),
+            body: vec![],
+        }
+    }
+
+    fn file(content_type: &str, body: Vec<u8>) -> Response {
+        Response {
+            status: Status::Ok,
+            headers: vec![],
+            content_type: content_type.to_string(),
+            body,
+        }
+    }
+
+    fn with_header(mut self, name: &str, value: &str) -> Response {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
     }
 
     fn get_status_str(&self) -> String {
         let status = match &self.status {
             Status::Ok => "HTTP/1.1 200 OK".to_string(),
+            Status::NotModified => "HTTP/1.1 304 Not Modified".to_string(),
+            Status::BadRequest => "HTTP/1.1 400 Bad Request".to_string(),
             Status::NotFound => "HTTP/1.1 404 Not found".to_string(),
             Status::MethodNotAllowed => "HTTP/1.1 405 Method Not Allowed".to_string(),
-            Status::InternalServerError => "500 Internal Server Error".to_string(),
+            Status::Conflict => "HTTP/1.1 409 Conflict".to_string(),
+            Status::PayloadTooLarge => "HTTP/1.1 413 Payload Too Large".to_string(),
+            Status::InternalServerError => "HTTP/1.1 500 Internal Server Error".to_string(),
             Status::Found(location) => {
                format!("HTTP/1.1 302 Found\nLocation: {}", location.uri) 
             }
@@ -86,3 +142,23 @@ impl Response {
         status
     }
 }
+
+impl From<AppError> for Response {
+    fn from(e: AppError) -> Self {
+        match &e {
+            AppError::BadRequest(_) | AppError::Validation(_) => {
+                Response::bad_request(&e.to_string())
+            }
+            AppError::NotFound(_) => Response::not_found(),
+            AppError::Conflict(_)
+            | AppError::Sql {
+                constraint: Some(_),
+                ..
+            } => Response::conflict(&e.to_string()),
+            AppError::Sql { .. } | AppError::PoolTimeout(_) => {
+                error!("{}", e);
+                Response::internal_server_error(&e.to_string())
+            }
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use crate::http::pdf::{Document, MARGIN, PAGE_HEIGHT, PAGE_WIDTH};
+use crate::model::acts::{ActLine, Party, TransferAct};
+use maud::{html, Markup, DOCTYPE};
+
+const STOCK: &str = "Issued from stock";
+
+fn title(act: &TransferAct) -> String {
+    format!("Acceptance act No. {} of {}", act.number, act.date)
+}
+
+fn unit_name(party: &Party) -> &str {
+    party
+        .unit
+        .as_ref()
+        .map_or("No unit", |x| x.full_name.as_str())
+}
+
+fn room_name(line: &ActLine) -> String {
+    line.room
+        .as_ref()
+        .map_or("-".to_string(), |x| x.room_number.to_string())
+}
+
+fn party(heading: &str, party: &Option<Party>) -> Markup {
+    html! {
+        tr {
+            th { (heading) }
+            @match party {
+                Some(party) => {
+                    td { (party.full_name()) }
+                    td { (party.employee.job_title) }
+                    td { (unit_name(party)) }
+                }
+                None => { td colspan="3" { (STOCK) } }
+            }
+        }
+    }
+}
+
+/// The act as a page of its own, without the site navigation, so it prints clean.
+pub fn act_page(act: &TransferAct) -> Markup {
+    let receiver = Some(act.receiver.clone());
+    html! {
+        (DOCTYPE)
+        html {
+            head {
+                meta charset="utf-8";
+                title { (title(act)) }
+                link rel="stylesheet" href="/static/css/styles.css";
+            }
+            body {
+                main {
+                    h1 { (title(act)) }
+
+                    table border="1" {
+                        thead {
+                            tr {
+                                th {}
+                                th { "Name" }
+                                th { "Job title" }
+                                th { "Unit" }
+                            }
+                        }
+                        tbody {
+                            (party("Handed over by", &act.giver))
+                            (party("Received by", &receiver))
+                        }
+                    }
+
+                    table border="1" {
+                        thead {
+                            tr {
+                                th { "No." }
+                                th { "Inventory number" }
+                                th { "Name" }
+                                th { "Model" }
+                                th { "Room" }
+                                th { "Price" }
+                            }
+                        }
+                        tbody {
+                            @for (index, line) in act.lines.iter().enumerate() {
+                                tr {
+                                    td { (index + 1) }
+                                    td { (line.tech.inventory_number) }
+                                    td { (line.tech.name) }
+                                    td { (line.tech.model) }
+                                    td { (room_name(line)) }
+                                    td { (line.tech.price) }
+                                }
+                            }
+                            tr {
+                                th colspan="5" { "Total, " (act.lines.len()) " item(s)" }
+                                th { (act.total()) }
+                            }
+                        }
+                    }
+
+                    br;
+                    p {
+                        "Handed over: ____________________ "
+                        @if let Some(giver) = &act.giver { (giver.full_name()) }
+                    }
+                    p { "Received: ____________________ " (act.receiver.full_name()) }
+
+                    br;
+                    a href=(format!("/transfer/{}/act.pdf", act.number)) { "PDF" }
+                    " "
+                    a href="/transfer" { "Back to the transfers" }
+                }
+            }
+        }
+    }
+}
+
+const TITLE_SIZE: f32 = 14.0;
+const TEXT_SIZE: f32 = 10.0;
+const LINE_HEIGHT: f32 = 14.0;
+const RIGHT: f32 = PAGE_WIDTH - MARGIN;
+
+/// Left edges of the item table columns; the price is set flush right.
+const COLUMN_NUMBER: f32 = MARGIN;
+const COLUMN_INVENTORY: f32 = MARGIN + 30.0;
+const COLUMN_TECH: f32 = MARGIN + 110.0;
+const COLUMN_ROOM: f32 = MARGIN + 360.0;
+const TECH_WIDTH: f32 = COLUMN_ROOM - COLUMN_TECH - 10.0;
+
+/// Moves down by `height`, starting a new page when that would run off this one.
+fn advance(doc: &mut Document, y: &mut f32, height: f32) {
+    if *y + height > PAGE_HEIGHT - MARGIN {
+        doc.new_page();
+        *y = MARGIN;
+    }
+    *y += height;
+}
+
+fn pdf_party(doc: &mut Document, y: &mut f32, heading: &str, party: &Option<Party>) {
+    advance(doc, y, LINE_HEIGHT * 1.5);
+    doc.text(MARGIN, *y, TEXT_SIZE, heading);
+    let details = match party {
+        Some(party) => vec![
+            party.full_name(),
+            party.employee.job_title.clone(),
+            unit_name(party).to_string(),
+        ],
+        None => vec![STOCK.to_string()],
+    };
+    for detail in details {
+        for text in doc.wrap(&detail, TEXT_SIZE, RIGHT - COLUMN_TECH) {
+            doc.text(COLUMN_TECH, *y, TEXT_SIZE, &text);
+            advance(doc, y, LINE_HEIGHT);
+        }
+    }
+}
+
+fn pdf_table_header(doc: &mut Document, y: &mut f32) {
+    advance(doc, y, LINE_HEIGHT);
+    doc.text(COLUMN_NUMBER, *y, TEXT_SIZE, "No.");
+    doc.text(COLUMN_INVENTORY, *y, TEXT_SIZE, "Inv. No.");
+    doc.text(COLUMN_TECH, *y, TEXT_SIZE, "Name, model");
+    doc.text(COLUMN_ROOM, *y, TEXT_SIZE, "Room");
+    doc.text_right(RIGHT, *y, TEXT_SIZE, "Price");
+    doc.line(MARGIN, *y + 4.0, RIGHT, *y + 4.0);
+}
+
+/// The act as a PDF, laid out like the printable page.
+pub fn act_pdf(act: &TransferAct) -> Result<Vec<u8>, String> {
+    let mut doc = Document::new(&title(act))?;
+    let mut y = MARGIN;
+
+    advance(&mut doc, &mut y, TITLE_SIZE);
+    doc.text(MARGIN, y, TITLE_SIZE, &title(act));
+    advance(&mut doc, &mut y, LINE_HEIGHT);
+
+    let receiver = Some(act.receiver.clone());
+    pdf_party(&mut doc, &mut y, "Handed over by", &act.giver);
+    pdf_party(&mut doc, &mut y, "Received by", &receiver);
+
+    advance(&mut doc, &mut y, LINE_HEIGHT);
+    pdf_table_header(&mut doc, &mut y);
+    for (index, line) in act.lines.iter().enumerate() {
+        let tech = format!("{} {}", line.tech.name, line.tech.model);
+        let wrapped = doc.wrap(&tech, TEXT_SIZE, TECH_WIDTH);
+        let height = LINE_HEIGHT * wrapped.len() as f32;
+        if y + height > PAGE_HEIGHT - MARGIN {
+            doc.new_page();
+            y = MARGIN;
+            pdf_table_header(&mut doc, &mut y);
+        }
+        advance(&mut doc, &mut y, LINE_HEIGHT);
+        doc.text(COLUMN_NUMBER, y, TEXT_SIZE, &(index + 1).to_string());
+        doc.text(
+            COLUMN_INVENTORY,
+            y,
+            TEXT_SIZE,
+            &line.tech.inventory_number.to_string(),
+        );
+        doc.text(COLUMN_ROOM, y, TEXT_SIZE, &room_name(line));
+        doc.text_right(RIGHT, y, TEXT_SIZE, &line.tech.price.to_string());
+        for (row, text) in wrapped.iter().enumerate() {
+            doc.text(COLUMN_TECH, y + LINE_HEIGHT * row as f32, TEXT_SIZE, text);
+        }
+        y += height - LINE_HEIGHT;
+    }
+    doc.line(MARGIN, y + 4.0, RIGHT, y + 4.0);
+    advance(&mut doc, &mut y, LINE_HEIGHT);
+    doc.text(
+        COLUMN_NUMBER,
+        y,
+        TEXT_SIZE,
+        &format!("Total, {} item(s)", act.lines.len()),
+    );
+    doc.text_right(RIGHT, y, TEXT_SIZE, &act.total().to_string());
+
+    let signatures = [
+        ("Handed over", act.giver.as_ref().map(|x| x.full_name())),
+        ("Received", Some(act.receiver.full_name())),
+    ];
+    advance(&mut doc, &mut y, LINE_HEIGHT);
+    for (heading, name) in signatures {
+        advance(&mut doc, &mut y, LINE_HEIGHT * 2.5);
+        doc.text(MARGIN, y, TEXT_SIZE, heading);
+        doc.line(COLUMN_TECH, y, COLUMN_ROOM - 10.0, y);
+        if let Some(name) = name {
+            doc.text(COLUMN_ROOM, y, TEXT_SIZE, &name);
+        }
+    }
+
+    Ok(doc.finish())
+}
@@ -0,0 +1,331 @@
+use super::base_page::base_template;
+use super::{downloads, Message};
+use crate::model::dao::audit::{AuditItem, AuditSummary, Finding};
+use crate::model::dao::room::Room;
+use crate::model::dao::unit::Unit;
+use maud::{html, Markup};
+
+pub fn list_page(
+    audits: Vec<AuditSummary>,
+    units: Vec<Unit>,
+    rooms: Vec<Room>,
+    message: Message,
+) -> Markup {
+    let content = html! {
+        h1 { "Stocktaking audits" }
+
+        form action="/open-audit" method="POST" {
+            label for="unit" { "Audit a unit: " }
+            select id="unit" name="unit" required {
+                @for unit in &units {
+                    option value=(unit.id()) { (unit.full_name) }
+                }
+            }
+            " "
+            button type="submit" { "Open audit" }
+        }
+        br;
+        form action="/open-audit" method="POST" {
+            label for="room" { "Audit a room: " }
+            select id="room" name="room" required {
+                @for room in &rooms {
+                    option value=(room.id()) { (room.room_number) }
+                }
+            }
+            " "
+            button type="submit" { "Open audit" }
+        }
+
+        (message.to_html())
+        (downloads("/audits"))
+
+        @if audits.is_empty() {
+            p { "No audits yet." }
+        } @else {
+            table border="1" {
+                thead {
+                    tr {
+                        th { "ID" }
+                        th { "Audited" }
+                        th { "Holdings as of" }
+                        th { "Status" }
+                        th { "Items" }
+                        th { "Not checked" }
+                        th { "Discrepancies" }
+                        th { "Actions" }
+                    }
+                }
+                tbody {
+                    @for summary in &audits {
+                        tr {
+                            td { (summary.audit.id()) }
+                            td { (summary.scope_name) }
+                            td { (summary.audit.audit_date) }
+                            td {
+                                @if let Some(closed_at) = summary.audit.closed_at {
+                                    "Closed on " (closed_at.date_naive())
+                                } @else {
+                                    "Open"
+                                }
+                            }
+                            td { (summary.items) }
+                            td { (summary.pending) }
+                            td { (summary.discrepancies) }
+                            td {
+                                a href=(format!("/audit/{}", summary.audit.id())) {
+                                    button type="button" {
+                                        @if summary.audit.is_closed() { "Report" } @else { "Continue" }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    };
+
+    base_template("Stocktaking audits", content)
+}
+
+/// Where the books placed an item when it was listed.
+fn book_place(item: &AuditItem) -> Markup {
+    html! {
+        @match item.book_room_number {
+            Some(room_number) => { "Room " (room_number) ", " (item.book_holder) }
+            None => { "Not held by anyone" }
+        }
+    }
+}
+
+fn tech_name(item: &AuditItem) -> Markup {
+    html! {
+        @if item.name.is_empty() {
+            em { "Not on the books" }
+        } @else {
+            (item.name) " " (item.model)
+        }
+    }
+}
+
+/// An open audit as a checklist, or the discrepancy report once it is closed.
+/// `items` may carry findings from a rejected submit, so they are not lost.
+pub fn audit_page(summary: &AuditSummary, items: &[AuditItem], message: Message) -> Markup {
+    let audit = &summary.audit;
+    let content = html! {
+        h1 { "Audit of " (summary.scope_name) }
+        p {
+            "Holdings as of " (audit.audit_date) ", opened on " (audit.opened_at.date_naive())
+            @if let Some(closed_at) = audit.closed_at {
+                ", closed on " (closed_at.date_naive())
+            }
+            "."
+        }
+
+        (message.to_html())
+        (downloads(&format!("/audit/{}", audit.id())))
+
+        @if audit.is_closed() {
+            (report(items))
+        } @else {
+            (checklist(summary, items))
+        }
+
+        br;
+        a href="/audits" { "Back to the audits" }
+    };
+
+    base_template("Stocktaking audit", content)
+}
+
+fn checklist(summary: &AuditSummary, items: &[AuditItem]) -> Markup {
+    let id = summary.audit.id();
+    html! {
+        h2 { "On the books" }
+        @if items.iter().any(|x| x.expected) {
+            form action=(format!("/audit/{}", id)) method="POST" {
+                table border="1" {
+                    thead {
+                        tr {
+                            th { "Inventory number" }
+                            th { "Tech" }
+                            th { "Where the books place it" }
+                            th { "Finding" }
+                            th { "Where found" }
+                        }
+                    }
+                    tbody {
+                        @for item in items.iter().filter(|x| x.expected) {
+                            tr {
+                                td { (item.inventory_number) }
+                                td { (tech_name(item)) }
+                                td { (book_place(item)) }
+                                td {
+                                    @for finding in Finding::MARKS {
+                                        label {
+                                            input type="radio" name=(format!("finding-{}", item.id())) value=(finding.as_str()) checked[item.finding == finding];
+                                            " " (finding.label())
+                                        }
+                                        " "
+                                    }
+                                }
+                                td {
+                                    input type="text" name=(format!("location-{}", item.id())) value=(item.location);
+                                }
+                            }
+                        }
+                    }
+                }
+                br;
+                button type="submit" { "Save findings" }
+            }
+        } @else {
+            p { "The books placed nothing here on " (summary.audit.audit_date) "." }
+        }
+
+        h2 { "Not on the list" }
+        (unexpected_table(items, Some(id)))
+        br;
+        form action=(format!("/audit-unexpected/{}", id)) method="POST" {
+            label for="inventory_number" { "Inventory number: " }
+            input type="number" id="inventory_number" name="inventory_number" required;
+            " "
+            label for="location" { "Where found: " }
+            input type="text" id="location" name="location";
+            " "
+            button type="submit" { "Add item" }
+        }
+
+        br;
+        form action=(format!("/close-audit/{}", id)) method="POST" {
+            @if summary.pending > 0 {
+                p { (summary.pending) " item(s) are not checked yet." }
+            }
+            button type="submit" { "Close audit" }
+        }
+    }
+}
+
+/// The unexpected items; with `remove_from` set, each can be taken off that audit.
+fn unexpected_table(items: &[AuditItem], remove_from: Option<i32>) -> Markup {
+    html! {
+        @if items.iter().any(|x| !x.expected) {
+            table border="1" {
+                thead {
+                    tr {
+                        th { "Inventory number" }
+                        th { "Tech" }
+                        th { "Where the books place it" }
+                        th { "Where found" }
+                        @if remove_from.is_some() {
+                            th { "Actions" }
+                        }
+                    }
+                }
+                tbody {
+                    @for item in items.iter().filter(|x| !x.expected) {
+                        tr {
+                            td { (item.inventory_number) }
+                            td { (tech_name(item)) }
+                            td { (book_place(item)) }
+                            td { (item.location) }
+                            @if let Some(audit_id) = remove_from {
+                                td {
+                                    form action=(format!("/remove-audit-item/{}/{}", audit_id, item.id())) method="POST" {
+                                        button type="submit" { "Remove" }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        } @else {
+            p { "No items found beyond the list." }
+        }
+    }
+}
+
+fn report(items: &[AuditItem]) -> Markup {
+    let expected: Vec<&AuditItem> = items.iter().filter(|x| x.expected).collect();
+    let count = |finding: Finding| expected.iter().filter(|x| x.finding == finding).count();
+    let missing: Vec<&&AuditItem> = expected
+        .iter()
+        .filter(|x| x.finding == Finding::Missing)
+        .collect();
+    let elsewhere: Vec<&&AuditItem> = expected
+        .iter()
+        .filter(|x| x.finding == Finding::FoundElsewhere)
+        .collect();
+    let unexpected = items.iter().filter(|x| !x.expected).count();
+
+    html! {
+        h2 { "Discrepancy report" }
+        table border="1" {
+            tbody {
+                tr { th { "On the books" } td { (expected.len()) } }
+                tr { th { (Finding::Found.label()) } td { (count(Finding::Found)) } }
+                tr { th { (Finding::Missing.label()) } td { (count(Finding::Missing)) } }
+                tr { th { (Finding::FoundElsewhere.label()) } td { (count(Finding::FoundElsewhere)) } }
+                tr { th { "Not on the list" } td { (unexpected) } }
+            }
+        }
+
+        @if !items.iter().any(|x| x.is_discrepancy()) {
+            p { "No discrepancies: everything on the books was found where expected." }
+        }
+
+        @if !missing.is_empty() {
+            h3 { "Missing" }
+            table border="1" {
+                thead {
+                    tr {
+                        th { "Inventory number" }
+                        th { "Tech" }
+                        th { "Where the books place it" }
+                        th { "Note" }
+                    }
+                }
+                tbody {
+                    @for item in &missing {
+                        tr {
+                            td { (item.inventory_number) }
+                            td { (tech_name(item)) }
+                            td { (book_place(item)) }
+                            td { (item.location) }
+                        }
+                    }
+                }
+            }
+        }
+
+        @if !elsewhere.is_empty() {
+            h3 { "Found elsewhere" }
+            table border="1" {
+                thead {
+                    tr {
+                        th { "Inventory number" }
+                        th { "Tech" }
+                        th { "Where the books place it" }
+                        th { "Where found" }
+                    }
+                }
+                tbody {
+                    @for item in &elsewhere {
+                        tr {
+                            td { (item.inventory_number) }
+                            td { (tech_name(item)) }
+                            td { (book_place(item)) }
+                            td { (item.location) }
+                        }
+                    }
+                }
+            }
+        }
+
+        @if unexpected > 0 {
+            h3 { "Not on the list" }
+            (unexpected_table(items, None))
+        }
+    }
+}
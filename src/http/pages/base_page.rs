@@ -1,65 +1,15 @@
-use maud::{html, Markup, DOCTYPE};
+use maud::{html, Markup, PreEscaped, DOCTYPE};
+
+const NOT_FOUND_PAGE: &str = "404.html";
 
 pub fn base_template(title: &str, content: Markup) -> Markup {
     html! {
         (DOCTYPE)
         html {
             head {
+                meta charset="utf-8";
                 title { (title) }
-                style {
-                    r#"
-                    body {
-                        font-family: Arial, sans-serif;
-                        background-color: #f4f4f4;
-                        margin: 0;
-                        padding: 0;
-                    }
-
-                    header {
-                        background-color: #333;
-                        color: white;
-                        padding: 10px 0;
-                        text-align: center;
-                    }
-
-                    nav a {
-                        color: white;
-                        text-decoration: none;
-                        margin: 0 15px;
-                    }
-
-                    nav a:hover {
-                        text-decoration: underline;
-                    }
-
-                    main {
-                        padding: 20px;
-                    }
-
-                    table {
-                        width: 100%;
-                        border-collapse: collapse;
-                        margin-top: 20px;
-                    }
-
-                    table, th, td {
-                        border: 1px solid #ddd;
-                    }
-
-                    th, td {
-                        padding: 8px;
-                        text-align: left;
-                    }
-
-                    th {
-                        background-color: #f2f2f2;
-                    }
-
-                    tr:hover {
-                        background-color: #f5f5f5;
-                    }
-                    "#
-                }
+                link rel="stylesheet" href="/static/css/styles.css";
             }
             body {
                 header {
@@ -69,9 +19,17 @@ pub fn base_template(title: &str, content: Markup) -> Markup {
                         a href="/tech" { "Tech" }
                         a href="/unit" { "Unit" }
                         a href="/employee" { "Employee" }
+                        a href="/room" { "Room" }
+                        a href="/category" { "Category" }
                         a href="/transfer" { "Transfer" }
+                        a href="/upcoming" { "Upcoming" }
                         a href="/unit-tech" { "Unit tech" }
                         a href="/employee-tech" { "Employee tech" }
+                        a href="/holdings" { "Holdings" }
+                        a href="/book-value" { "Book value" }
+                        a href="/write-offs" { "Write-offs" }
+                        a href="/audits" { "Audits" }
+                        a href="/import" { "Import" }
                     }
                 }
                 main {
@@ -82,6 +40,14 @@ pub fn base_template(title: &str, content: Markup) -> Markup {
     }
 }
 
+/// Serves the designers' `404.html` when it exists in the working directory.
+pub fn not_found() -> Markup {
+    match std::fs::read_to_string(NOT_FOUND_PAGE) {
+        Ok(page) => PreEscaped(page),
+        Err(_) => base_error_template("Not found", "Not found"),
+    }
+}
+
 pub fn method_not_allowed() -> Markup {
     base_error_template("Method not allowed", "Method not allowed")
 }
@@ -91,8 +57,10 @@ pub fn base_error_template(title: &str, error_name: &str) -> Markup {
         (DOCTYPE)
         html {
             head {
+                meta charset="utf-8";
                 title { (title) }
                 link rel="stylesheet" href="https://maxcdn.bootstrapcdn.com/bootstrap/4.5.2/css/bootstrap.min.css";
+                link rel="stylesheet" href="/static/css/styles.css";
             }
             body {
                 header {
@@ -102,9 +70,17 @@ pub fn base_error_template(title: &str, error_name: &str) -> Markup {
                         a href="/tech" { "Tech" }
                         a href="/unit" { "Unit" }
                         a href="/employee" { "Employee" }
+                        a href="/room" { "Room" }
+                        a href="/category" { "Category" }
                         a href="/transfer" { "Transfer" }
+                        a href="/upcoming" { "Upcoming" }
                         a href="/unit-tech" { "Unit tech" }
                         a href="/employee-tech" { "Employee tech" }
+                        a href="/holdings" { "Holdings" }
+                        a href="/book-value" { "Book value" }
+                        a href="/write-offs" { "Write-offs" }
+                        a href="/audits" { "Audits" }
+                        a href="/import" { "Import" }
                     }
                 }
                 main {
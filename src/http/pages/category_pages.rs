@@ -0,0 +1,126 @@
+use super::base_page::base_template;
+use super::{downloads, Message};
+use crate::model::dao::category::{DepreciationMethod, TechCategory};
+use maud::{html, Markup};
+
+pub fn table_page(category_list: Vec<TechCategory>) -> Markup {
+    let content = html! {
+        h1 { "Tech categories" }
+
+        a href="/add-category" {
+            button type="button" { "Add Category" }
+        }
+        (downloads("/category"))
+
+        table border="1" {
+            thead {
+                tr {
+                    th { "ID" }
+                    th { "Name" }
+                    th { "Depreciation" }
+                    th { "Useful life, years" }
+                    th { "Actions" }
+                }
+            }
+            tbody {
+                @for category in category_list {
+                    tr {
+                        td { (category.id()) }
+                        td { (category.name) }
+                        td { (category.depreciation.method.label()) }
+                        td { (category.depreciation.useful_life_years) }
+                        td {
+                            form action={(format!("/delete-category/{}", category.id()))} method="POST" {
+                                button type="submit" { "Delete" }
+                            }
+
+                            form action={(format!("/edit-category/{}", category.id()))} method="GET" {
+                                button type="submit" { "Edit" }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    };
+
+    base_template("Tech categories", content)
+}
+
+fn method_select(selected: Option<DepreciationMethod>) -> Markup {
+    html! {
+        label for="method" { "Depreciation:" }
+        select id="method" name="method" required {
+            @for method in DepreciationMethod::ALL {
+                option value=(method.as_str()) selected[selected == Some(method)] {
+                    (method.label())
+                }
+            }
+        }
+    }
+}
+
+pub fn add_form_get() -> Markup {
+    input_form_add(Message::none().to_html())
+}
+
+pub fn add_form_post(message: Message) -> Markup {
+    input_form_add(message.to_html())
+}
+
+fn input_form_add(message: Markup) -> Markup {
+    let content = html! {
+        h1 { "Add new Category" }
+        form action="/add-category" method="POST" {
+            label for="name" { "Name:" }
+            input type="text" id="name" name="name" required;
+            br;
+            (method_select(None))
+            br;
+            label for="useful_life_years" { "Useful life, years:" }
+            input type="number" id="useful_life_years" name="useful_life_years" min="1" required;
+            br;
+            button type="submit" { "Add Category" }
+        }
+
+        (message)
+        br; br;
+
+        a href="/category" { "Back to Category List" }
+    };
+    base_template("Add category", content)
+}
+
+pub fn edit_form_get(category: &TechCategory) -> Markup {
+    input_form_edit(category, Message::none().to_html())
+}
+
+pub fn edit_form_post(category: &TechCategory, message: Message) -> Markup {
+    input_form_edit(category, message.to_html())
+}
+
+fn input_form_edit(category: &TechCategory, message: Markup) -> Markup {
+    let content = html! {
+        h1 { "Edit Category" }
+
+        form action=({format!("/edit-category/{}", category.id())}) method="POST" {
+            label for="name" { "Name:" }
+            input type="text" id="name" name="name" value=(category.name) required;
+            br; br;
+
+            (method_select(Some(category.depreciation.method)))
+            br; br;
+
+            label for="useful_life_years" { "Useful life, years:" }
+            input type="number" id="useful_life_years" name="useful_life_years" value=(category.depreciation.useful_life_years) min="1" required;
+            br; br;
+
+            button type="submit" { "Save Changes" }
+        }
+        (message)
+        br; br;
+
+        a href="/category" { "Back to Category List" }
+    };
+    base_template("Edit category", content)
+}
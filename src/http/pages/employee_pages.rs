@@ -1,16 +1,32 @@
 use super::base_page::base_template;
-use super::Message;
+use super::{archived_badge, downloads, Message};
 use crate::model::dao::employee::Employee;
+use crate::model::dao::tech::TechEmployee;
+use crate::model::dao::transfer::TransferDetails;
 use crate::model::dao::unit::Unit;
 use maud::{html, Markup};
 
-pub fn table_page(employee_list: Vec<Employee>) -> Markup {
+/// The current employees, or with `archived` set, the employees that have been archived.
+pub fn table_page(employee_list: Vec<Employee>, archived: bool) -> Markup {
     let content = html! {
-        h1 { "Employee list" }
+        @if archived {
+            h1 { "Archived employees" }
+
+            a href="/employee" {
+                button type="button" { "Show Current Employees" }
+            }
+        } @else {
+            h1 { "Employee list" }
 
-        a href="/add-employee" {
-            button type="button" { "Add Employee" }
+            a href="/add-employee" {
+                button type="button" { "Add Employee" }
+            }
+            " "
+            a href="/employee?archived=true" {
+                button type="button" { "Show Archived" }
+            }
         }
+        (downloads(if archived { "/employee?archived=true" } else { "/employee" }))
 
         table border="1" {
             thead {
@@ -22,6 +38,9 @@ pub fn table_page(employee_list: Vec<Employee>) -> Markup {
                     th { "Is supervisor" }
                     th { "Is accountable" }
                     th { "Job title" }
+                    @if archived {
+                        th { "Archived On" }
+                    }
                     th { "Actions" }
                 }
             }
@@ -35,14 +54,26 @@ pub fn table_page(employee_list: Vec<Employee>) -> Markup {
                         td { (employee.is_supervisor) }
                         td { (employee.is_accountable) }
                         td { (employee.job_title) }
+                        @if let Some(archived_at) = employee.archived_at {
+                            td { (archived_at.date_naive()) }
+                        }
                         td {
-                            form action={(format!("/delete-employee/{}", employee.id()))} method="POST" {
-                                input type="hidden" name="_method" value="DELETE" ;
+                            form action={(format!("/delete-employee/{}", employee.id()))} method="GET" {
                                 button type="submit" { "Delete" }
                             }
 
-                            form action={(format!("/edit-employee/{}", employee.id()))} method="GET" {
-                                button type="submit" { "Edit" }
+                            @if archived {
+                                form action={(format!("/restore-employee/{}", employee.id()))} method="POST" {
+                                    button type="submit" { "Restore" }
+                                }
+                            } @else {
+                                form action={(format!("/edit-employee/{}", employee.id()))} method="GET" {
+                                    button type="submit" { "Edit" }
+                                }
+
+                                form action={(format!("/archive-employee/{}", employee.id()))} method="POST" {
+                                    button type="submit" { "Archive" }
+                                }
                             }
                         }
                     }
@@ -151,6 +182,7 @@ fn input_form_edit(employee: &Employee, units: Vec<Unit>, message: Markup) -> Ma
                 @for unit in units {
                     option value=(unit.id()) selected[unit.id() == employee.unit_id]{
                         (unit.full_name)
+                        @if unit.is_archived() { " (archived)" }
                     }
                 }
             }
@@ -225,3 +257,117 @@ pub fn add_form_get(units: Vec<Unit>) -> Markup {
 pub fn add_form_post(units: Vec<Unit>, message: Message) -> Markup {
     input_form_add(units, html! {(message.content)})
 }
+
+pub fn delete_page(
+    employee: &Employee,
+    transfers: Vec<TransferDetails>,
+    held: Vec<TechEmployee>,
+    candidates: Vec<Employee>,
+    message: Message,
+) -> Markup {
+    let content = html! {
+        h1 { "Delete employee" }
+        p {
+            (employee.last_name) " " (employee.first_name) " " (employee.middle_name) ", " (employee.job_title)
+            (archived_badge(employee.is_archived()))
+        }
+
+        @if transfers.is_empty() {
+            p { "No transfers refer to this employee." }
+            form action=(format!("/delete-employee/{}", employee.id())) method="POST" {
+                button type="submit" { "Delete" }
+            }
+        } @else {
+            @if !held.is_empty() {
+                h2 { "Tech currently held" }
+                table border="1" {
+                    thead {
+                        tr {
+                            th { "Inventory number" }
+                            th { "Name" }
+                            th { "Model" }
+                            th { "Since" }
+                        }
+                    }
+                    tbody {
+                        @for tech in held {
+                            tr {
+                                td { (tech.inventory_number) }
+                                td { (tech.name) }
+                                td { (tech.model) }
+                                td { (tech.date) }
+                            }
+                        }
+                    }
+                }
+            }
+
+            h2 { "Transfers (" (transfers.len()) ")" }
+            table border="1" {
+                thead {
+                    tr {
+                        th { "Date" }
+                        th { "Tech" }
+                        th { "Room" }
+                    }
+                }
+                tbody {
+                    @for transfer in &transfers {
+                        tr {
+                            td { (transfer.transfer_date) }
+                            td {
+                                @match &transfer.tech {
+                                    Some(tech) => (tech.inventory_number) " " (tech.name),
+                                    None => "No tech data",
+                                }
+                            }
+                            td {
+                                @match &transfer.room {
+                                    Some(room) => (room.room_number),
+                                    None => "No room data",
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            br;
+            @if !employee.is_archived() {
+                p { "To keep the employee in the records, archive them instead." }
+                form action=(format!("/archive-employee/{}", employee.id())) method="POST" {
+                    button type="submit" { "Archive" }
+                }
+                br;
+            }
+            @if candidates.is_empty() {
+                p { "There is no other accountable employee in the unit to take the tech over." }
+            } @else {
+                p {
+                    "The employee cannot be deleted while these transfers refer to them. "
+                    "The tech they hold can go to another accountable employee of the unit with a transfer dated today; "
+                    "the past transfers stay as they are, and the employee is archived."
+                }
+                form action=(format!("/delete-employee/{}", employee.id())) method="POST" {
+                    label for="hand_over_to" { "Hand over to: " }
+                    select id="hand_over_to" name="hand_over_to" required {
+                        @for candidate in candidates {
+                            option value=(candidate.id()) {
+                                (candidate.last_name) " " (candidate.first_name) " " (candidate.middle_name)
+                            }
+                        }
+                    }
+                    " "
+                    button type="submit" { "Hand over and archive" }
+                }
+            }
+        }
+
+        (message.to_html())
+
+        br;
+        a href="/employee" { "Back to Employee List" }
+    };
+
+    base_template("Delete employee", content)
+}
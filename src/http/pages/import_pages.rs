@@ -0,0 +1,163 @@
+use super::base_page::base_template;
+use super::Message;
+use crate::model::import::{CsvTable, ImportKind, Mapping, Preview};
+use maud::{html, Markup};
+
+fn columns(kind: ImportKind) -> Markup {
+    html! {
+        @for (index, field) in kind.fields().iter().enumerate() {
+            @if index > 0 { ", " }
+            (field.label)
+            @if field.required { "*" }
+        }
+    }
+}
+
+pub fn upload_page(message: Message) -> Markup {
+    let content = html! {
+        h1 { "Import from CSV" }
+        p {
+            "The first row of the file names the columns. Starred columns are required; "
+            "columns can also be matched by hand on the next page. Nothing is saved until "
+            "every row checks out."
+        }
+        ul {
+            @for kind in ImportKind::ALL {
+                li { b { (kind.label()) ": " } (columns(kind)) }
+            }
+        }
+        p {
+            "Dates are written as 2024-03-01 or 01.03.2024. The currency defaults to RUB. "
+            "An employee is either a supervisor or an accountable: answer yes in one of "
+            "the two columns."
+        }
+
+        form action="/import" method="POST" enctype="multipart/form-data" {
+            label for="kind" { "Import: " }
+            select id="kind" name="kind" required {
+                @for kind in ImportKind::ALL {
+                    option value=(kind.as_str()) { (kind.label()) }
+                }
+            }
+            br;
+            label for="file" { "CSV file: " }
+            input type="file" id="file" name="file" accept=".csv,text/csv" required;
+            br;
+            button type="submit" { "Preview" }
+        }
+
+        (message.to_html())
+    };
+
+    base_template("Import", content)
+}
+
+fn mapping_select(table: &CsvTable, name: &str, selected: Option<usize>) -> Markup {
+    html! {
+        select name=(name) {
+            option value="" selected[selected.is_none()] { "Not in the file" }
+            @for (index, header) in table.headers.iter().enumerate() {
+                option value=(index) selected[selected == Some(index)] {
+                    (index + 1) ". " (header)
+                }
+            }
+        }
+    }
+}
+
+/// The dry run of an import. The file travels back with the form, so the rows can
+/// be checked again with another mapping or imported without uploading it again.
+pub fn preview_page(
+    preview: &Preview,
+    table: &CsvTable,
+    csv: &str,
+    mapping: &Mapping,
+    message: Message,
+) -> Markup {
+    let kind = preview.kind;
+    let fields = kind.fields();
+    let error_count = preview.error_count();
+    let content = html! {
+        h1 { "Import preview: " (kind.label()) }
+
+        (message.to_html())
+
+        p {
+            (preview.rows.len()) " row(s) read, "
+            @if error_count == 0 {
+                "no problems found."
+            } @else {
+                (error_count) " problem(s) to fix before importing."
+            }
+        }
+        @if !preview.errors.is_empty() {
+            ul {
+                @for error in &preview.errors {
+                    li { (error) }
+                }
+            }
+        }
+
+        form action="/import" method="POST" {
+            input type="hidden" name="kind" value=(kind.as_str());
+            textarea name="csv" hidden { (csv) }
+
+            h2 { "Columns" }
+            table border="1" {
+                thead {
+                    tr {
+                        th { "Field" }
+                        th { "Read from column" }
+                    }
+                }
+                tbody {
+                    @for (field, column) in fields.iter().zip(mapping) {
+                        tr {
+                            td { (field.label) @if field.required { "*" } }
+                            td { (mapping_select(table, &format!("map-{}", field.key), *column)) }
+                        }
+                    }
+                }
+            }
+            br;
+            button type="submit" name="action" value="preview" { "Check again" }
+            " "
+            button type="submit" name="action" value="import" disabled[!preview.is_valid()] {
+                "Import " (preview.rows.len()) " row(s)"
+            }
+
+            h2 { "Rows" }
+            table border="1" {
+                thead {
+                    tr {
+                        th { "Line" }
+                        @for field in fields {
+                            th { (field.label) }
+                        }
+                        th { "Problems" }
+                    }
+                }
+                tbody {
+                    @for row in &preview.rows {
+                        tr {
+                            td { (row.line) }
+                            @for value in &row.values {
+                                td { (value) }
+                            }
+                            td {
+                                @for error in &row.errors {
+                                    div class="field-error" { (error) }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        br;
+        a href="/import" { "Upload another file" }
+    };
+
+    base_template("Import preview", content)
+}
@@ -1,10 +1,17 @@
+pub mod act_pages;
+pub mod audit_pages;
 pub mod base_page;
+pub mod category_pages;
 pub mod employee_pages;
+pub mod import_pages;
+pub mod room_pages;
 pub mod tech_pages;
 pub mod transfer_pages;
 pub mod unit_pages;
 pub mod welcome;
+pub mod write_off_pages;
 use crate::model::dao::employee::Employee;
+use crate::model::validation::FieldErrors;
 use chrono::NaiveDate;
 use maud::{html, Markup};
 
@@ -59,13 +66,48 @@ impl Message {
     }
 }
 
-fn get_elem_class(message_kind: MessageKind) -> String {
-    match message_kind {
-        MessageKind::Success => "success".into(),
-        MessageKind::Notify => "notify".into(),
-        MessageKind::Error => "error".into(),
-        MK::None => "".into()
+pub use MessageKind as MK;
+
+/// A GET form that reloads `action` for another day.
+pub fn date_filter(action: &str, date: NaiveDate) -> Markup {
+    html! {
+        form action=(action) method="GET" class="date-filter" {
+            label for="date" { "As of: " }
+            input type="date" id="date" name="date" value=(date) required;
+            button type="submit" { "Show" }
+        }
     }
 }
 
-pub use MessageKind as MK;
+/// Links that download the table on the page; `href` is the page with its filters.
+pub fn downloads(href: &str) -> Markup {
+    let separator = if href.contains('?') { '&' } else { '?' };
+    html! {
+        p class="downloads" {
+            "Download: "
+            a href=(format!("{}{}format=csv", href, separator)) { "CSV" }
+            " "
+            a href=(format!("{}{}format=csv&bom=true", href, separator)) { "CSV for Excel" }
+            " "
+            a href=(format!("{}{}format=xlsx", href, separator)) { "Excel" }
+        }
+    }
+}
+
+/// Marks an archived tech, employee or unit where its history is still shown.
+pub fn archived_badge(archived: bool) -> Markup {
+    html! {
+        @if archived {
+            " " span class="badge-archived" { "archived" }
+        }
+    }
+}
+
+/// The validation message for one form field, shown right under it.
+pub fn field_error(errors: &FieldErrors, field: &str) -> Markup {
+    html! {
+        @if let Some(message) = errors.get(field) {
+            div class="field-error" { (message) }
+        }
+    }
+}
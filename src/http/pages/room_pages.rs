@@ -0,0 +1,107 @@
+use super::base_page::base_template;
+use super::{downloads, Message};
+use crate::model::dao::room::Room;
+use maud::{html, Markup};
+
+pub fn table_page(room_list: Vec<Room>) -> Markup {
+    let content = html! {
+        h1 { "Room list" }
+
+        a href="/add-room" {
+            button type="button" { "Add Room" }
+        }
+        (downloads("/room"))
+
+        table border="1" {
+            thead {
+                tr {
+                    th { "ID" }
+                    th { "Room number" }
+                    th { "Squares" }
+                    th { "Actions" }
+                }
+            }
+            tbody {
+                @for room in room_list {
+                    tr {
+                        td { (room.id()) }
+                        td { (room.room_number) }
+                        td { (room.squares) }
+                        td {
+                            form action={(format!("/delete-room/{}", room.id()))} method="POST" {
+                                input type="hidden" name="_method" value="DELETE" ;
+                                button type="submit" { "Delete" }
+                            }
+
+                            form action={(format!("/edit-room/{}", room.id()))} method="GET" {
+                                button type="submit" { "Edit" }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    };
+
+    base_template("Room", content)
+}
+
+pub fn add_form_get() -> Markup {
+    input_form_add(Message::none().to_html())
+}
+
+pub fn add_form_post(message: Message) -> Markup {
+    input_form_add(message.to_html())
+}
+
+fn input_form_add(message: Markup) -> Markup {
+    let content = html! {
+        h1 { "Add new Room" }
+        form action="/add-room" method="POST" {
+            label for="room_number" { "Room number:" }
+            input type="number" id="room_number" name="room_number" required;
+            br;
+            label for="squares" { "Squares:" }
+            input type="number" id="squares" name="squares" min="1" required;
+            br;
+            button type="submit" { "Add Room" }
+        }
+
+        (message)
+        br; br;
+
+        a href="/room" { "Back to Room List" }
+    };
+    base_template("Add room", content)
+}
+
+pub fn edit_form_get(room: &Room) -> Markup {
+    input_form_edit(room, Message::none().to_html())
+}
+
+pub fn edit_form_post(room: &Room, message: Message) -> Markup {
+    input_form_edit(room, message.to_html())
+}
+
+fn input_form_edit(room: &Room, message: Markup) -> Markup {
+    let content = html! {
+        h1 { "Edit Room" }
+
+        form action=({format!("/edit-room/{}", room.id())}) method="POST" {
+            label for="room_number" { "Room number:" }
+            input type="number" id="room_number" name="room_number" value=(room.room_number) required;
+            br; br;
+
+            label for="squares" { "Squares:" }
+            input type="number" id="squares" name="squares" value=(room.squares) min="1" required;
+            br; br;
+
+            button type="submit" { "Save Changes" }
+        }
+        (message)
+        br; br;
+
+        a href="/room" { "Back to Room List" }
+    };
+    base_template("Edit room", content)
+}
@@ -1,15 +1,42 @@
 use super::base_page::base_template;
-use super::Message;
-use crate::model::dao::tech::{Tech, TechEmployee, TechUnit};
+use super::{archived_badge, date_filter, downloads, Message};
+use crate::model::dao::category::{self, TechCategory};
+use crate::model::dao::tech::{Tech, TechEmployee, TechHolding, TechUnit};
+use crate::model::dao::transfer::TransferDetails;
+use crate::model::dao::write_off::WriteOffDetails;
+use crate::model::money::{Currency, Totals};
+use chrono::NaiveDate;
 use maud::{html, Markup};
 
-pub fn table_page(tech_list: Vec<Tech>) -> Markup {
+fn written_off_badge(tech: &Tech) -> Markup {
+    html! {
+        @if let Some(date) = tech.written_off_on {
+            " " span class="badge-written-off" title=(format!("Written off on {}", date)) { "written off" }
+        }
+    }
+}
+
+/// The tech in use, or with `archived` set, the tech that has been archived.
+pub fn table_page(tech_list: Vec<Tech>, archived: bool) -> Markup {
     let content = html! {
-        h1 { "Tech list" }
+        @if archived {
+            h1 { "Archived tech" }
+
+            a href="/tech" {
+                button type="button" { "Show Tech In Use" }
+            }
+        } @else {
+            h1 { "Tech list" }
 
-        a href="/add-tech" {
-            button type="button" { "Add Tech" }
+            a href="/add-tech" {
+                button type="button" { "Add Tech" }
+            }
+            " "
+            a href="/tech?archived=true" {
+                button type="button" { "Show Archived" }
+            }
         }
+        (downloads(if archived { "/tech?archived=true" } else { "/tech" }))
 
         table border="1" {
             thead {
@@ -19,6 +46,9 @@ pub fn table_page(tech_list: Vec<Tech>) -> Markup {
                     th { "Name" }
                     th { "Model" }
                     th { "Acquisition Date" }
+                    @if archived {
+                        th { "Archived On" }
+                    }
                     th { "Actions" }
                 }
             }
@@ -27,17 +57,38 @@ pub fn table_page(tech_list: Vec<Tech>) -> Markup {
                     tr {
                         td { (tech.id()) }
                         td { (tech.inventory_number) }
-                        td { (tech.name) }
+                        td {
+                            a href=(format!("/tech/{}", tech.id())) { (tech.name) }
+                            (written_off_badge(&tech))
+                        }
                         td { (tech.model) }
                         td { (tech.acquisition_date) }
+                        @if let Some(archived_at) = tech.archived_at {
+                            td { (archived_at.date_naive()) }
+                        }
                         td {
-                            form action={(format!("/delete-tech/{}", tech.id()))} method="POST" {
-                                input type="hidden" name="_method" value="DELETE" ;
+                            form action={(format!("/delete-tech/{}", tech.id()))} method="GET" {
                                 button type="submit" { "Delete" }
                             }
 
-                            form action={(format!("/edit-tech/{}", tech.id()))} method="GET" {
-                                button type="submit" { "Edit" }
+                            @if archived {
+                                form action={(format!("/restore-tech/{}", tech.id()))} method="POST" {
+                                    button type="submit" { "Restore" }
+                                }
+                            } @else {
+                                form action={(format!("/edit-tech/{}", tech.id()))} method="GET" {
+                                    button type="submit" { "Edit" }
+                                }
+
+                                form action={(format!("/archive-tech/{}", tech.id()))} method="POST" {
+                                    button type="submit" { "Archive" }
+                                }
+
+                                @if !tech.is_written_off() {
+                                    form action={(format!("/write-off-tech/{}", tech.id()))} method="GET" {
+                                        button type="submit" { "Write off" }
+                                    }
+                                }
                             }
                         }
                     }
@@ -49,10 +100,171 @@ pub fn table_page(tech_list: Vec<Tech>) -> Markup {
     base_template("Tech", content)
 }
 
+/// How long the item stayed with the holder of `transfers[index]`: until the next
+/// transfer, until it was written off, or until today for the one it is still with.
+fn days_held(
+    transfers: &[TransferDetails],
+    index: usize,
+    today: NaiveDate,
+    written_off_on: Option<NaiveDate>,
+) -> String {
+    let date = transfers[index].transfer_date;
+    match transfers.get(index + 1) {
+        Some(next) if next.transfer_date <= today => {
+            (next.transfer_date - date).num_days().to_string()
+        }
+        _ if date > today => "Scheduled".to_string(),
+        _ => match written_off_on.filter(|x| *x <= today) {
+            Some(end) => (end - date).num_days().to_string(),
+            None => format!("{} (current)", (today - date).num_days()),
+        },
+    }
+}
 
-pub fn table_page_view_employee(tech_list: Vec<TechEmployee>) -> Markup {
+/// `date` is the day the book value is shown for.
+pub fn detail_page(
+    tech: &Tech,
+    category: Option<TechCategory>,
+    transfers: Vec<TransferDetails>,
+    write_off: Option<WriteOffDetails>,
+    date: NaiveDate,
+    today: NaiveDate,
+) -> Markup {
+    let depreciation = category.as_ref().map(|x| x.depreciation);
     let content = html! {
-        h1 { "Tech by employee" }
+        h1 { (tech.name) " " (tech.model) (archived_badge(tech.is_archived())) (written_off_badge(tech)) }
+        (date_filter(&format!("/tech/{}", tech.id()), date))
+
+        table border="1" {
+            tbody {
+                tr { th { "Inventory number" } td { (tech.inventory_number) } }
+                tr { th { "Name" } td { (tech.name) } }
+                tr { th { "Model" } td { (tech.model) } }
+                tr { th { "Acquisition date" } td { (tech.acquisition_date) } }
+                tr { th { "Price" } td { (tech.price) } }
+                tr {
+                    th { "Category" }
+                    td {
+                        @match &category {
+                            Some(category) => {
+                                (category.name) ", " (category.depreciation.method.label())
+                                " over " (category.depreciation.useful_life_years) " year(s)"
+                            }
+                            None => "None, the item does not depreciate",
+                        }
+                    }
+                }
+                tr {
+                    th { "Book value on " (date) }
+                    td {
+                        @if tech.written_off_on.is_some_and(|x| x <= date) {
+                            "Written off"
+                        } @else {
+                            (category::book_value(depreciation, tech.price, tech.acquisition_date, date))
+                        }
+                    }
+                }
+                @if let Some(archived_at) = tech.archived_at {
+                    tr { th { "Archived on" } td { (archived_at.date_naive()) } }
+                }
+            }
+        }
+
+        h2 { "Movement history" }
+        @if transfers.is_empty() {
+            p { "This item has never been transferred." }
+        } @else {
+            table border="1" {
+                thead {
+                    tr {
+                        th { "Date" }
+                        th { "Employee" }
+                        th { "Unit" }
+                        th { "Room" }
+                        th { "Days held" }
+                    }
+                }
+                tbody {
+                    @for (index, transfer) in transfers.iter().enumerate() {
+                        tr {
+                            td { (transfer.transfer_date) }
+                            td {
+                                @match &transfer.employee {
+                                    Some(employee) => {
+                                        a href=(format!("/view-employee-tech/{}", employee.id())) {
+                                            (employee.last_name) " " (employee.first_name) " " (employee.middle_name)
+                                        }
+                                        (archived_badge(employee.is_archived()))
+                                    }
+                                    None => "No employee data",
+                                }
+                            }
+                            td {
+                                @match &transfer.unit {
+                                    Some(unit) => {
+                                        (unit.full_name)
+                                        (archived_badge(unit.is_archived()))
+                                    }
+                                    None => "No unit data",
+                                }
+                            }
+                            td {
+                                @match &transfer.room {
+                                    Some(room) => (room.room_number),
+                                    None => "No room data",
+                                }
+                            }
+                            td { (days_held(&transfers, index, today, tech.written_off_on)) }
+                        }
+                    }
+                }
+            }
+        }
+
+        @if let Some(entry) = write_off {
+            h2 { "Write-off" }
+            table border="1" {
+                tbody {
+                    tr { th { "Act number" } td { (entry.write_off.act_number) } }
+                    tr { th { "Date" } td { (entry.write_off.write_off_date) } }
+                    tr { th { "Disposal" } td { (entry.write_off.disposal.label()) } }
+                    tr { th { "Reason" } td { (entry.write_off.reason) } }
+                    tr {
+                        th { "Signed by" }
+                        td {
+                            (entry.employee.last_name) " " (entry.employee.first_name) " " (entry.employee.middle_name)
+                            (archived_badge(entry.employee.is_archived()))
+                        }
+                    }
+                    tr { th { "Residual value" } td { (entry.residual_value()) } }
+                }
+            }
+        }
+
+        br;
+        a href=(format!("/edit-tech/{}", tech.id())) { "Edit" }
+        @if !tech.is_written_off() {
+            " | "
+            a href=(format!("/write-off-tech/{}", tech.id())) { "Write off" }
+        }
+        " | "
+        a href="/tech" { "Back to Tech List" }
+    };
+
+    base_template("Tech details", content)
+}
+
+
+pub fn table_page_view_employee(
+    tech_list: Vec<TechEmployee>,
+    employee_id: i32,
+    date: NaiveDate,
+) -> Markup {
+    let total_price: Totals = tech_list.iter().map(|x| x.price).collect();
+    let content = html! {
+        h1 { "Tech by employee on " (date) }
+        (date_filter(&format!("/view-employee-tech/{}", employee_id), date))
+        (downloads(&format!("/view-employee-tech/{}?date={}", employee_id, date)))
 
         table border="1" {
             thead {
@@ -65,6 +277,7 @@ pub fn table_page_view_employee(tech_list: Vec<TechEmployee>) -> Markup {
                     th { "Inventory number" }
                     th { "Name" }
                     th { "Model" }
+                    th { "Price" }
                 }
             }
             tbody {
@@ -78,18 +291,28 @@ pub fn table_page_view_employee(tech_list: Vec<TechEmployee>) -> Markup {
                         td { (tech.inventory_number) }
                         td { (tech.name) }
                         td { (tech.model) }
+                        td { (tech.price) }
                     }
                 }
             }
+            tfoot {
+                tr {
+                    th colspan="8" { "Total" }
+                    th { (total_price) }
+                }
+            }
         }
     };
 
     base_template("Tech by employee", content)
 }
 
-pub fn table_page_view_unit(tech_list: Vec<TechUnit>) -> Markup {
+pub fn table_page_view_unit(tech_list: Vec<TechUnit>, unit_id: i32, date: NaiveDate) -> Markup {
+    let total_price: Totals = tech_list.iter().map(|x| x.price).collect();
     let content = html! {
-        h1 { "Tech by unit" }
+        h1 { "Tech by unit on " (date) }
+        (date_filter(&format!("/view-unit-tech/{}", unit_id), date))
+        (downloads(&format!("/view-unit-tech/{}?date={}", unit_id, date)))
 
         table border="1" {
             thead {
@@ -99,6 +322,7 @@ pub fn table_page_view_unit(tech_list: Vec<TechUnit>) -> Markup {
                     th { "Inventory number" }
                     th { "Name" }
                     th { "Model" }
+                    th { "Price" }
                 }
             }
             tbody {
@@ -109,18 +333,93 @@ pub fn table_page_view_unit(tech_list: Vec<TechUnit>) -> Markup {
                         td { (tech.inventory_number) }
                         td { (tech.name) }
                         td { (tech.model) }
+                        td { (tech.price) }
                     }
                 }
             }
+            tfoot {
+                tr {
+                    th colspan="5" { "Total" }
+                    th { (total_price) }
+                }
+            }
         }
     };
 
     base_template("Tech by unit", content)
 }
 
+pub fn holdings_page(holdings: Vec<TechHolding>, date: NaiveDate) -> Markup {
+    let total_price: Totals = holdings.iter().map(|x| x.price).collect();
+    let total_book_value: Totals = holdings.iter().map(|x| x.book_value(date)).collect();
+
+    let content = html! {
+        h1 { "Holdings on " (date) }
+        (date_filter("/holdings", date))
+        (downloads(&format!("/holdings?date={}", date)))
+
+        table border="1" {
+            thead {
+                tr {
+                    th { "Unit" }
+                    th { "Employee" }
+                    th { "Room" }
+                    th { "Inventory number" }
+                    th { "Name" }
+                    th { "Model" }
+                    th { "Held since" }
+                    th { "Price" }
+                    th { "Book value" }
+                }
+            }
+            tbody {
+                @for holding in holdings {
+                    tr {
+                        td { (holding.unit_name) (archived_badge(holding.unit_archived)) }
+                        td {
+                            (holding.last_name) " " (holding.first_name) " " (holding.middle_name)
+                            (archived_badge(holding.employee_archived))
+                        }
+                        td { (holding.room_number) }
+                        td { (holding.inventory_number) }
+                        td { (holding.name) (archived_badge(holding.tech_archived)) }
+                        td { (holding.model) }
+                        td { (holding.date) }
+                        td { (holding.price) }
+                        td { (holding.book_value(date)) }
+                    }
+                }
+            }
+            tfoot {
+                tr {
+                    th colspan="7" { "Total" }
+                    th { (total_price) }
+                    th { (total_book_value) }
+                }
+            }
+        }
+    };
+
+    base_template("Holdings", content)
+}
+
 
 
-fn input_form_add(message: Markup) -> Markup {
+fn category_select(categories: &[TechCategory], selected: Option<i32>) -> Markup {
+    html! {
+        label for="category" { "Category:" }
+        select id="category" name="category" {
+            option value="" { "No depreciation" }
+            @for category in categories {
+                option value=(category.id()) selected[selected == Some(category.id())] {
+                    (category.name)
+                }
+            }
+        }
+    }
+}
+
+fn input_form_add(categories: &[TechCategory], message: Markup) -> Markup {
     let content = html! {
         h1 { "Add new Tech" }
         form action="/add-tech" method="POST" {
@@ -137,7 +436,12 @@ fn input_form_add(message: Markup) -> Markup {
                 input type="date" id="acquisition_date" name="acquisition_date" required;
                 br;
                 label for="price" { "Price:" }
-                input type="number" id="price" name="price" required;
+                input type="text" inputmode="decimal" id="price" name="price" required;
+                br;
+                label for="currency" { "Currency:" }
+                input type="text" id="currency" name="currency" value=(Currency::RUB) maxlength="3" pattern="[A-Za-z]{3}" required;
+                br;
+                (category_select(categories, None))
                 br;
                 button type="submit" { "Add Tech" }
             }
@@ -150,15 +454,15 @@ fn input_form_add(message: Markup) -> Markup {
     base_template("Add tech", content)
 }
 
-pub fn add_form_get() -> Markup {
-    input_form_add(Message::none().to_html())
+pub fn add_form_get(categories: &[TechCategory]) -> Markup {
+    input_form_add(categories, Message::none().to_html())
 }
 
-pub fn add_form_post(message: Message) -> Markup {
-    input_form_add(message.to_html())
+pub fn add_form_post(categories: &[TechCategory], message: Message) -> Markup {
+    input_form_add(categories, message.to_html())
 }
 
-fn input_form_edit(tech: &Tech, message: Markup) -> Markup {
+fn input_form_edit(tech: &Tech, categories: &[TechCategory], message: Markup) -> Markup {
     let content = html! {
         h1 { "Edit Tech Item" }
 
@@ -180,7 +484,14 @@ fn input_form_edit(tech: &Tech, message: Markup) -> Markup {
             br; br;
 
             label for="price" { "Price:" }
-            input type="number" id="price" name="price" value=(tech.price) required;
+            input type="text" inputmode="decimal" id="price" name="price" value=(tech.price.amount) required;
+            br; br;
+
+            label for="currency" { "Currency:" }
+            input type="text" id="currency" name="currency" value=(tech.price.currency) maxlength="3" pattern="[A-Za-z]{3}" required;
+            br; br;
+
+            (category_select(categories, tech.category_id))
             br; br;
 
             button type="submit" { "Save Changes" }
@@ -195,10 +506,69 @@ fn input_form_edit(tech: &Tech, message: Markup) -> Markup {
     base_template("Tech edit", content)
 }
 
-pub fn edit_form_get(tech: &Tech) -> Markup {
-    input_form_edit(tech, Message::none().to_html())
+pub fn edit_form_get(tech: &Tech, categories: &[TechCategory]) -> Markup {
+    input_form_edit(tech, categories, Message::none().to_html())
 }
 
-pub fn edit_form_post(tech: &Tech, message: Message) -> Markup {
-    input_form_edit(tech, message.to_html())
+pub fn edit_form_post(tech: &Tech, categories: &[TechCategory], message: Message) -> Markup {
+    input_form_edit(tech, categories, message.to_html())
+}
+
+pub fn delete_page(tech: &Tech, transfers: Vec<TransferDetails>, message: Message) -> Markup {
+    let content = html! {
+        h1 { "Delete tech" }
+        p { (tech.inventory_number) " " (tech.name) " " (tech.model) }
+
+        @if transfers.is_empty() {
+            p { "This item has never been transferred." }
+            form action=(format!("/delete-tech/{}", tech.id())) method="POST" {
+                button type="submit" { "Delete" }
+            }
+        } @else {
+            h2 { "Transfers (" (transfers.len()) ")" }
+            table border="1" {
+                thead {
+                    tr {
+                        th { "Date" }
+                        th { "Employee" }
+                        th { "Room" }
+                    }
+                }
+                tbody {
+                    @for transfer in &transfers {
+                        tr {
+                            td { (transfer.transfer_date) }
+                            td {
+                                @match &transfer.employee {
+                                    Some(employee) => (employee.last_name) " " (employee.first_name) " " (employee.middle_name),
+                                    None => "No employee data",
+                                }
+                            }
+                            td {
+                                @match &transfer.room {
+                                    Some(room) => (room.room_number),
+                                    None => "No room data",
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            br;
+            p { "The item cannot be deleted while it has a transfer history." }
+            @if !tech.is_archived() {
+                form action=(format!("/archive-tech/{}", tech.id())) method="POST" {
+                    button type="submit" { "Archive and keep the history" }
+                }
+            }
+        }
+
+        (message.to_html())
+
+        br;
+        a href="/tech" { "Back to Tech List" }
+    };
+
+    base_template("Delete tech", content)
 }
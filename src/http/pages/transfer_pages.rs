@@ -1,9 +1,11 @@
 use super::base_page::base_template;
-use super::{Message, Session};
+use super::{archived_badge, downloads, field_error, Message, Session};
 use crate::model::dao::room::Room;
 use crate::model::dao::tech::Tech;
-use crate::model::dao::transfer::{TransferDetails, TransferUnit};
+use crate::model::dao::transfer::{is_pending, TransferDetails, TransferUnit};
 use crate::model::dao::unit::Unit;
+use crate::model::validation::FieldErrors;
+use chrono::NaiveDate;
 use maud::{html, Markup};
 
 fn get_or_def<T, F>(option: &Option<T>, default: &str, accessor: F) -> String
@@ -15,19 +17,33 @@ where
         .map_or(default.to_string(), |value| accessor(value).to_string())
 }
 
-pub fn table_page(transfer_details: Vec<TransferDetails>) -> Markup {
+fn status(transfer_date: NaiveDate, today: NaiveDate) -> &'static str {
+    if is_pending(transfer_date, today) {
+        "Pending"
+    } else {
+        "Done"
+    }
+}
+
+pub fn table_page(transfer_details: Vec<TransferDetails>, today: NaiveDate) -> Markup {
     let content = html! {
         h1 { "Transfer list" }
 
         a href="/add-transfer" {
             button type="button" { "Add Transfer" }
         }
+        " "
+        a href="/upcoming" {
+            button type="button" { "Upcoming Transfers" }
+        }
+        (downloads("/transfer"))
 
         table border="1" {
             thead {
                 tr {
                     th { "ID" }
                     th { "Transfer date" }
+                    th { "Status" }
                     th { "Tech" }
                     th { "Room" }
                     th { "Employee" }
@@ -40,14 +56,27 @@ pub fn table_page(transfer_details: Vec<TransferDetails>) -> Markup {
                     tr {
                         td { (transfer.id) }
                         td { (transfer.transfer_date) }
-                        td { (get_or_def(&transfer.tech, "No tech data", |tech| tech.name.as_str().to_string())) }
+                        td { (status(transfer.transfer_date, today)) }
+                        td {
+                            (get_or_def(&transfer.tech, "No tech data", |tech| tech.name.as_str().to_string()))
+                            (archived_badge(transfer.tech.as_ref().is_some_and(|x| x.is_archived())))
+                        }
                         td { (get_or_def(&transfer.room, "No room data", |room| format!("number: {}, squares: {}", room.room_number, room.squares))) }
-                        td { (get_or_def(&transfer.employee, "No employee data", |employee| format!("{} {} {}", employee.first_name.clone(), employee.middle_name.clone(), employee.last_name.clone()))) }
-                        td { (get_or_def(&transfer.unit, "No unit data", |unit| unit.full_name.clone())) }
+                        td {
+                            (get_or_def(&transfer.employee, "No employee data", |employee| format!("{} {} {}", employee.first_name.clone(), employee.middle_name.clone(), employee.last_name.clone())))
+                            (archived_badge(transfer.employee.as_ref().is_some_and(|x| x.is_archived())))
+                        }
+                        td {
+                            (get_or_def(&transfer.unit, "No unit data", |unit| unit.full_name.clone()))
+                            (archived_badge(transfer.unit.as_ref().is_some_and(|x| x.is_archived())))
+                        }
                         td {
                             form action={(format!("/edit-transfer/{}", transfer.id))} method="GET" {
                                 button type="submit" { "Edit" }
                             }
+                            a href=(format!("/transfer/{}/act", transfer.id)) {
+                                button type="button" { "Act" }
+                            }
                         }
                     }
                 }
@@ -64,6 +93,7 @@ fn input_form_edit(
     rooms: Vec<Room>,
     transfer: TransferUnit,
     session: Option<Session>,
+    errors: FieldErrors,
     message: Markup,
 ) -> Markup {
     let is_unit = session.is_none();
@@ -76,6 +106,7 @@ fn input_form_edit(
             @if is_unit  {
                 label for="date" { "Transfer Date: " }
                 input type="date" id="date" name="date" value=(transfer.inner.transfer_date) required;
+                (field_error(&errors, "date"))
 
                 br; br;
 
@@ -84,18 +115,22 @@ fn input_form_edit(
                     @for t in tech {
                         option value=(t.id()) selected[t.id() == transfer.inner.tech_id]{
                             (t.name)
+                            @if t.is_archived() { " (archived)" }
                         }
                     }
                 }
+                (field_error(&errors, "tech"))
                 br; br;
                 label for="unit" { "Unit: " }
                 select id="unit" name="unit" required {
                     @for unit in units {
                         option value=(unit.id()) selected[unit.id() == transfer.unit_id] {
                             (unit.full_name)
+                            @if unit.is_archived() { " (archived)" }
                         }
                     }
                 }
+                (field_error(&errors, "unit"))
                 br; br;
 
                 label for="room" { "Room: " }
@@ -106,6 +141,7 @@ fn input_form_edit(
                         }
                     }
                 }
+                (field_error(&errors, "room"))
                 br; br;
 
             } @else {
@@ -143,6 +179,7 @@ fn input_form_edit(
                         }
                     }
                 }
+                (field_error(&errors, "employee"))
                 br; br;
             }
 
@@ -166,7 +203,15 @@ pub fn edit_form_get(
     transfer: TransferUnit,
     message: Message,
 ) -> Markup {
-    input_form_edit(tech, units, rooms, transfer, None, message.to_html())
+    input_form_edit(
+        tech,
+        units,
+        rooms,
+        transfer,
+        None,
+        FieldErrors::new(),
+        message.to_html(),
+    )
 }
 
 pub fn edit_form_post(
@@ -175,19 +220,33 @@ pub fn edit_form_post(
     rooms: Vec<Room>,
     transfer: TransferUnit,
     session: Option<Session>,
+    errors: FieldErrors,
     message: Message,
 ) -> Markup {
-    input_form_edit(tech, units, rooms, transfer, session, message.to_html())
+    input_form_edit(
+        tech,
+        units,
+        rooms,
+        transfer,
+        session,
+        errors,
+        message.to_html(),
+    )
 }
 
 fn input_form_add(
     tech: Vec<Tech>,
     units: Vec<Unit>,
     rooms: Vec<Room>,
+    draft: Option<TransferUnit>,
     session: Option<Session>,
+    errors: FieldErrors,
     message: Markup,
 ) -> Markup {
     let is_unit = session.is_none();
+    let drafted = |accessor: fn(&TransferUnit) -> i32, id: i32| {
+        draft.as_ref().is_some_and(|x| accessor(x) == id)
+    };
 
     let content = html! {
         h1 { "Add new Transfer" }
@@ -196,37 +255,41 @@ fn input_form_add(
 
             @if is_unit  {
                 label for="date" { "Transfer Date: " }
-                input type="date" id="date" name="date" required;
+                input type="date" id="date" name="date" value=[draft.as_ref().map(|x| x.inner.transfer_date)] required;
+                (field_error(&errors, "date"))
 
                 br; br;
 
                 label for="tech" { "Tech: " }
                 select id="tech" name="tech" required {
                     @for t in tech {
-                        option value=(t.id()) {
+                        option value=(t.id()) selected[drafted(|x| x.inner.tech_id, t.id())] {
                             (t.name)
                         }
                     }
                 }
+                (field_error(&errors, "tech"))
                 br; br;
                 label for="unit" { "Unit: " }
                 select id="unit" name="unit" required {
                     @for unit in units {
-                        option value=(unit.id()) {
+                        option value=(unit.id()) selected[drafted(|x| x.unit_id, unit.id())] {
                             (unit.full_name)
                         }
                     }
                 }
+                (field_error(&errors, "unit"))
                 br; br;
 
                 label for="room" { "Room: " }
                 select id="room" name="room" required {
                     @for room in rooms {
-                        option value=(room.id()) {
+                        option value=(room.id()) selected[drafted(|x| x.inner.room_id, room.id())] {
                             (format!("Room: {}, {} sq. meters", room.room_number, room.squares))
                         }
                     }
                 }
+                (field_error(&errors, "room"))
                 br; br;
 
             } @else {
@@ -264,6 +327,7 @@ fn input_form_add(
                         }
                     }
                 }
+                (field_error(&errors, "employee"))
                 br; br;
             }
 
@@ -286,15 +350,103 @@ pub fn add_form_get(
     rooms: Vec<Room>,
     message: Message,
 ) -> Markup {
-    input_form_add(tech, units, rooms, None, message.to_html())
+    input_form_add(
+        tech,
+        units,
+        rooms,
+        None,
+        None,
+        FieldErrors::new(),
+        message.to_html(),
+    )
 }
 
 pub fn add_form_post(
     tech: Vec<Tech>,
     units: Vec<Unit>,
     rooms: Vec<Room>,
+    draft: Option<TransferUnit>,
     session: Option<Session>,
+    errors: FieldErrors,
     message: Message,
 ) -> Markup {
-    input_form_add(tech, units, rooms, session, message.to_html())
+    input_form_add(
+        tech,
+        units,
+        rooms,
+        draft,
+        session,
+        errors,
+        message.to_html(),
+    )
+}
+
+pub fn upcoming_page(transfers: Vec<TransferDetails>, today: NaiveDate, message: Message) -> Markup {
+    let content = html! {
+        h1 { "Upcoming transfers" }
+
+        a href="/add-transfer" {
+            button type="button" { "Schedule Transfer" }
+        }
+        (downloads("/upcoming"))
+
+        (message.to_html())
+
+        @if transfers.is_empty() {
+            p { "No transfers are scheduled." }
+        } @else {
+            table border="1" {
+                thead {
+                    tr {
+                        th { "Transfer date" }
+                        th { "In days" }
+                        th { "Tech" }
+                        th { "Room" }
+                        th { "Employee" }
+                        th { "Unit" }
+                        th { "Actions" }
+                    }
+                }
+                tbody {
+                    @for transfer in transfers {
+                        tr {
+                            td { (transfer.transfer_date) }
+                            td { ((transfer.transfer_date - today).num_days()) }
+                            td {
+                                @match &transfer.tech {
+                                    Some(tech) => {
+                                        a href=(format!("/tech/{}", tech.id())) {
+                                            (tech.inventory_number) " " (tech.name)
+                                        }
+                                        (archived_badge(tech.is_archived()))
+                                    }
+                                    None => "No tech data",
+                                }
+                            }
+                            td { (get_or_def(&transfer.room, "No room data", |room| room.room_number.to_string())) }
+                            td {
+                                (get_or_def(&transfer.employee, "No employee data", |employee| format!("{} {} {}", employee.first_name, employee.middle_name, employee.last_name)))
+                                (archived_badge(transfer.employee.as_ref().is_some_and(|x| x.is_archived())))
+                            }
+                            td {
+                                (get_or_def(&transfer.unit, "No unit data", |unit| unit.full_name.clone()))
+                                (archived_badge(transfer.unit.as_ref().is_some_and(|x| x.is_archived())))
+                            }
+                            td {
+                                form action=(format!("/reschedule-transfer/{}", transfer.id)) method="POST" {
+                                    input type="date" name="date" value=(transfer.transfer_date) required;
+                                    button type="submit" { "Reschedule" }
+                                }
+                                form action=(format!("/cancel-transfer/{}", transfer.id)) method="POST" {
+                                    button type="submit" { "Cancel" }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    };
+
+    base_template("Upcoming transfers", content)
 }
@@ -1,15 +1,33 @@
 use super::base_page::base_template;
-use super::Message;
+use super::{archived_badge, date_filter, downloads, Message};
+use crate::model::dao::employee::Employee;
+use crate::model::dao::tech::TechHolding;
 use crate::model::dao::unit::Unit;
+use crate::model::money::Totals;
+use chrono::NaiveDate;
 use maud::{html, Markup};
 
-pub fn table_page(unit_list: Vec<Unit>) -> Markup {
+/// The units in use, or with `archived` set, the units that have been archived.
+pub fn table_page(unit_list: Vec<Unit>, archived: bool) -> Markup {
     let content = html! {
-        h1 { "Unit list" }
+        @if archived {
+            h1 { "Archived units" }
+
+            a href="/unit" {
+                button type="button" { "Show Units In Use" }
+            }
+        } @else {
+            h1 { "Unit list" }
 
-        a href="/add-unit" {
-            button type="button" { "Add Unit" }
+            a href="/add-unit" {
+                button type="button" { "Add Unit" }
+            }
+            " "
+            a href="/unit?archived=true" {
+                button type="button" { "Show Archived" }
+            }
         }
+        (downloads(if archived { "/unit?archived=true" } else { "/unit" }))
 
         table border="1" {
             thead {
@@ -18,6 +36,9 @@ pub fn table_page(unit_list: Vec<Unit>) -> Markup {
                     th { "Unit number" }
                     th { "Full name" }
                     th { "Short name" }
+                    @if archived {
+                        th { "Archived On" }
+                    }
                     th { "Actions" }
                 }
             }
@@ -28,14 +49,26 @@ pub fn table_page(unit_list: Vec<Unit>) -> Markup {
                         td { (unit.unit_number) }
                         td { (unit.full_name) }
                         td { (unit.short_name) }
+                        @if let Some(archived_at) = unit.archived_at {
+                            td { (archived_at.date_naive()) }
+                        }
                         td {
-                            form action={(format!("/delete-unit/{}", unit.id()))} method="POST" {
-                                input type="hidden" name="_method" value="DELETE" ;
+                            form action={(format!("/delete-unit/{}", unit.id()))} method="GET" {
                                 button type="submit" { "Delete" }
                             }
 
-                            form action={(format!("/edit-unit/{}", unit.id()))} method="GET" {
-                                button type="submit" { "Edit" }
+                            @if archived {
+                                form action={(format!("/restore-unit/{}", unit.id()))} method="POST" {
+                                    button type="submit" { "Restore" }
+                                }
+                            } @else {
+                                form action={(format!("/edit-unit/{}", unit.id()))} method="GET" {
+                                    button type="submit" { "Edit" }
+                                }
+
+                                form action={(format!("/archive-unit/{}", unit.id()))} method="POST" {
+                                    button type="submit" { "Archive" }
+                                }
                             }
                         }
                     }
@@ -163,4 +196,140 @@ fn input_form_edit(unit: &Unit, message: Markup) -> Markup {
     base_template("Edit unit", content)
 }
 
+pub fn delete_page(
+    unit: &Unit,
+    employees: Vec<Employee>,
+    message: Message,
+) -> Markup {
+    let content = html! {
+        h1 { "Delete unit" }
+        p { (unit.unit_number) " " (unit.full_name) " (" (unit.short_name) ")" }
+
+        @if employees.is_empty() {
+            p { "The unit has no employees." }
+            form action=(format!("/delete-unit/{}", unit.id())) method="POST" {
+                button type="submit" { "Delete" }
+            }
+        } @else {
+            h2 { "Employees (" (employees.len()) ")" }
+            table border="1" {
+                thead {
+                    tr {
+                        th { "Name" }
+                        th { "Job title" }
+                        th { "Is accountable" }
+                    }
+                }
+                tbody {
+                    @for employee in &employees {
+                        tr {
+                            td {
+                                (employee.last_name) " " (employee.first_name) " " (employee.middle_name)
+                                (archived_badge(employee.is_archived()))
+                            }
+                            td { (employee.job_title) }
+                            td { (employee.is_accountable) }
+                        }
+                    }
+                }
+            }
+
+            br;
+            p {
+                "The unit cannot be deleted while it has employees: the tech they held "
+                "is counted to the unit in past reports."
+            }
+            @if !unit.is_archived() {
+                form action=(format!("/archive-unit/{}", unit.id())) method="POST" {
+                    button type="submit" { "Archive and keep the records" }
+                }
+            }
+        }
+
+        (message.to_html())
+
+        br;
+        a href="/unit" { "Back to Unit List" }
+    };
+
+    base_template("Delete unit", content)
+}
+
+struct UnitBookValue<'a> {
+    unit_id: i32,
+    unit_name: &'a str,
+    unit_archived: bool,
+    items: usize,
+    price: Totals,
+    book_value: Totals,
+}
+
+/// Totals of what every unit holds on `date`, worked out from the holdings, which
+/// come sorted by unit.
+pub fn book_value_page(holdings: Vec<TechHolding>, date: NaiveDate) -> Markup {
+    let mut units: Vec<UnitBookValue> = vec![];
+    for holding in &holdings {
+        match units.last_mut() {
+            Some(unit) if unit.unit_id == holding.unit_id => {}
+            _ => units.push(UnitBookValue {
+                unit_id: holding.unit_id,
+                unit_name: &holding.unit_name,
+                unit_archived: holding.unit_archived,
+                items: 0,
+                price: Totals::default(),
+                book_value: Totals::default(),
+            }),
+        }
+        if let Some(unit) = units.last_mut() {
+            unit.items += 1;
+            unit.price.add(holding.price);
+            unit.book_value.add(holding.book_value(date));
+        }
+    }
+    let total_price: Totals = holdings.iter().map(|x| x.price).collect();
+    let total_book_value: Totals = holdings.iter().map(|x| x.book_value(date)).collect();
+
+    let content = html! {
+        h1 { "Book value by unit on " (date) }
+        (date_filter("/book-value", date))
+        (downloads(&format!("/book-value?date={}", date)))
+
+        @if units.is_empty() {
+            p { "No unit held any tech on " (date) "." }
+        } @else {
+            table border="1" {
+                thead {
+                    tr {
+                        th { "Unit" }
+                        th { "Items" }
+                        th { "Price" }
+                        th { "Book value" }
+                    }
+                }
+                tbody {
+                    @for unit in &units {
+                        tr {
+                            td {
+                                a href=(format!("/view-unit-tech/{}?date={}", unit.unit_id, date)) { (unit.unit_name) }
+                                (archived_badge(unit.unit_archived))
+                            }
+                            td { (unit.items) }
+                            td { (unit.price) }
+                            td { (unit.book_value) }
+                        }
+                    }
+                }
+                tfoot {
+                    tr {
+                        th { "Total" }
+                        th { (holdings.len()) }
+                        th { (total_price) }
+                        th { (total_book_value) }
+                    }
+                }
+            }
+        }
+    };
 
+    base_template("Book value", content)
+}
@@ -0,0 +1,149 @@
+use super::base_page::base_template;
+use super::{archived_badge, downloads, field_error, Message};
+use crate::model::dao::employee::Employee;
+use crate::model::dao::tech::Tech;
+use crate::model::dao::write_off::{Disposal, WriteOff, WriteOffDetails};
+use crate::model::money::{Amount, Totals};
+use crate::model::validation::FieldErrors;
+use maud::{html, Markup};
+
+/// The write-off act for one item. `draft` refills the form after a rejected submit.
+pub fn form_page(
+    tech: &Tech,
+    employees: Vec<Employee>,
+    draft: Option<&WriteOff>,
+    errors: FieldErrors,
+    message: Message,
+) -> Markup {
+    let content = html! {
+        h1 { "Write off tech" }
+        p { (tech.inventory_number) " " (tech.name) " " (tech.model) ", acquired on " (tech.acquisition_date) " for " (tech.price) }
+
+        @if let Some(date) = tech.written_off_on {
+            p { "This item was written off on " (date) "." }
+        } @else {
+            form action=(format!("/write-off-tech/{}", tech.id())) method="POST" {
+                label for="date" { "Date: " }
+                input type="date" id="date" name="date" value=[draft.map(|x| x.write_off_date)] required;
+                (field_error(&errors, "date"))
+                br; br;
+
+                label for="disposal" { "Disposal: " }
+                select id="disposal" name="disposal" required {
+                    @for disposal in Disposal::ALL {
+                        option value=(disposal.as_str()) selected[draft.is_some_and(|x| x.disposal == disposal)] {
+                            (disposal.label())
+                        }
+                    }
+                }
+                (field_error(&errors, "disposal"))
+                br; br;
+
+                label for="reason" { "Reason: " }
+                textarea id="reason" name="reason" rows="3" cols="50" required {
+                    @if let Some(draft) = draft { (draft.reason) }
+                }
+                (field_error(&errors, "reason"))
+                br; br;
+
+                label for="act_number" { "Act number: " }
+                input type="text" id="act_number" name="act_number" value=[draft.map(|x| x.act_number.as_str())] required;
+                (field_error(&errors, "act_number"))
+                br; br;
+
+                label for="employee" { "Signed by: " }
+                select id="employee" name="employee" required {
+                    @for employee in &employees {
+                        option value=(employee.id()) selected[draft.is_some_and(|x| x.employee_id == employee.id())] {
+                            (employee.last_name) " " (employee.first_name) " " (employee.middle_name) ", " (employee.job_title)
+                        }
+                    }
+                }
+                (field_error(&errors, "employee"))
+                br; br;
+
+                label for="residual_value" { "Residual value, " (tech.price.currency) ": " }
+                input type="text" inputmode="decimal" id="residual_value" name="residual_value" value=(draft.map_or(Amount::ZERO, |x| x.residual_value)) required;
+                (field_error(&errors, "residual_value"))
+                br; br;
+
+                button type="submit" { "Write off" }
+            }
+        }
+
+        (message.to_html())
+
+        br;
+        a href=(format!("/tech/{}", tech.id())) { "Back to the item" }
+    };
+
+    base_template("Write off tech", content)
+}
+
+pub fn register_page(register: Vec<WriteOffDetails>, year: i32) -> Markup {
+    let total_price: Totals = register.iter().map(|x| x.tech.price).collect();
+    let total_residual: Totals = register.iter().map(|x| x.residual_value()).collect();
+
+    let content = html! {
+        h1 { "Write-off register for " (year) }
+
+        form action="/write-offs" method="GET" class="date-filter" {
+            label for="year" { "Year: " }
+            input type="number" id="year" name="year" value=(year) required;
+            button type="submit" { "Show" }
+        }
+        (downloads(&format!("/write-offs?year={}", year)))
+
+        @if register.is_empty() {
+            p { "Nothing was written off in " (year) "." }
+        } @else {
+            table border="1" {
+                thead {
+                    tr {
+                        th { "Act number" }
+                        th { "Date" }
+                        th { "Inventory number" }
+                        th { "Tech" }
+                        th { "Disposal" }
+                        th { "Reason" }
+                        th { "Signed by" }
+                        th { "Price" }
+                        th { "Residual value" }
+                    }
+                }
+                tbody {
+                    @for entry in &register {
+                        tr {
+                            td { (entry.write_off.act_number) }
+                            td { (entry.write_off.write_off_date) }
+                            td { (entry.tech.inventory_number) }
+                            td {
+                                a href=(format!("/tech/{}", entry.tech.id())) {
+                                    (entry.tech.name) " " (entry.tech.model)
+                                }
+                                (archived_badge(entry.tech.is_archived()))
+                            }
+                            td { (entry.write_off.disposal.label()) }
+                            td { (entry.write_off.reason) }
+                            td {
+                                (entry.employee.last_name) " " (entry.employee.first_name) " " (entry.employee.middle_name)
+                                (archived_badge(entry.employee.is_archived()))
+                            }
+                            td { (entry.tech.price) }
+                            td { (entry.residual_value()) }
+                        }
+                    }
+                }
+                tfoot {
+                    tr {
+                        th colspan="7" { "Total: " (register.len()) " item(s)" }
+                        th { (total_price) }
+                        th { (total_residual) }
+                    }
+                }
+            }
+        }
+    };
+
+    base_template("Write-offs", content)
+}
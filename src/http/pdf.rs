@@ -0,0 +1,244 @@
+//! A small PDF writer for printable documents: A4 pages of text and lines in one
+//! embedded TrueType font, so names print in whatever script they are written in.
+use miniz_oxide::deflate::compress_to_vec_zlib;
+use pdf_writer::types::{CidFontType, FontFlags, SystemInfo, UnicodeCmap};
+use pdf_writer::{Content, Filter, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::OnceLock;
+use ttf_parser::{name_id, Face, GlyphId};
+
+pub const PAGE_WIDTH: f32 = 595.0;
+pub const PAGE_HEIGHT: f32 = 842.0;
+/// Space left blank around the text on every side.
+pub const MARGIN: f32 = 50.0;
+
+const FONT_NAME: Name = Name(b"F1");
+/// How much page contents and the font shrink; they are written once per request.
+const COMPRESSION: u8 = 6;
+
+static FONT: OnceLock<Vec<u8>> = OnceLock::new();
+
+/// Reads the font documents are set in. Called once at startup.
+pub fn load_font(path: &Path) -> Result<(), String> {
+    let data = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    if let Err(e) = Face::parse(&data, 0) {
+        return Err(format!("{}: {}", path.display(), e));
+    }
+    let _ = FONT.set(data);
+    Ok(())
+}
+
+/// A document being laid out. Coordinates are in points from the top left corner
+/// of the current page.
+pub struct Document {
+    title: String,
+    font: &'static [u8],
+    face: Face<'static>,
+    pages: Vec<Content>,
+    /// Every glyph used with the character it stands for, to make the text searchable.
+    glyphs: BTreeMap<u16, char>,
+}
+
+impl Document {
+    pub fn new(title: &str) -> Result<Document, String> {
+        let font = FONT
+            .get()
+            .ok_or("No font is loaded for PDF documents; set documents.font")?;
+        let face = Face::parse(font, 0).map_err(|e| e.to_string())?;
+        Ok(Document {
+            title: title.into(),
+            font,
+            face,
+            pages: vec![Content::new()],
+            glyphs: BTreeMap::new(),
+        })
+    }
+
+    pub fn new_page(&mut self) {
+        self.pages.push(Content::new());
+    }
+
+    fn page(&mut self) -> &mut Content {
+        self.pages.last_mut().expect("A document always has a page")
+    }
+
+    fn glyph(&self, c: char) -> GlyphId {
+        self.face.glyph_index(c).unwrap_or(GlyphId(0))
+    }
+
+    /// How wide `text` comes out at `size`.
+    pub fn text_width(&self, text: &str, size: f32) -> f32 {
+        let units: u32 = text
+            .chars()
+            .map(|c| self.face.glyph_hor_advance(self.glyph(c)).unwrap_or(0) as u32)
+            .sum();
+        units as f32 * size / self.face.units_per_em() as f32
+    }
+
+    /// Writes one line of text with its baseline at `y`.
+    pub fn text(&mut self, x: f32, y: f32, size: f32, text: &str) {
+        let mut encoded = Vec::with_capacity(text.len() * 2);
+        for c in text.chars() {
+            let glyph = self.glyph(c);
+            self.glyphs.entry(glyph.0).or_insert(c);
+            encoded.extend(glyph.0.to_be_bytes());
+        }
+        self.page()
+            .begin_text()
+            .set_font(FONT_NAME, size)
+            .next_line(x, PAGE_HEIGHT - y)
+            .show(Str(&encoded))
+            .end_text();
+    }
+
+    /// Writes `text` so that it ends at `right`.
+    pub fn text_right(&mut self, right: f32, y: f32, size: f32, text: &str) {
+        let x = right - self.text_width(text, size);
+        self.text(x, y, size, text);
+    }
+
+    /// Splits `text` into lines no wider than `width`, breaking between words where
+    /// it can.
+    pub fn wrap(&self, text: &str, size: f32, width: f32) -> Vec<String> {
+        let mut lines = vec![];
+        let mut line = String::new();
+        for word in text.split_whitespace() {
+            let candidate = match line.is_empty() {
+                true => word.to_string(),
+                false => format!("{} {}", line, word),
+            };
+            if self.text_width(&candidate, size) <= width {
+                line = candidate;
+                continue;
+            }
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            // A word too long for a line of its own is cut wherever it overflows.
+            for c in word.chars() {
+                line.push(c);
+                if self.text_width(&line, size) > width && line.chars().count() > 1 {
+                    line.pop();
+                    lines.push(std::mem::replace(&mut line, c.to_string()));
+                }
+            }
+        }
+        if !line.is_empty() || lines.is_empty() {
+            lines.push(line);
+        }
+        lines
+    }
+
+    pub fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) {
+        self.page()
+            .set_line_width(0.5)
+            .move_to(x1, PAGE_HEIGHT - y1)
+            .line_to(x2, PAGE_HEIGHT - y2)
+            .stroke();
+    }
+
+    /// The finished file.
+    pub fn finish(self) -> Vec<u8> {
+        let mut next_id = 1;
+        let mut alloc = || {
+            let id = Ref::new(next_id);
+            next_id += 1;
+            id
+        };
+        let catalog_id = alloc();
+        let page_tree_id = alloc();
+        let info_id = alloc();
+        let font_id = alloc();
+        let cid_font_id = alloc();
+        let descriptor_id = alloc();
+        let font_file_id = alloc();
+        let cmap_id = alloc();
+        let pages: Vec<(Ref, Ref)> = self.pages.iter().map(|_| (alloc(), alloc())).collect();
+
+        let mut pdf = Pdf::new();
+        pdf.catalog(catalog_id).pages(page_tree_id);
+        pdf.document_info(info_id)
+            .title(TextStr(&self.title))
+            .producer(TextStr("pbz"));
+        pdf.pages(page_tree_id)
+            .kids(pages.iter().map(|(page_id, _)| *page_id))
+            .count(pages.len() as i32);
+
+        for ((page_id, content_id), content) in pages.iter().zip(self.pages) {
+            let mut page = pdf.page(*page_id);
+            page.parent(page_tree_id)
+                .media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT))
+                .contents(*content_id);
+            page.resources().fonts().pair(FONT_NAME, font_id);
+            page.finish();
+            let data = compress_to_vec_zlib(&content.finish(), COMPRESSION);
+            pdf.stream(*content_id, &data).filter(Filter::FlateDecode);
+        }
+
+        let base_font = self
+            .face
+            .names()
+            .into_iter()
+            .find(|x| x.name_id == name_id::POST_SCRIPT_NAME)
+            .and_then(|x| x.to_string())
+            .unwrap_or_else(|| "DocumentFont".to_string());
+        let system_info = SystemInfo {
+            registry: Str(b"Adobe"),
+            ordering: Str(b"Identity"),
+            supplement: 0,
+        };
+        let scale = 1000.0 / self.face.units_per_em() as f32;
+
+        pdf.type0_font(font_id)
+            .base_font(Name(base_font.as_bytes()))
+            .encoding_predefined(Name(b"Identity-H"))
+            .descendant_font(cid_font_id)
+            .to_unicode(cmap_id);
+
+        let mut cid_font = pdf.cid_font(cid_font_id);
+        cid_font
+            .subtype(CidFontType::Type2)
+            .base_font(Name(base_font.as_bytes()))
+            .system_info(system_info)
+            .font_descriptor(descriptor_id)
+            .cid_to_gid_map_predefined(Name(b"Identity"));
+        let mut widths = cid_font.widths();
+        for glyph in self.glyphs.keys() {
+            let advance = self.face.glyph_hor_advance(GlyphId(*glyph)).unwrap_or(0);
+            widths.consecutive(*glyph, [advance as f32 * scale]);
+        }
+        widths.finish();
+        cid_font.finish();
+
+        let bbox = self.face.global_bounding_box();
+        pdf.font_descriptor(descriptor_id)
+            .name(Name(base_font.as_bytes()))
+            .flags(FontFlags::SYMBOLIC)
+            .bbox(Rect::new(
+                bbox.x_min as f32 * scale,
+                bbox.y_min as f32 * scale,
+                bbox.x_max as f32 * scale,
+                bbox.y_max as f32 * scale,
+            ))
+            .italic_angle(self.face.italic_angle())
+            .ascent(self.face.ascender() as f32 * scale)
+            .descent(self.face.descender() as f32 * scale)
+            .cap_height(self.face.capital_height().unwrap_or(self.face.ascender()) as f32 * scale)
+            .stem_v(80.0)
+            .font_file2(font_file_id);
+
+        let font_data = compress_to_vec_zlib(self.font, COMPRESSION);
+        pdf.stream(font_file_id, &font_data)
+            .filter(Filter::FlateDecode)
+            .pair(Name(b"Length1"), self.font.len() as i32);
+
+        let mut cmap = UnicodeCmap::new(Name(b"Custom"), system_info);
+        for (glyph, c) in &self.glyphs {
+            cmap.pair(*glyph, *c);
+        }
+        pdf.cmap(cmap_id, &cmap.finish());
+
+        pdf.finish()
+    }
+}
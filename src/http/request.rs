@@ -0,0 +1,186 @@
+use super::form::{self, FormData, FormError, UploadedFile};
+use super::router::Params;
+use crate::model::error::AppError;
+use chrono::{Local, NaiveDate};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Method {
+    Get,
+    Head,
+    Post,
+    Put,
+    Delete,
+    Options,
+    Other(String),
+}
+
+impl Method {
+    fn parse(method: &str) -> Method {
+        match method {
+            "GET" => Method::Get,
+            "HEAD" => Method::Head,
+            "POST" => Method::Post,
+            "PUT" => Method::Put,
+            "DELETE" => Method::Delete,
+            "OPTIONS" => Method::Options,
+            other => Method::Other(other.to_string()),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Method::Get => "GET",
+            Method::Head => "HEAD",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Delete => "DELETE",
+            Method::Options => "OPTIONS",
+            Method::Other(other) => other,
+        }
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Header names are stored lowercased, so lookups ignore case.
+#[derive(Debug, Default)]
+pub struct Headers {
+    headers: Vec<(String, String)>,
+}
+
+impl Headers {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        let name = name.to_lowercase();
+        self.headers
+            .iter()
+            .find(|(k, _)| *k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn insert(&mut self, name: &str, value: &str) {
+        self.headers
+            .push((name.trim().to_lowercase(), value.trim().to_string()));
+    }
+}
+
+#[derive(Debug)]
+pub enum RequestError {
+    MalformedRequestLine,
+    MalformedHeader(String),
+    Form(FormError),
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RequestError::MalformedRequestLine => write!(f, "Malformed request line"),
+            RequestError::MalformedHeader(line) => write!(f, "Malformed header: {}", line),
+            RequestError::Form(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<FormError> for RequestError {
+    fn from(e: FormError) -> Self {
+        RequestError::Form(e)
+    }
+}
+
+#[derive(Debug)]
+pub struct Request {
+    pub method: Method,
+    pub path: String,
+    pub query: FormData,
+    pub headers: Headers,
+    pub body: FormData,
+    /// Files sent with a `multipart/form-data` form.
+    pub files: Vec<UploadedFile>,
+    pub params: Params,
+}
+
+impl Request {
+    /// Builds a request from the raw header block (request line included) and the body bytes.
+    pub fn parse(head: &str, body: &[u8]) -> Result<Request, RequestError> {
+        let mut lines = head.lines();
+        let request_line = lines.next().ok_or(RequestError::MalformedRequestLine)?;
+        let mut split_request = request_line.split_whitespace();
+        let method = split_request
+            .next()
+            .ok_or(RequestError::MalformedRequestLine)?;
+        let target = split_request
+            .next()
+            .ok_or(RequestError::MalformedRequestLine)?;
+
+        let mut headers = Headers::default();
+        for line in lines.filter(|line| !line.trim().is_empty()) {
+            let (name, value) = line
+                .split_once(':')
+                .ok_or_else(|| RequestError::MalformedHeader(line.to_string()))?;
+            headers.insert(name, value);
+        }
+
+        let (path, query) = match target.split_once('?') {
+            Some((path, query)) => (path, query),
+            None => (target, ""),
+        };
+
+        let content_type = headers.get("content-type");
+        let is_form =
+            content_type.is_none_or(|x| x.starts_with("application/x-www-form-urlencoded"));
+        let boundary = content_type
+            .filter(|x| x.starts_with("multipart/form-data"))
+            .and_then(|x| {
+                x.split(';')
+                    .filter_map(|param| param.trim().strip_prefix("boundary="))
+                    .next()
+            })
+            .map(|x| x.trim_matches('"'));
+        let (body, files) = match boundary {
+            _ if body.is_empty() => (FormData::default(), vec![]),
+            Some(boundary) => form::parse_multipart(body, boundary)?,
+            None if is_form => {
+                let body = std::str::from_utf8(body).map_err(|_| FormError::InvalidUtf8)?;
+                (FormData::parse(body)?, vec![])
+            }
+            None => (FormData::default(), vec![]),
+        };
+
+        Ok(Request {
+            method: Method::parse(method),
+            path: path.to_string(),
+            query: FormData::parse(query)?,
+            headers,
+            body,
+            files,
+            params: Params::default(),
+        })
+    }
+
+    /// The file sent in the form field `name`, if one was chosen.
+    pub fn file(&self, name: &str) -> Option<&UploadedFile> {
+        self.files
+            .iter()
+            .find(|x| x.name == name && !x.filename.is_empty())
+    }
+
+    pub fn param<T>(&self, name: &str) -> Option<T>
+    where
+        T: FromStr,
+    {
+        self.params.get(name)
+    }
+
+    /// The `?date=` a report is shown for, today when the query leaves it out.
+    pub fn as_of_date(&self) -> Result<NaiveDate, AppError> {
+        Ok(self
+            .query
+            .optional("date")?
+            .unwrap_or_else(|| Local::now().date_naive()))
+    }
+}
@@ -0,0 +1,116 @@
+use super::export::{sheets, Export};
+use super::form::FormData;
+use super::pages::*;
+use super::request::{Method, Request};
+use super::Response;
+use crate::model::context::AppContext;
+use crate::model::dao::*;
+use crate::model::error::AppError;
+
+pub fn handle_room_table(req: &Request, ctx: &AppContext) -> Response {
+    let export = match Export::requested(req) {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+    let room_dao = &ctx.room;
+    match room_dao.get_all() {
+        Ok(rooms) => match export {
+            Some(export) => export.respond(&sheets::rooms(&rooms)),
+            None => Response::ok(room_pages::table_page(rooms)),
+        },
+        Err(e) => e.into(),
+    }
+}
+
+fn room_from_form(body: &FormData) -> Result<room::Room, AppError> {
+    let squares: i32 = body.field("squares")?;
+    if squares <= 0 {
+        return Err(AppError::validation("Squares have to be a positive number"));
+    }
+    Ok(room::Room::new(body.field("room_number")?, squares))
+}
+
+fn save_error(action: &str, e: AppError) -> Message {
+    match &e {
+        AppError::Sql {
+            constraint: Some(constraint),
+            ..
+        } if constraint == "room_room_number_key" => {
+            Message::new("A room with this number already exists", MK::Error)
+        }
+        _ => Message::new(&format!("Failed to {} room: {}", action, e), MK::Error),
+    }
+}
+
+pub fn handle_room_add(req: &Request, ctx: &AppContext) -> Response {
+    match req.method {
+        Method::Get => Response::ok(room_pages::add_form_get()),
+        Method::Post => {
+            let mut room = match room_from_form(&req.body) {
+                Ok(x) => x,
+                Err(e) => {
+                    let message = Message::new(&e.to_string(), MK::Error);
+                    return Response::ok(room_pages::add_form_post(message));
+                }
+            };
+
+            let room_dao = &ctx.room;
+            let message = match room_dao.insert(&mut room) {
+                Ok(()) => Message::new("Added room", MK::Notify),
+                Err(e) => save_error("add", e),
+            };
+
+            Response::ok(room_pages::add_form_post(message))
+        }
+        _ => Response::method_not_allowed(),
+    }
+}
+
+pub fn handle_room_edit(req: &Request, ctx: &AppContext) -> Response {
+    let arg = match req.param::<i32>("id") {
+        Some(x) => x,
+        None => return Response::not_found(),
+    };
+
+    let room_dao = &ctx.room;
+    let room = match room_dao.get_id(arg) {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+
+    match req.method {
+        Method::Get => Response::ok(room_pages::edit_form_get(&room)),
+        Method::Post => {
+            let mut room = match room_from_form(&req.body) {
+                Ok(x) => x,
+                Err(e) => {
+                    let message = Message::new(&e.to_string(), MK::Error);
+                    return Response::ok(room_pages::edit_form_post(&room, message));
+                }
+            };
+
+            room.set_id(arg);
+            let message = match room_dao.update(&room) {
+                Ok(()) => Message::new("Updated room", MK::Success),
+                Err(e) => save_error("update", e),
+            };
+            Response::ok(room_pages::edit_form_post(&room, message))
+        }
+        _ => Response::method_not_allowed(),
+    }
+}
+
+pub fn handle_room_delete(req: &Request, ctx: &AppContext) -> Response {
+    let arg = match req.param::<i32>("id") {
+        Some(x) => x,
+        None => return Response::not_found(),
+    };
+
+    let room_dao = &ctx.room;
+    match room_dao.delete(arg) {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+
+    Response::found("/room")
+}
@@ -0,0 +1,137 @@
+use super::form;
+use super::request::{Method, Request};
+use super::Response;
+use crate::model::context::AppContext;
+use std::str::FromStr;
+
+pub type Handler = fn(&Request, &AppContext) -> Response;
+
+/// Values captured by `{name}` segments of the matched route.
+#[derive(Debug, Default)]
+pub struct Params {
+    params: Vec<(String, String)>,
+}
+
+impl Params {
+    pub fn get<T>(&self, name: &str) -> Option<T>
+    where
+        T: FromStr,
+    {
+        self.params
+            .iter()
+            .find(|(k, _)| k == name)
+            .and_then(|(_, v)| v.parse().ok())
+    }
+}
+
+enum Segment {
+    Static(String),
+    Param(String),
+    Rest(String),
+}
+
+struct Route {
+    method: Method,
+    segments: Vec<Segment>,
+    handler: Handler,
+}
+
+impl Route {
+    fn matches(&self, path: &[&str]) -> Option<Params> {
+        let has_rest = matches!(self.segments.last(), Some(Segment::Rest(_)));
+        if self.segments.len() != path.len() && !(has_rest && path.len() >= self.segments.len()) {
+            return None;
+        }
+        let mut params = Params::default();
+        for (i, (segment, part)) in self.segments.iter().zip(path).enumerate() {
+            match segment {
+                Segment::Rest(name) => {
+                    let value = path[i..]
+                        .iter()
+                        .map(|part| form::decode(part, false))
+                        .collect::<Result<Vec<_>, _>>()
+                        .ok()?;
+                    params.params.push((name.clone(), value.join("/")));
+                }
+                Segment::Static(s) if s == part => {}
+                Segment::Static(_) => return None,
+                Segment::Param(name) => {
+                    let value = form::decode(part, false).ok()?;
+                    params.params.push((name.clone(), value));
+                }
+            }
+        }
+        Some(params)
+    }
+}
+
+fn split_path(path: &str) -> Vec<&str> {
+    path.split('/').filter(|part| !part.is_empty()).collect()
+}
+
+#[derive(Default)]
+pub struct Router {
+    routes: Vec<Route>,
+}
+
+impl Router {
+    pub fn new() -> Router {
+        Router::default()
+    }
+
+    /// Registers a handler; `{name}` segments are captured into `Request::params`
+    /// and a trailing `{*name}` captures the rest of the path.
+    pub fn route(mut self, method: Method, pattern: &str, handler: Handler) -> Router {
+        let segments = split_path(pattern)
+            .into_iter()
+            .map(
+                |part| match part.strip_prefix('{').and_then(|x| x.strip_suffix('}')) {
+                    Some(name) => match name.strip_prefix('*') {
+                        Some(name) => Segment::Rest(name.to_string()),
+                        None => Segment::Param(name.to_string()),
+                    },
+                    None => Segment::Static(part.to_string()),
+                },
+            )
+            .collect();
+        self.routes.push(Route {
+            method,
+            segments,
+            handler,
+        });
+        self
+    }
+
+    pub fn get(self, pattern: &str, handler: Handler) -> Router {
+        self.route(Method::Get, pattern, handler)
+    }
+
+    pub fn post(self, pattern: &str, handler: Handler) -> Router {
+        self.route(Method::Post, pattern, handler)
+    }
+
+    pub fn dispatch(&self, mut req: Request, ctx: &AppContext) -> Response {
+        let path = split_path(&req.path);
+        let mut allowed: Vec<&Method> = vec![];
+
+        for route in &self.routes {
+            let Some(params) = route.matches(&path) else {
+                continue;
+            };
+            if route.method == req.method {
+                req.params = params;
+                return (route.handler)(&req, ctx);
+            }
+            if !allowed.contains(&&route.method) {
+                allowed.push(&route.method);
+            }
+        }
+
+        if allowed.is_empty() {
+            debug!("No route for {} {}", req.method, req.path);
+            return Response::not_found();
+        }
+        let allowed: Vec<&str> = allowed.iter().map(|method| method.as_str()).collect();
+        Response::method_not_allowed().with_header("Allow", &allowed.join(", "))
+    }
+}
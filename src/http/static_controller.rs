@@ -0,0 +1,113 @@
+use super::request::Request;
+use super::Response;
+use crate::model::context::AppContext;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const STATIC_DIR: &str = "static";
+const HTTP_DATE: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+fn static_dir() -> PathBuf {
+    std::env::var("STATIC_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(STATIC_DIR))
+}
+
+fn mime_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|x| x.to_str())
+        .map(|x| x.to_lowercase());
+    match extension.as_deref() {
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("html") | Some("htm") => "text/html; charset=utf-8",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("json") => "application/json",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("ico") => "image/x-icon",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        Some("ttf") => "font/ttf",
+        Some("pdf") => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Joins the requested path onto the static root, refusing anything that could
+/// step outside of it (`..`, absolute paths, hidden files).
+fn resolve(root: &Path, requested: &str) -> Option<PathBuf> {
+    let requested = Path::new(requested);
+    let mut path = root.to_path_buf();
+    for component in requested.components() {
+        match component {
+            Component::Normal(part) if !part.to_string_lossy().starts_with('.') => path.push(part),
+            _ => return None,
+        }
+    }
+
+    let root = root.canonicalize().ok()?;
+    let path = path.canonicalize().ok()?;
+    if path.starts_with(&root) && path.is_file() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+fn is_not_modified(req: &Request, etag: &str, modified: &DateTime<Utc>) -> bool {
+    if let Some(tags) = req.headers.get("if-none-match") {
+        return tags.split(',').any(|tag| {
+            let tag = tag.trim();
+            tag == "*" || tag.trim_start_matches("W/") == etag
+        });
+    }
+    req.headers
+        .get("if-modified-since")
+        .and_then(|since| NaiveDateTime::parse_from_str(since, HTTP_DATE).ok())
+        .is_some_and(|since| modified.timestamp() <= since.and_utc().timestamp())
+}
+
+pub fn handle_static(req: &Request, _ctx: &AppContext) -> Response {
+    let requested: String = match req.param("path") {
+        Some(x) => x,
+        None => return Response::not_found(),
+    };
+    let path = match resolve(&static_dir(), &requested) {
+        Some(x) => x,
+        None => return Response::not_found(),
+    };
+    let metadata = match fs::metadata(&path) {
+        Ok(x) => x,
+        Err(_) => return Response::not_found(),
+    };
+
+    let modified = metadata.modified().unwrap_or(SystemTime::now());
+    let modified_secs = modified
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or(0);
+    let etag = format!("\"{:x}-{:x}\"", metadata.len(), modified_secs);
+    let modified = DateTime::<Utc>::from(modified);
+    let last_modified = modified.format(HTTP_DATE).to_string();
+
+    let response = if is_not_modified(req, &etag, &modified) {
+        Response::not_modified()
+    } else {
+        match fs::read(&path) {
+            Ok(contents) => Response::file(mime_type(&path), contents),
+            Err(e) => return Response::internal_server_error(&e.to_string()),
+        }
+    };
+
+    response
+        .with_header("ETag", &etag)
+        .with_header("Last-Modified", &last_modified)
+        .with_header("Cache-Control", "no-cache")
+}
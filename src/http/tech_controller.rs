@@ -1,127 +1,238 @@
+use super::export::{sheets, Export};
+use super::form::FormData;
 use super::pages::*;
-use super::{get_route_arg, Response, Status};
+use super::request::{Method, Request};
+use super::Response;
+use crate::model::context::AppContext;
 use crate::model::dao::*;
-use crate::model::pool::PostgrePool;
-use std::collections::HashMap;
-use std::rc::Rc;
+use crate::model::error::AppError;
+use crate::model::money::{Amount, Currency, Money};
+use chrono::Local;
 
-pub fn handle_tech_table(method: &str, pool: Rc<PostgrePool>) -> Response {
-    if method != "GET" {
-        return Response::method_not_allowed();
+pub fn handle_tech_table(req: &Request, ctx: &AppContext) -> Response {
+    let export = match Export::requested(req) {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+    let tech_dao = &ctx.tech;
+    let archived = req.query.contains_key("archived");
+    let tech = match archived {
+        true => tech_dao.get_archived(),
+        false => tech_dao.get_all(),
+    };
+    match tech {
+        Ok(tech) => match export {
+            Some(export) => export.respond(&sheets::tech(&tech, archived)),
+            None => Response::ok(tech_pages::table_page(tech, archived)),
+        },
+        Err(e) => e.into(),
     }
-    let tech_dao = tech::TechDaoImpl::new(pool);
-    match tech_dao.get_all() {
-        Ok(tech) => Response::ok(tech_pages::table_page(tech)),
-        Err(e) => Response::internal_server_error(&e.to_string()),
+}
+
+fn tech_from_form(body: &FormData) -> Result<tech::Tech, AppError> {
+    let price: Amount = body.field("price")?;
+    if price.is_negative() {
+        return Err(AppError::validation("The price cannot be negative"));
     }
+    let currency: Currency = body
+        .field("currency")
+        .map_err(|_| AppError::validation("The currency has to be a three-letter code"))?;
+    let mut tech = tech::Tech::new(
+        body.field("inventory_number")?,
+        body.text("name")?,
+        body.text("model")?,
+        body.text("acquisition_date")?,
+        Money::new(price, currency),
+    )?;
+    tech.category_id = body.optional("category")?;
+    Ok(tech)
 }
 
-pub fn handle_tech_add(
-    method: &str,
-    body: Option<HashMap<String, String>>,
-    pool: Rc<PostgrePool>,
-) -> Response {
-    if method == "GET" {
-        return Response::ok(tech_pages::add_form_get());
-    } else if method == "POST" {
-        let tech_dao = tech::TechDaoImpl::new(pool);
-        let body = body.unwrap();
-        let price: i32 = match body["price"].parse() {
-            Ok(x) => x,
-            Err(e) => return Response::internal_server_error(&e.to_string()),
-        };
-        let inventory_number: i32 = match body["inventory_number"].parse() {
+pub fn handle_tech_add(req: &Request, ctx: &AppContext) -> Response {
+    let category_dao = &ctx.category;
+    let categories = match category_dao.get_all() {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+
+    if req.method == Method::Get {
+        Response::ok(tech_pages::add_form_get(&categories))
+    } else if req.method == Method::Post {
+        let tech_dao = &ctx.tech;
+        let mut tech = match tech_from_form(&req.body) {
             Ok(x) => x,
-            Err(e) => return Response::internal_server_error(&e.to_string()),
+            Err(e) => {
+                let message = Message::new(&e.to_string(), MK::Error);
+                return Response::ok(tech_pages::add_form_post(&categories, message));
+            }
         };
 
-        let mut tech = tech::Tech::new(
-            inventory_number,
-            &body["name"],
-            &body["model"],
-            &body["acquisition_date"],
-            price,
-        );
-
         let message = match tech_dao.insert(&mut tech) {
             Ok(()) => Message::new("Added tech", MK::Notify),
-            Err(_) => Message::new("Failed to add tech", MK::Error),
+            Err(e) => Message::new(&format!("Failed to add tech: {}", e), MK::Error),
         };
-        let response = Response::ok(tech_pages::add_form_post(message));
-        return response;
+
+        Response::ok(tech_pages::add_form_post(&categories, message))
     } else {
-        return Response::method_not_allowed();
+        Response::method_not_allowed()
     }
 }
 
-pub fn handle_tech_edit(
-    method: &str,
-    route: &str,
-    body: Option<HashMap<String, String>>,
-    pool: Rc<PostgrePool>,
-) -> Response {
-    let arg = match get_route_arg::<i32>(route, 2) {
+pub fn handle_tech_edit(req: &Request, ctx: &AppContext) -> Response {
+    let arg = match req.param::<i32>("id") {
         Some(x) => x,
-        None => {
-            return Response::internal_server_error("Couldn't get arguments from url");
-        }
+        None => return Response::not_found(),
     };
 
-    let tech_dao = tech::TechDaoImpl::new(pool);
+    let tech_dao = &ctx.tech;
     let tech = match tech_dao.get_id(arg) {
         Ok(x) => x,
-        Err(e) => return Response::internal_server_error(&e.to_string()),
+        Err(e) => return e.into(),
+    };
+    let category_dao = &ctx.category;
+    let categories = match category_dao.get_all() {
+        Ok(x) => x,
+        Err(e) => return e.into(),
     };
 
-    match method {
-        "GET" => Response::ok(tech_pages::edit_form_get(&tech)),
-        "POST" => {
-            let message = Message::new("Updated tech", MK::Notify);
-            let body = body.unwrap();
-
-            let price: i32 = match body["price"].parse() {
+    match req.method {
+        Method::Get => Response::ok(tech_pages::edit_form_get(&tech, &categories)),
+        Method::Post => {
+            let mut tech = match tech_from_form(&req.body) {
                 Ok(x) => x,
-                Err(e) => return Response::internal_server_error(&e.to_string()),
+                Err(e) => {
+                    let message = Message::new(&e.to_string(), MK::Error);
+                    return Response::ok(tech_pages::edit_form_post(&tech, &categories, message));
+                }
             };
 
-            let inventory_number: i32 = match body["inventory_number"].parse() {
-                Ok(x) => x,
-                Err(e) => return Response::internal_server_error(&e.to_string()),
+            tech.set_id(arg);
+            let message = match tech_dao.update(&tech) {
+                Ok(()) => Message::new("Updated tech", MK::Success),
+                Err(e) => Message::new(&format!("Error on updating tech: {}", e), MK::Error),
             };
+            Response::ok(tech_pages::edit_form_post(&tech, &categories, message))
+        }
+        _ => Response::method_not_allowed(),
+    }
+}
 
-            let mut tech = tech::Tech::new(
-                inventory_number,
-                &body["name"],
-                &body["model"],
-                &body["acquisition_date"],
-                price,
-            );
+fn delete_page(ctx: &AppContext, id: i32, message: Message) -> Response {
+    let tech_dao = &ctx.tech;
+    let tech = match tech_dao.get_id(id) {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+    let transfer_dao = &ctx.transfer;
+    let transfers = match transfer_dao.get_by_tech_id(id) {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
 
-            tech.set_id(arg);
-            tech_dao.update(&tech);
-            let response = Response::ok(tech_pages::edit_form_post(&tech, message));
-            return response;
+    Response::ok(tech_pages::delete_page(&tech, transfers, message))
+}
+
+pub fn handle_tech_delete(req: &Request, ctx: &AppContext) -> Response {
+    let arg = match req.param::<i32>("id") {
+        Some(x) => x,
+        None => return Response::not_found(),
+    };
+
+    match req.method {
+        Method::Get => delete_page(ctx, arg, Message::none()),
+        Method::Post => {
+            let tech_dao = &ctx.tech;
+            match tech_dao.delete(arg) {
+                Ok(()) => Response::found("/tech"),
+                Err(e @ AppError::Conflict(_)) => {
+                    delete_page(ctx, arg, Message::new(&e.to_string(), MK::Error))
+                }
+                Err(e) => e.into(),
+            }
         }
         _ => Response::method_not_allowed(),
     }
 }
 
-pub fn handle_tech_delete(method: &str, route: &str, pool: Rc<PostgrePool>) -> Response {
-    if method != "POST" {
-        return Response::method_not_allowed();
+pub fn handle_tech_archive(req: &Request, ctx: &AppContext) -> Response {
+    let arg = match req.param::<i32>("id") {
+        Some(x) => x,
+        None => return Response::not_found(),
+    };
+
+    let tech_dao = &ctx.tech;
+    match tech_dao.archive(arg) {
+        Ok(()) => Response::found("/tech"),
+        Err(e) => e.into(),
     }
-    let arg = match get_route_arg::<i32>(route, 2) {
+}
+
+pub fn handle_tech_restore(req: &Request, ctx: &AppContext) -> Response {
+    let arg = match req.param::<i32>("id") {
         Some(x) => x,
-        None => {
-            return Response::internal_server_error("Couldn't get arguments from url");
-        }
+        None => return Response::not_found(),
+    };
+
+    let tech_dao = &ctx.tech;
+    match tech_dao.restore(arg) {
+        Ok(()) => Response::found("/tech?archived=true"),
+        Err(e) => e.into(),
+    }
+}
+
+pub fn handle_holdings(req: &Request, ctx: &AppContext) -> Response {
+    let date = match req.as_of_date() {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+    let export = match Export::requested(req) {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+    let tech_dao = &ctx.tech;
+    match tech_dao.get_holdings(date) {
+        Ok(holdings) => match export {
+            Some(export) => export.respond(&sheets::holdings(&holdings, date)),
+            None => Response::ok(tech_pages::holdings_page(holdings, date)),
+        },
+        Err(e) => e.into(),
+    }
+}
+
+pub fn handle_tech_detail(req: &Request, ctx: &AppContext) -> Response {
+    let arg = match req.param::<i32>("id") {
+        Some(x) => x,
+        None => return Response::not_found(),
+    };
+    let date = match req.as_of_date() {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+
+    let tech_dao = &ctx.tech;
+    let tech = match tech_dao.get_id(arg) {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+    let category_dao = &ctx.category;
+    let category = match tech.category_id.map(|x| category_dao.get_id(x)).transpose() {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+    let transfer_dao = &ctx.transfer;
+    let transfers = match transfer_dao.get_by_tech_id(arg) {
+        Ok(x) => x,
+        Err(e) => return e.into(),
     };
 
-    let tech_dao = tech::TechDaoImpl::new(pool);
-    match tech_dao.delete(arg) {
+    let write_off_dao = &ctx.write_off;
+    let write_off = match write_off_dao.get_by_tech_id(arg) {
         Ok(x) => x,
-        Err(e) => return Response::internal_server_error(&e.to_string()),
+        Err(e) => return e.into(),
     };
 
-    Response::found("/tech")
+    let today = Local::now().date_naive();
+    Response::ok(tech_pages::detail_page(
+        &tech, category, transfers, write_off, date, today,
+    ))
 }
@@ -1,38 +1,53 @@
+use super::export::{sheets, Export};
+use super::form::FormData;
 use super::pages::*;
-use super::{get_route_arg, Response, Status};
+use super::request::{Method, Request};
+use super::Response;
+use crate::model::context::AppContext;
 use crate::model::dao::*;
-use crate::model::pool::PostgrePool;
-use std::collections::HashMap;
-use std::rc::Rc;
+use crate::model::error::AppError;
 
-pub fn handle_unit_table(method: &str, pool: Rc<PostgrePool>) -> Response {
-    if method != "GET" {
-        return Response::method_not_allowed();
+pub fn handle_unit_table(req: &Request, ctx: &AppContext) -> Response {
+    let export = match Export::requested(req) {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+    let unit_dao = &ctx.unit;
+    let archived = req.query.contains_key("archived");
+    let units = match archived {
+        true => unit_dao.get_archived(),
+        false => unit_dao.get_all(),
+    };
+    match units {
+        Ok(units) => match export {
+            Some(export) => export.respond(&sheets::units(&units, archived)),
+            None => Response::ok(unit_pages::table_page(units, archived)),
+        },
+        Err(e) => e.into(),
     }
+}
 
-    let tech_dao = unit::UnitDAOImpl::new(pool);
-    match tech_dao.get_all() {
-        Ok(tech) => Response::ok(unit_pages::table_page(tech)),
-        Err(e) => Response::internal_server_error(&e.to_string()),
-    }
+fn unit_from_form(body: &FormData) -> Result<unit::Unit, AppError> {
+    Ok(unit::Unit::new(
+        body.field("unit_number")?,
+        body.text("full_name")?,
+        body.text("short_name")?,
+    ))
 }
 
-pub fn handle_unit_add(
-    method: &str,
-    body: Option<HashMap<String, String>>,
-    pool: Rc<PostgrePool>,
-) -> Response {
-    if method == "GET" {
-        return Response::ok(unit_pages::add_form_get());
-    } else if method == "POST" {
-        let body = body.unwrap();
-        let unit_number: i32 = match body["unit_number"].parse() {
+pub fn handle_unit_add(req: &Request, ctx: &AppContext) -> Response {
+    if req.method == Method::Get {
+        Response::ok(unit_pages::add_form_get())
+    } else if req.method == Method::Post {
+        let mut unit = match unit_from_form(&req.body) {
             Ok(x) => x,
-            Err(e) => return Response::internal_server_error(&e.to_string()),
+            Err(e) => {
+                let message = Message::new(&e.to_string(), MK::Error);
+                return Response::ok(unit_pages::add_form_post(message));
+            }
         };
-        let mut unit = unit::Unit::new(unit_number, &body["full_name"], &body["short_name"]);
 
-        let unit_dao = unit::UnitDAOImpl::new(pool);
+        let unit_dao = &ctx.unit;
         let message = match unit_dao.insert(&mut unit) {
             Err(_) => Message::new(
                 "Tried adding unit(the unit number should be unique)",
@@ -41,104 +56,157 @@ pub fn handle_unit_add(
             Ok(()) => Message::new("Updated unit", MK::Notify),
         };
 
-        let response = Response::ok(unit_pages::add_form_post(message));
-        return response;
+        Response::ok(unit_pages::add_form_post(message))
     } else {
-        return Response::method_not_allowed();
+        Response::method_not_allowed()
     }
 }
 
-pub fn handle_unit_edit(
-    method: &str,
-    route: &str,
-    body: Option<HashMap<String, String>>,
-    pool: Rc<PostgrePool>,
-) -> Response {
-    let arg = match get_route_arg::<i32>(route, 2) {
+pub fn handle_unit_edit(req: &Request, ctx: &AppContext) -> Response {
+    let arg = match req.param::<i32>("id") {
         Some(x) => x,
-        None => {
-            return Response::internal_server_error("Couldn't get arguments from url");
-        }
+        None => return Response::not_found(),
     };
 
-    let unit_dao = unit::UnitDAOImpl::new(pool);
+    let unit_dao = &ctx.unit;
     let unit = match unit_dao.get_id(arg) {
         Ok(x) => x,
-        Err(e) => return Response::internal_server_error(&e.to_string()),
+        Err(e) => return e.into(),
     };
 
-    match method {
-        "GET" => Response::ok(unit_pages::edit_form_get(&unit)),
-        "POST" => {
-            let body = body.unwrap();
-            let unit_number: i32 = match body["unit_number"].parse() {
+    match req.method {
+        Method::Get => Response::ok(unit_pages::edit_form_get(&unit)),
+        Method::Post => {
+            let mut unit = match unit_from_form(&req.body) {
                 Ok(x) => x,
-                Err(e) => return Response::internal_server_error(&e.to_string()),
+                Err(e) => {
+                    let message = Message::new(&e.to_string(), MK::Error);
+                    return Response::ok(unit_pages::edit_form_post(&unit, message));
+                }
             };
-            let mut unit = unit::Unit::new(unit_number, &body["full_name"], &body["short_name"]);
 
             unit.set_id(arg);
 
-            let message = match unit_dao.update(&mut unit) {
-                Err(e) => Message::new(
-                    &format!("Tried updating unit: {}", e.to_string()),
-                    MK::Error,
-                ),
+            let message = match unit_dao.update(&unit) {
+                Err(e) => Message::new(&format!("Tried updating unit: {}", e), MK::Error),
                 Ok(()) => Message::new("Updated unit", MK::Notify),
             };
 
-            let response = Response::ok(unit_pages::edit_form_post(&unit, message));
-            return response;
+            Response::ok(unit_pages::edit_form_post(&unit, message))
         }
         _ => Response::method_not_allowed(),
     }
 }
 
-pub fn handle_unit_delete(method: &str, route: &str, pool: Rc<PostgrePool>) -> Response {
-    if method != "POST" {
-        return Response::method_not_allowed();
-    }
-    let arg = match get_route_arg::<i32>(route, 2) {
-        Some(x) => x,
-        None => {
-            return Response::internal_server_error("Couldn't get arguments from url");
-        }
+fn delete_page(ctx: &AppContext, id: i32, message: Message) -> Response {
+    let unit_dao = &ctx.unit;
+    let unit = match unit_dao.get_id(id) {
+        Ok(x) => x,
+        Err(e) => return e.into(),
     };
-
-    let unit_dao = unit::UnitDAOImpl::new(pool);
-    match unit_dao.delete(arg) {
+    let employee_dao = &ctx.employee;
+    let employees = match employee_dao.get_by_unit_id(id) {
         Ok(x) => x,
-        Err(e) => return Response::internal_server_error(&e.to_string()),
+        Err(e) => return e.into(),
     };
 
-    Response::found("/unit")
+    Response::ok(unit_pages::delete_page(&unit, employees, message))
 }
 
-pub fn handle_view_unit_tech(method: &str, route: &str, pool: Rc<PostgrePool>) -> Response {
-    if method != "GET" {
-        return Response::method_not_allowed();
-    }
-    let arg = match get_route_arg::<i32>(route, 2) {
+pub fn handle_unit_delete(req: &Request, ctx: &AppContext) -> Response {
+    let arg = match req.param::<i32>("id") {
         Some(x) => x,
-        None => {
-            return Response::internal_server_error("Couldn't get arguments from url");
+        None => return Response::not_found(),
+    };
+
+    match req.method {
+        Method::Get => delete_page(ctx, arg, Message::none()),
+        Method::Post => {
+            let unit_dao = &ctx.unit;
+            match unit_dao.delete(arg) {
+                Ok(()) => Response::found("/unit"),
+                Err(e @ AppError::Conflict(_)) => {
+                    delete_page(ctx, arg, Message::new(&e.to_string(), MK::Error))
+                }
+                Err(e) => e.into(),
+            }
         }
+        _ => Response::method_not_allowed(),
+    }
+}
+
+pub fn handle_unit_archive(req: &Request, ctx: &AppContext) -> Response {
+    let arg = match req.param::<i32>("id") {
+        Some(x) => x,
+        None => return Response::not_found(),
+    };
+
+    let unit_dao = &ctx.unit;
+    match unit_dao.archive(arg) {
+        Ok(()) => Response::found("/unit"),
+        Err(e) => e.into(),
+    }
+}
+
+pub fn handle_unit_restore(req: &Request, ctx: &AppContext) -> Response {
+    let arg = match req.param::<i32>("id") {
+        Some(x) => x,
+        None => return Response::not_found(),
     };
-    let tech_dao = tech::TechDaoImpl::new(pool);
-    match tech_dao.get_by_unit_id(arg) {
-        Ok(tech) => Response::ok(tech_pages::table_page_view_unit(tech)),
-        Err(e) => Response::internal_server_error(&e.to_string()),
+
+    let unit_dao = &ctx.unit;
+    match unit_dao.restore(arg) {
+        Ok(()) => Response::found("/unit?archived=true"),
+        Err(e) => e.into(),
     }
 }
 
-pub fn handle_unit_tech(method: &str, pool: Rc<PostgrePool>) -> Response {
-    if method != "GET" {
-        return Response::method_not_allowed();
+pub fn handle_view_unit_tech(req: &Request, ctx: &AppContext) -> Response {
+    let arg = match req.param::<i32>("id") {
+        Some(x) => x,
+        None => return Response::not_found(),
+    };
+    let date = match req.as_of_date() {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+    let export = match Export::requested(req) {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+    let tech_dao = &ctx.tech;
+    match tech_dao.get_by_unit_id(arg, date) {
+        Ok(tech) => match export {
+            Some(export) => export.respond(&sheets::unit_holdings(&tech, arg, date)),
+            None => Response::ok(tech_pages::table_page_view_unit(tech, arg, date)),
+        },
+        Err(e) => e.into(),
     }
+}
 
-    let unit_dao = unit::UnitDAOImpl::new(pool);
+pub fn handle_unit_tech(_req: &Request, ctx: &AppContext) -> Response {
+    let unit_dao = &ctx.unit;
     match unit_dao.get_all() {
         Ok(tech) => Response::ok(unit_pages::table_page_view(tech)),
-        Err(e) => Response::internal_server_error(&e.to_string()),
+        Err(e) => e.into(),
+    }
+}
+
+pub fn handle_book_value(req: &Request, ctx: &AppContext) -> Response {
+    let date = match req.as_of_date() {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+    let export = match Export::requested(req) {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+    let tech_dao = &ctx.tech;
+    match tech_dao.get_holdings(date) {
+        Ok(holdings) => match export {
+            Some(export) => export.respond(&sheets::book_value(&holdings, date)),
+            None => Response::ok(unit_pages::book_value_page(holdings, date)),
+        },
+        Err(e) => e.into(),
     }
 }
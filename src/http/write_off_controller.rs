@@ -0,0 +1,155 @@
+use super::controller::read_field;
+use super::export::{sheets, Export};
+use super::form::FormData;
+use super::pages::*;
+use super::request::{Method, Request};
+use super::Response;
+use crate::model::context::AppContext;
+use crate::model::dao::write_off::{Disposal, WriteOff};
+use crate::model::error::AppError;
+use crate::model::validation::{FieldErrors, WriteOffValidator};
+use chrono::{Datelike, Local, NaiveDate};
+
+pub fn handle_write_off_register(req: &Request, ctx: &AppContext) -> Response {
+    let year = match req.query.optional::<i32>("year") {
+        Ok(x) => x.unwrap_or_else(|| Local::now().year()),
+        Err(e) => return e.into(),
+    };
+    let export = match Export::requested(req) {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+
+    let write_off_dao = &ctx.write_off;
+    match write_off_dao.get_by_year(year) {
+        Ok(register) => match export {
+            Some(export) => export.respond(&sheets::write_offs(&register, year)),
+            None => Response::ok(write_off_pages::register_page(register, year)),
+        },
+        Err(e) => e.into(),
+    }
+}
+
+/// The write-off described by the submitted form.
+fn write_off_from_form(
+    tech_id: i32,
+    body: &FormData,
+    errors: &mut FieldErrors,
+) -> Option<WriteOff> {
+    let date: Option<NaiveDate> = read_field(body, "date", "Not valid date value", errors);
+    let employee_id = read_field(body, "employee", "Invalid employee value", errors);
+    let residual_value = read_field(
+        body,
+        "residual_value",
+        "The residual value has to be an amount with at most two decimal places",
+        errors,
+    );
+    let disposal = match body.text("disposal").and_then(Disposal::parse) {
+        Ok(x) => Some(x),
+        Err(e) => {
+            errors.add("disposal", &e.to_string());
+            None
+        }
+    };
+
+    match (date, disposal, employee_id, residual_value) {
+        (Some(date), Some(disposal), Some(employee_id), Some(residual_value)) => {
+            Some(WriteOff::new(
+                tech_id,
+                date,
+                disposal,
+                body.get("reason").unwrap_or(""),
+                body.get("act_number").unwrap_or(""),
+                employee_id,
+                residual_value,
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// Checks the write-off and records it in the same unit of work.
+fn save_write_off(ctx: &AppContext, draft: &mut WriteOff) -> Result<FieldErrors, AppError> {
+    ctx.with_transaction(|tx| {
+        let errors = WriteOffValidator::new(tx).check(draft)?;
+        if errors.is_empty() {
+            tx.write_off.insert(draft)?;
+        }
+        Ok(errors)
+    })
+}
+
+fn invalid() -> Message {
+    Message::new("Please correct the highlighted fields", MK::Error)
+}
+
+pub fn handle_tech_write_off(req: &Request, ctx: &AppContext) -> Response {
+    let arg = match req.param::<i32>("id") {
+        Some(x) => x,
+        None => return Response::not_found(),
+    };
+
+    let tech_dao = &ctx.tech;
+    let tech = match tech_dao.get_id(arg) {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+    let employee_dao = &ctx.employee;
+    let employees = match employee_dao.get_all() {
+        Ok(x) => x,
+        Err(e) => return e.into(),
+    };
+
+    match req.method {
+        Method::Get => Response::ok(write_off_pages::form_page(
+            &tech,
+            employees,
+            None,
+            FieldErrors::new(),
+            Message::none(),
+        )),
+        Method::Post => {
+            let mut errors = FieldErrors::new();
+            let mut draft = match write_off_from_form(arg, &req.body, &mut errors) {
+                Some(x) => x,
+                None => {
+                    return Response::ok(write_off_pages::form_page(
+                        &tech,
+                        employees,
+                        None,
+                        errors,
+                        invalid(),
+                    ));
+                }
+            };
+
+            let (errors, message) = match save_write_off(ctx, &mut draft) {
+                Ok(errors) if errors.is_empty() => {
+                    info!("Wrote off tech {} under act {}", arg, draft.act_number);
+                    return Response::found(&format!("/tech/{}", arg));
+                }
+                Ok(errors) => (errors, invalid()),
+                Err(AppError::Sql {
+                    constraint: Some(constraint),
+                    ..
+                }) if constraint == "write_off_act_number_key" => {
+                    let mut errors = FieldErrors::new();
+                    errors.add("act_number", "An act with this number already exists");
+                    (errors, invalid())
+                }
+                Err(e @ AppError::Conflict(_)) => {
+                    (FieldErrors::new(), Message::new(&e.to_string(), MK::Error))
+                }
+                Err(e) => return e.into(),
+            };
+            Response::ok(write_off_pages::form_page(
+                &tech,
+                employees,
+                Some(&draft),
+                errors,
+                message,
+            ))
+        }
+        _ => Response::method_not_allowed(),
+    }
+}
@@ -1,10 +1,17 @@
+#[macro_use]
+mod logger;
+mod config;
 mod http;
 #[allow(unused_variables, dead_code, unused_imports)]
 mod model;
+use config::Config;
 use http::connection::open_connection;
-use model::dao::tech::{self, Tech};
+use model::context::AppContext;
+use model::dao::memory::MemoryStore;
+use model::migrations;
+use model::money;
 use model::pool::create_pool;
-use std::rc::Rc;
+use std::sync::Arc;
 
 // #[allow(dead_code)]
 // fn test_pool() {
@@ -15,7 +22,51 @@ use std::rc::Rc;
 //     tech_dao.insert(tech);
 // }
 
+/// Prints a startup error and stops; nothing is running yet that needs cleaning up.
+fn exit_with(message: &str) -> ! {
+    eprintln!("Error: {}", message);
+    std::process::exit(1);
+}
+
 fn main() {
-    let pool = Rc::new(create_pool());
-    open_connection(pool);
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let config = match Config::load(&args) {
+        Ok(x) => x,
+        Err(e) => exit_with(&e.to_string()),
+    };
+    if config.help {
+        println!("{}", config::USAGE);
+        return;
+    }
+    logger::set_level(config.log_level);
+    money::set_locale(config.locale);
+    // Acts still print as HTML without a font, so this is not worth stopping for.
+    if let Err(e) = http::pdf::load_font(&config.pdf_font) {
+        warn!("Cannot load the PDF font, PDF documents are disabled: {}", e);
+    }
+
+    let context = match &config.database {
+        None => {
+            info!("Using the in-memory store, changes are lost on exit");
+            AppContext::in_memory(Arc::new(MemoryStore::with_demo_data()))
+        }
+        Some(database) => {
+            let pool = match create_pool(database) {
+                Ok(x) => Arc::new(x),
+                Err(e) => exit_with(&e.to_string()),
+            };
+            match migrations::run(&pool) {
+                Ok(0) => info!("Database schema is up to date"),
+                Ok(count) => info!("Applied {} migration(s)", count),
+                Err(e) => exit_with(&e.to_string()),
+            }
+            if config.migrate_only {
+                return;
+            }
+            AppContext::postgres(pool)
+        }
+    };
+    if let Err(e) = open_connection(Arc::new(context), &config.server) {
+        exit_with(&format!("cannot listen on {}: {}", config.server.bind_address, e));
+    }
 }
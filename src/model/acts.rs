@@ -0,0 +1,126 @@
+//! Acceptance acts: the paper the giver and the receiver sign when tech changes
+//! hands. Transfers to the same employee on the same day from the same previous
+//! holder were one handover, so they go on one act.
+use super::context::AppContext;
+use super::dao::employee::Employee;
+use super::dao::room::Room;
+use super::dao::tech::Tech;
+use super::dao::transfer::TransferDetails;
+use super::dao::unit::Unit;
+use super::error::AppError;
+use super::money::Totals;
+use chrono::NaiveDate;
+
+/// One side of the handover.
+#[derive(Debug, Clone)]
+pub struct Party {
+    pub employee: Employee,
+    pub unit: Option<Unit>,
+}
+
+impl Party {
+    fn from_transfer(transfer: &TransferDetails) -> Option<Party> {
+        Some(Party {
+            employee: transfer.employee.clone()?,
+            unit: transfer.unit.clone(),
+        })
+    }
+
+    pub fn full_name(&self) -> String {
+        format!(
+            "{} {} {}",
+            self.employee.last_name, self.employee.first_name, self.employee.middle_name
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct ActLine {
+    pub transfer_id: i32,
+    pub tech: Tech,
+    pub room: Option<Room>,
+}
+
+#[derive(Debug)]
+pub struct TransferAct {
+    /// The lowest transfer id on the act, so each of its transfers prints the same act.
+    pub number: i32,
+    pub date: NaiveDate,
+    /// Empty when the items are issued for the first time, from stock.
+    pub giver: Option<Party>,
+    pub receiver: Party,
+    pub lines: Vec<ActLine>,
+}
+
+impl TransferAct {
+    pub fn total(&self) -> Totals {
+        self.lines.iter().map(|x| x.tech.price).collect()
+    }
+}
+
+/// The transfer of the same tech just before `transfer`, whose employee gave it away.
+fn previous(
+    ctx: &AppContext,
+    transfer: &TransferDetails,
+) -> Result<Option<TransferDetails>, AppError> {
+    let Some(tech) = &transfer.tech else {
+        return Ok(None);
+    };
+    let history = ctx.transfer.get_by_tech_id(tech.id())?;
+    Ok(history
+        .into_iter()
+        .take_while(|x| x.id != transfer.id)
+        .last())
+}
+
+fn holder(transfer: &Option<TransferDetails>) -> Option<i32> {
+    transfer
+        .as_ref()
+        .and_then(|x| x.employee.as_ref())
+        .map(|x| x.id())
+}
+
+/// The act that `transfer_id` was handed over under.
+pub fn transfer_act(ctx: &AppContext, transfer_id: i32) -> Result<TransferAct, AppError> {
+    let transfer = ctx.transfer.get_id_with_unit(transfer_id)?.inner;
+    let same_day: Vec<TransferDetails> = ctx
+        .transfer
+        .get_by_employee_id(transfer.employee_id)?
+        .into_iter()
+        .filter(|x| x.transfer_date == transfer.transfer_date)
+        .collect();
+    let this = same_day
+        .iter()
+        .find(|x| x.id == transfer_id)
+        .ok_or_else(|| AppError::not_found("Transfer"))?;
+    let receiver = Party::from_transfer(this).ok_or_else(|| AppError::not_found("Employee"))?;
+    let given_by = previous(ctx, this)?;
+    let giver_id = holder(&given_by);
+
+    let mut lines = vec![];
+    for details in &same_day {
+        if holder(&previous(ctx, details)?) != giver_id {
+            continue;
+        }
+        let Some(tech) = details.tech.clone() else {
+            continue;
+        };
+        lines.push(ActLine {
+            transfer_id: details.id,
+            tech,
+            room: details.room.clone(),
+        });
+    }
+
+    Ok(TransferAct {
+        number: lines
+            .iter()
+            .map(|x| x.transfer_id)
+            .min()
+            .unwrap_or(transfer_id),
+        date: transfer.transfer_date,
+        giver: given_by.as_ref().and_then(Party::from_transfer),
+        receiver,
+        lines,
+    })
+}
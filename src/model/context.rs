@@ -0,0 +1,79 @@
+use super::dao::audit::{AuditDAOImpl, AuditDao};
+use super::dao::category::{CategoryDAOImpl, CategoryDao};
+use super::dao::employee::{EmployeeDAOImpl, EmployeeDao};
+use super::dao::memory::MemoryStore;
+use super::dao::room::{RoomDAOImpl, RoomDao};
+use super::dao::tech::{TechDao, TechDaoImpl};
+use super::dao::transfer::{TransferDAOImpl, TransferDao};
+use super::dao::unit::{UnitDAOImpl, UnitDao};
+use super::dao::write_off::{WriteOffDAOImpl, WriteOffDao};
+use super::error::AppError;
+use super::pool::PostgrePool;
+use super::transaction::Db;
+use std::sync::Arc;
+
+/// The DAOs handed to every controller, so the same handlers run against
+/// Postgres or the in-memory store.
+pub struct AppContext {
+    pub tech: Arc<dyn TechDao>,
+    pub unit: Arc<dyn UnitDao>,
+    pub employee: Arc<dyn EmployeeDao>,
+    pub room: Arc<dyn RoomDao>,
+    pub transfer: Arc<dyn TransferDao>,
+    pub write_off: Arc<dyn WriteOffDao>,
+    pub category: Arc<dyn CategoryDao>,
+    pub audit: Arc<dyn AuditDao>,
+    backend: Backend,
+}
+
+enum Backend {
+    Postgres(Db),
+    Memory(Arc<MemoryStore>),
+}
+
+impl AppContext {
+    pub fn postgres(pool: Arc<PostgrePool>) -> AppContext {
+        Self::with_db(Db::Pool(pool))
+    }
+
+    fn with_db(db: Db) -> AppContext {
+        AppContext {
+            tech: Arc::new(TechDaoImpl::new(db.clone())),
+            unit: Arc::new(UnitDAOImpl::new(db.clone())),
+            employee: Arc::new(EmployeeDAOImpl::new(db.clone())),
+            room: Arc::new(RoomDAOImpl::new(db.clone())),
+            transfer: Arc::new(TransferDAOImpl::new(db.clone())),
+            write_off: Arc::new(WriteOffDAOImpl::new(db.clone())),
+            category: Arc::new(CategoryDAOImpl::new(db.clone())),
+            audit: Arc::new(AuditDAOImpl::new(db.clone())),
+            backend: Backend::Postgres(db),
+        }
+    }
+
+    pub fn in_memory(store: Arc<MemoryStore>) -> AppContext {
+        AppContext {
+            tech: store.clone(),
+            unit: store.clone(),
+            employee: store.clone(),
+            room: store.clone(),
+            transfer: store.clone(),
+            write_off: store.clone(),
+            category: store.clone(),
+            audit: store.clone(),
+            backend: Backend::Memory(store),
+        }
+    }
+
+    /// Runs `f` as one unit of work: every DAO call it makes through the context it
+    /// is given commits if `f` returns `Ok` and is undone otherwise. Calls made on
+    /// `self` inside `f` are not part of it, and on the in-memory store they block.
+    pub fn with_transaction<T, F>(&self, f: F) -> Result<T, AppError>
+    where
+        F: FnOnce(&AppContext) -> Result<T, AppError>,
+    {
+        match &self.backend {
+            Backend::Postgres(db) => db.transaction(|db| f(&Self::with_db(db.clone()))),
+            Backend::Memory(store) => store.transaction(|store| f(&Self::in_memory(store))),
+        }
+    }
+}
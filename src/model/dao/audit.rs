@@ -0,0 +1,501 @@
+use super::tech::{Tech, TechHolding};
+use super::{AppError, Db, Row};
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// What the auditors made of an item on the list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Finding {
+    /// Not checked yet.
+    Pending,
+    /// Where the books place it.
+    Found,
+    Missing,
+    /// Found, but not where the books place it; the item's location says where.
+    FoundElsewhere,
+}
+
+impl Finding {
+    pub const ALL: [Finding; 4] = [
+        Finding::Pending,
+        Finding::Found,
+        Finding::Missing,
+        Finding::FoundElsewhere,
+    ];
+    /// The findings an auditor can mark an item with.
+    pub const MARKS: [Finding; 3] = [Finding::Found, Finding::Missing, Finding::FoundElsewhere];
+
+    /// The value stored in `audit_item.finding`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Finding::Pending => "pending",
+            Finding::Found => "found",
+            Finding::Missing => "missing",
+            Finding::FoundElsewhere => "found_elsewhere",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Finding::Pending => "Not checked",
+            Finding::Found => "Found",
+            Finding::Missing => "Missing",
+            Finding::FoundElsewhere => "Found elsewhere",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Finding, AppError> {
+        Finding::ALL
+            .into_iter()
+            .find(|x| x.as_str() == value)
+            .ok_or_else(|| AppError::validation("Unknown audit finding"))
+    }
+}
+
+/// What an audit covers: everything a unit's employees hold, or everything in a room.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditScope {
+    Unit(i32),
+    Room(i32),
+}
+
+impl AuditScope {
+    fn from_columns(unit_id: Option<i32>, room_id: Option<i32>) -> Result<AuditScope, AppError> {
+        match (unit_id, room_id) {
+            (Some(x), None) => Ok(AuditScope::Unit(x)),
+            (None, Some(x)) => Ok(AuditScope::Room(x)),
+            _ => Err(AppError::validation(
+                "An audit covers either a unit or a room",
+            )),
+        }
+    }
+
+    pub fn unit_id(&self) -> Option<i32> {
+        match self {
+            AuditScope::Unit(x) => Some(*x),
+            AuditScope::Room(_) => None,
+        }
+    }
+
+    pub fn room_id(&self) -> Option<i32> {
+        match self {
+            AuditScope::Unit(_) => None,
+            AuditScope::Room(x) => Some(*x),
+        }
+    }
+
+    /// Whether the books place `holding` inside the scope.
+    pub fn covers(&self, holding: &TechHolding) -> bool {
+        match self {
+            AuditScope::Unit(x) => holding.unit_id == *x,
+            AuditScope::Room(x) => holding.room_id == *x,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Audit {
+    id: Option<i32>,
+    pub scope: AuditScope,
+    /// The day whose holdings the list was taken from.
+    pub audit_date: NaiveDate,
+    pub opened_at: DateTime<Utc>,
+    /// Once set, the findings are final.
+    pub closed_at: Option<DateTime<Utc>>,
+}
+
+impl Audit {
+    fn from_row(row: &Row) -> Result<Audit, AppError> {
+        Ok(Audit {
+            id: row.get("id"),
+            scope: AuditScope::from_columns(row.get("unit_id"), row.get("room_id"))?,
+            audit_date: row.get("audit_date"),
+            opened_at: row.get("opened_at"),
+            closed_at: row.get("closed_at"),
+        })
+    }
+
+    pub fn new(scope: AuditScope, audit_date: NaiveDate) -> Audit {
+        Audit {
+            id: None,
+            scope,
+            audit_date,
+            opened_at: Utc::now(),
+            closed_at: None,
+        }
+    }
+
+    pub fn id(&self) -> i32 {
+        self.id.unwrap_or(0)
+    }
+
+    pub fn set_id(&mut self, id: i32) {
+        self.id = Some(id)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed_at.is_some()
+    }
+}
+
+/// A line of an audit. What the books said about the item is copied in when the
+/// line is added, so later transfers and edits do not rewrite the report.
+#[derive(Debug, Clone)]
+pub struct AuditItem {
+    id: Option<i32>,
+    pub audit_id: i32,
+    pub inventory_number: i32,
+    /// Empty for an inventory number the books do not know, or a tech since deleted.
+    pub tech_id: Option<i32>,
+    pub name: String,
+    pub model: String,
+    /// Whether the books placed the item inside the audit's scope.
+    pub expected: bool,
+    pub book_room_number: Option<i32>,
+    pub book_holder: String,
+    pub finding: Finding,
+    /// Where the item actually turned up, for items not where the books place them.
+    pub location: String,
+}
+
+impl AuditItem {
+    fn from_row(row: &Row) -> Result<AuditItem, AppError> {
+        Ok(AuditItem {
+            id: row.get("id"),
+            audit_id: row.get("audit_id"),
+            inventory_number: row.get("inventory_number"),
+            tech_id: row.get("tech_id"),
+            name: row.get("name"),
+            model: row.get("model"),
+            expected: row.get("expected"),
+            book_room_number: row.get("book_room_number"),
+            book_holder: row.get("book_holder"),
+            finding: Finding::parse(row.get("finding"))?,
+            location: row.get("location"),
+        })
+    }
+
+    /// An item the books place inside the audit's scope, still to be checked.
+    pub fn expected(holding: &TechHolding) -> AuditItem {
+        AuditItem {
+            id: None,
+            audit_id: 0,
+            inventory_number: holding.inventory_number,
+            tech_id: Some(holding.tech_id),
+            name: holding.name.clone(),
+            model: holding.model.clone(),
+            expected: true,
+            book_room_number: Some(holding.room_number),
+            book_holder: holder(holding),
+            finding: Finding::Pending,
+            location: String::new(),
+        }
+    }
+
+    /// An item found during the audit that the books place elsewhere, or do not
+    /// know at all when `tech` is empty.
+    pub fn unexpected(
+        inventory_number: i32,
+        tech: Option<&Tech>,
+        holding: Option<&TechHolding>,
+        location: &str,
+    ) -> AuditItem {
+        AuditItem {
+            id: None,
+            audit_id: 0,
+            inventory_number,
+            tech_id: tech.map(|x| x.id()),
+            name: tech.map(|x| x.name.clone()).unwrap_or_default(),
+            model: tech.map(|x| x.model.clone()).unwrap_or_default(),
+            expected: false,
+            book_room_number: holding.map(|x| x.room_number),
+            book_holder: holding.map(holder).unwrap_or_default(),
+            finding: Finding::Found,
+            location: location.into(),
+        }
+    }
+
+    pub fn id(&self) -> i32 {
+        self.id.unwrap_or(0)
+    }
+
+    pub fn set_id(&mut self, id: i32) {
+        self.id = Some(id)
+    }
+
+    /// Whether the item belongs in the discrepancy report.
+    pub fn is_discrepancy(&self) -> bool {
+        !self.expected || matches!(self.finding, Finding::Missing | Finding::FoundElsewhere)
+    }
+}
+
+fn holder(holding: &TechHolding) -> String {
+    format!(
+        "{} {} {}",
+        holding.last_name, holding.first_name, holding.middle_name
+    )
+}
+
+/// An audit with what it covers and how far it got, for the list of audits.
+#[derive(Debug)]
+pub struct AuditSummary {
+    pub audit: Audit,
+    /// The unit's full name or the room number.
+    pub scope_name: String,
+    pub items: i64,
+    pub pending: i64,
+    pub discrepancies: i64,
+}
+
+impl AuditSummary {
+    fn from_row(row: &Row) -> Result<AuditSummary, AppError> {
+        Ok(AuditSummary {
+            audit: Audit::from_row(row)?,
+            scope_name: row.get("scope_name"),
+            items: row.get("items"),
+            pending: row.get("pending"),
+            discrepancies: row.get("discrepancies"),
+        })
+    }
+}
+
+pub(super) fn closed(closed_at: DateTime<Utc>) -> AppError {
+    AppError::Conflict(format!(
+        "The audit was closed on {}; its findings cannot change any more",
+        closed_at.date_naive()
+    ))
+}
+
+pub(super) fn unchecked(items: i64) -> AppError {
+    AppError::Conflict(format!(
+        "{} item(s) are not checked yet; mark every item before closing the audit",
+        items
+    ))
+}
+
+/// Units and rooms keep their audits, as the reports refer to them.
+pub(super) fn audited(what: &str, audits: i64) -> AppError {
+    AppError::Conflict(format!(
+        "The {} was audited {} time(s); its audit reports have to be kept",
+        what, audits
+    ))
+}
+
+pub struct AuditDAOImpl {
+    db: Db,
+}
+
+pub trait AuditDao: Send + Sync {
+    /// Records the audit together with its list of items.
+    fn open(&self, audit: &mut Audit, items: &mut [AuditItem]) -> Result<(), AppError>;
+    /// Every audit, the latest opened first.
+    fn get_all(&self) -> Result<Vec<AuditSummary>, AppError>;
+    fn get_id(&self, id: i32) -> Result<AuditSummary, AppError>;
+    /// The items the books expected first, then the unexpected ones, each by
+    /// inventory number.
+    fn get_items(&self, audit_id: i32) -> Result<Vec<AuditItem>, AppError>;
+    /// Records the finding for an expected item of an open audit.
+    fn mark(
+        &self,
+        audit_id: i32,
+        item_id: i32,
+        finding: Finding,
+        location: &str,
+    ) -> Result<(), AppError>;
+    /// Adds an unexpected item to an open audit.
+    fn add_item(&self, item: &mut AuditItem) -> Result<(), AppError>;
+    /// Takes an unexpected item added by mistake off an open audit.
+    fn remove_item(&self, audit_id: i32, item_id: i32) -> Result<(), AppError>;
+    /// Closes the audit, refusing while any item is not checked.
+    fn close(&self, id: i32) -> Result<(), AppError>;
+}
+
+impl AuditDAOImpl {
+    pub fn new(db: Db) -> Self {
+        Self { db }
+    }
+
+    const INSERT: &'static str = "INSERT INTO audit (unit_id, room_id, audit_date) VALUES ($1, $2, $3) RETURNING id, opened_at;";
+    const INSERT_ITEM: &'static str = "INSERT INTO audit_item (audit_id, inventory_number, tech_id, name, model, expected, book_room_number, book_holder, finding, location) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) RETURNING id;";
+    const FIND_ITEMS: &'static str =
+        "SELECT * FROM audit_item WHERE audit_id = $1 ORDER BY expected DESC, inventory_number;";
+    const LOCK: &'static str = "SELECT closed_at FROM audit WHERE id = $1 FOR UPDATE;";
+    const MARK: &'static str = "UPDATE audit_item SET finding = $1, location = $2 WHERE id = $3 AND audit_id = $4 AND expected;";
+    const DELETE_ITEM: &'static str =
+        "DELETE FROM audit_item WHERE id = $1 AND audit_id = $2 AND NOT expected;";
+    const COUNT_PENDING: &'static str =
+        "SELECT COUNT(*) FROM audit_item WHERE audit_id = $1 AND finding = 'pending';";
+    const CLOSE: &'static str = "UPDATE audit SET closed_at = now() WHERE id = $1;";
+    const FIND_SUMMARY: &'static str = r#"SELECT
+        a.*,
+        COALESCE(u.full_name, 'Room ' || r.room_number) AS scope_name,
+        COUNT(i.id) AS items,
+        COUNT(i.id) FILTER (WHERE i.finding = 'pending') AS pending,
+        COUNT(i.id) FILTER (
+            WHERE NOT i.expected OR i.finding IN ('missing', 'found_elsewhere')
+        ) AS discrepancies
+    FROM
+        audit a
+    LEFT JOIN unit u ON a.unit_id = u.id
+    LEFT JOIN room r ON a.room_id = r.id
+    LEFT JOIN audit_item i ON i.audit_id = a.id"#;
+    const GROUP_SUMMARY: &'static str = "GROUP BY a.id, u.full_name, r.room_number";
+
+    /// Locks the audit for the rest of the transaction, failing if it is closed.
+    fn lock_open(db: &Db, id: i32) -> Result<(), AppError> {
+        let mut conn = db.get()?;
+        let row = conn
+            .query_opt(Self::LOCK, &[&id])?
+            .ok_or_else(|| AppError::not_found("Audit"))?;
+        match row.get::<_, Option<DateTime<Utc>>>(0) {
+            Some(closed_at) => Err(closed(closed_at)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl AuditDao for AuditDAOImpl {
+    fn open(&self, audit: &mut Audit, items: &mut [AuditItem]) -> Result<(), AppError> {
+        self.db.transaction(|db| {
+            let mut conn = db.get()?;
+            let row = conn.query_one(
+                Self::INSERT,
+                &[
+                    &audit.scope.unit_id(),
+                    &audit.scope.room_id(),
+                    &audit.audit_date,
+                ],
+            )?;
+            audit.id = Some(row.get(0));
+            audit.opened_at = row.get(1);
+            for item in items.iter_mut() {
+                item.audit_id = audit.id();
+                let id: i32 = conn
+                    .query_one(
+                        Self::INSERT_ITEM,
+                        &[
+                            &item.audit_id,
+                            &item.inventory_number,
+                            &item.tech_id,
+                            &item.name,
+                            &item.model,
+                            &item.expected,
+                            &item.book_room_number,
+                            &item.book_holder,
+                            &item.finding.as_str(),
+                            &item.location,
+                        ],
+                    )
+                    .map(|row| row.get(0))?;
+                item.id = Some(id);
+            }
+            Ok(())
+        })
+    }
+
+    fn get_all(&self) -> Result<Vec<AuditSummary>, AppError> {
+        let mut conn = self.db.get()?;
+        let query = format!(
+            "{} {} ORDER BY a.opened_at DESC, a.id DESC;",
+            Self::FIND_SUMMARY,
+            Self::GROUP_SUMMARY
+        );
+        let mut audits = vec![];
+        for row in conn.query(&query, &[])? {
+            audits.push(AuditSummary::from_row(&row)?);
+        }
+        Ok(audits)
+    }
+
+    fn get_id(&self, id: i32) -> Result<AuditSummary, AppError> {
+        let mut conn = self.db.get()?;
+        let query = format!(
+            "{} WHERE a.id = $1 {};",
+            Self::FIND_SUMMARY,
+            Self::GROUP_SUMMARY
+        );
+        let row = conn
+            .query_opt(&query, &[&id])?
+            .ok_or_else(|| AppError::not_found("Audit"))?;
+        AuditSummary::from_row(&row)
+    }
+
+    fn get_items(&self, audit_id: i32) -> Result<Vec<AuditItem>, AppError> {
+        let mut conn = self.db.get()?;
+        let mut items = vec![];
+        for row in conn.query(Self::FIND_ITEMS, &[&audit_id])? {
+            items.push(AuditItem::from_row(&row)?);
+        }
+        Ok(items)
+    }
+
+    fn mark(
+        &self,
+        audit_id: i32,
+        item_id: i32,
+        finding: Finding,
+        location: &str,
+    ) -> Result<(), AppError> {
+        self.db.transaction(|db| {
+            Self::lock_open(db, audit_id)?;
+            let mut conn = db.get()?;
+            let rows_affected = conn.execute(
+                Self::MARK,
+                &[&finding.as_str(), &location, &item_id, &audit_id],
+            )?;
+            if rows_affected == 0 {
+                return Err(AppError::not_found("Audit item"));
+            }
+            Ok(())
+        })
+    }
+
+    fn add_item(&self, item: &mut AuditItem) -> Result<(), AppError> {
+        self.db.transaction(|db| {
+            Self::lock_open(db, item.audit_id)?;
+            let mut conn = db.get()?;
+            let id: i32 = conn
+                .query_one(
+                    Self::INSERT_ITEM,
+                    &[
+                        &item.audit_id,
+                        &item.inventory_number,
+                        &item.tech_id,
+                        &item.name,
+                        &item.model,
+                        &item.expected,
+                        &item.book_room_number,
+                        &item.book_holder,
+                        &item.finding.as_str(),
+                        &item.location,
+                    ],
+                )
+                .map(|row| row.get(0))?;
+            item.id = Some(id);
+            Ok(())
+        })
+    }
+
+    fn remove_item(&self, audit_id: i32, item_id: i32) -> Result<(), AppError> {
+        self.db.transaction(|db| {
+            Self::lock_open(db, audit_id)?;
+            let mut conn = db.get()?;
+            if conn.execute(Self::DELETE_ITEM, &[&item_id, &audit_id])? == 0 {
+                return Err(AppError::not_found("Audit item"));
+            }
+            Ok(())
+        })
+    }
+
+    fn close(&self, id: i32) -> Result<(), AppError> {
+        self.db.transaction(|db| {
+            Self::lock_open(db, id)?;
+            let mut conn = db.get()?;
+            let pending: i64 = conn.query_one(Self::COUNT_PENDING, &[&id])?.get(0);
+            if pending > 0 {
+                return Err(unchecked(pending));
+            }
+            conn.execute(Self::CLOSE, &[&id])?;
+            Ok(())
+        })
+    }
+}
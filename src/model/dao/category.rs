@@ -0,0 +1,373 @@
+use super::{AppError, Db, Row};
+use crate::model::money::{Amount, Money};
+use chrono::{Datelike, NaiveDate};
+
+/// How a category's tech loses value over its useful life.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepreciationMethod {
+    /// The same amount every month until nothing is left.
+    StraightLine,
+    /// Double the straight-line rate, charged on what is left each month. Once that
+    /// charge falls below spreading the rest evenly, the rest is spread evenly, so the
+    /// value still reaches zero at the end of the useful life.
+    DecliningBalance,
+}
+
+impl DepreciationMethod {
+    pub const ALL: [DepreciationMethod; 2] = [
+        DepreciationMethod::StraightLine,
+        DepreciationMethod::DecliningBalance,
+    ];
+
+    /// The value stored in `tech_category.method`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DepreciationMethod::StraightLine => "straight_line",
+            DepreciationMethod::DecliningBalance => "declining_balance",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            DepreciationMethod::StraightLine => "Straight-line",
+            DepreciationMethod::DecliningBalance => "Declining balance",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<DepreciationMethod, AppError> {
+        DepreciationMethod::ALL
+            .into_iter()
+            .find(|x| x.as_str() == value)
+            .ok_or_else(|| AppError::validation("Unknown depreciation method"))
+    }
+}
+
+/// The depreciation rule of a category.
+#[derive(Debug, Clone, Copy)]
+pub struct Depreciation {
+    pub method: DepreciationMethod,
+    pub useful_life_years: i32,
+}
+
+impl Depreciation {
+    /// What an item bought on `acquired` for `price` is worth on `date`. It loses
+    /// value for every full month since it was bought, each month's charge rounded
+    /// to the kopeck.
+    pub fn book_value(&self, price: Money, acquired: NaiveDate, date: NaiveDate) -> Money {
+        let life = self.useful_life_years.max(1) as i128 * 12;
+        let months = (full_months(acquired, date) as i128).min(life);
+        let price_minor = price.amount.minor() as i128;
+        let value = match self.method {
+            DepreciationMethod::StraightLine => div_round(price_minor * (life - months), life),
+            DepreciationMethod::DecliningBalance => {
+                let mut value = price_minor;
+                for month in 0..months {
+                    let even = div_round(value, life - month);
+                    value -= div_round(value * 2, life).max(even);
+                }
+                value
+            }
+        };
+        Money::new(Amount::from_minor(value as i64), price.currency)
+    }
+}
+
+/// The book value of an item that may have no category, and so no depreciation.
+pub fn book_value(
+    depreciation: Option<Depreciation>,
+    price: Money,
+    acquired: NaiveDate,
+    date: NaiveDate,
+) -> Money {
+    match depreciation {
+        Some(x) => x.book_value(price, acquired, date),
+        None => price,
+    }
+}
+
+/// `a / b` rounded half away from zero, `b` being positive.
+fn div_round(a: i128, b: i128) -> i128 {
+    match a < 0 {
+        true => (a - b / 2) / b,
+        false => (a + b / 2) / b,
+    }
+}
+
+fn full_months(from: NaiveDate, to: NaiveDate) -> u32 {
+    let months = (to.year() - from.year()) * 12 + to.month() as i32 - from.month() as i32;
+    let months = match to.day() < from.day() {
+        true => months - 1,
+        false => months,
+    };
+    months.max(0) as u32
+}
+
+#[derive(Debug, Clone)]
+pub struct TechCategory {
+    id: Option<i32>,
+    pub name: String,
+    pub depreciation: Depreciation,
+}
+
+impl TechCategory {
+    fn from_row(row: Row) -> Result<TechCategory, AppError> {
+        Ok(TechCategory {
+            id: row.get(0),
+            name: row.get(1),
+            depreciation: Depreciation {
+                method: DepreciationMethod::parse(row.get(2))?,
+                useful_life_years: row.get(3),
+            },
+        })
+    }
+
+    pub fn new(name: &str, method: DepreciationMethod, useful_life_years: i32) -> Self {
+        Self {
+            id: None,
+            name: name.into(),
+            depreciation: Depreciation {
+                method,
+                useful_life_years,
+            },
+        }
+    }
+
+    pub fn id(&self) -> i32 {
+        self.id.unwrap_or(0)
+    }
+
+    pub fn set_id(&mut self, id: i32) {
+        self.id = Some(id);
+    }
+}
+
+pub(super) fn still_used(tech: i64) -> AppError {
+    AppError::Conflict(format!(
+        "The category is used by {} tech item(s) and cannot be deleted",
+        tech
+    ))
+}
+
+pub struct CategoryDAOImpl {
+    db: Db,
+}
+
+pub trait CategoryDao: Send + Sync {
+    fn insert(&self, category: &mut TechCategory) -> Result<(), AppError>;
+    fn update(&self, category: &TechCategory) -> Result<(), AppError>;
+    fn get_all(&self) -> Result<Vec<TechCategory>, AppError>;
+    fn get_id(&self, id: i32) -> Result<TechCategory, AppError>;
+    /// Refuses to delete a category that any tech still belongs to.
+    fn delete(&self, id: i32) -> Result<(), AppError>;
+}
+
+impl CategoryDAOImpl {
+    pub fn new(db: Db) -> Self {
+        Self { db }
+    }
+
+    const INSERT: &'static str = "INSERT INTO tech_category (name, method, useful_life_years) VALUES ($1, $2, $3) RETURNING id;";
+    const UPDATE: &'static str =
+        "UPDATE tech_category SET name = $1, method = $2, useful_life_years = $3 WHERE id = $4;";
+    const FIND_ALL: &'static str =
+        "SELECT id, name, method, useful_life_years FROM tech_category ORDER BY name;";
+    const FIND_ID: &'static str =
+        "SELECT id, name, method, useful_life_years FROM tech_category WHERE id = $1;";
+    const COUNT_TECH: &'static str = "SELECT COUNT(*) FROM tech WHERE category_id = $1;";
+    const DELETE: &'static str = "DELETE FROM tech_category WHERE id = $1;";
+}
+
+impl CategoryDao for CategoryDAOImpl {
+    fn insert(&self, category: &mut TechCategory) -> Result<(), AppError> {
+        let mut conn = self.db.get()?;
+        let id: i32 = conn
+            .query_one(
+                Self::INSERT,
+                &[
+                    &category.name,
+                    &category.depreciation.method.as_str(),
+                    &category.depreciation.useful_life_years,
+                ],
+            )
+            .map(|row| row.get(0))?;
+        category.id = Some(id);
+        Ok(())
+    }
+
+    fn update(&self, category: &TechCategory) -> Result<(), AppError> {
+        let mut conn = self.db.get()?;
+        let rows_affected = conn.execute(
+            Self::UPDATE,
+            &[
+                &category.name,
+                &category.depreciation.method.as_str(),
+                &category.depreciation.useful_life_years,
+                &category.id,
+            ],
+        )?;
+        if rows_affected == 0 {
+            return Err(AppError::not_found("Category"));
+        }
+        Ok(())
+    }
+
+    fn get_all(&self) -> Result<Vec<TechCategory>, AppError> {
+        let mut conn = self.db.get()?;
+        let mut categories = vec![];
+        for row in conn.query(Self::FIND_ALL, &[])? {
+            categories.push(TechCategory::from_row(row)?);
+        }
+        Ok(categories)
+    }
+
+    fn get_id(&self, id: i32) -> Result<TechCategory, AppError> {
+        let mut conn = self.db.get()?;
+        let row = conn
+            .query_opt(Self::FIND_ID, &[&id])?
+            .ok_or_else(|| AppError::not_found("Category"))?;
+        TechCategory::from_row(row)
+    }
+
+    fn delete(&self, id: i32) -> Result<(), AppError> {
+        self.db.transaction(|db| {
+            let mut conn = db.get()?;
+            let tech: i64 = conn.query_one(Self::COUNT_TECH, &[&id])?.get(0);
+            if tech > 0 {
+                return Err(still_used(tech));
+            }
+            let rows_affected = conn.execute(Self::DELETE, &[&id])?;
+            if rows_affected == 0 {
+                return Err(AppError::not_found("Category"));
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::money::Currency;
+
+    fn date(value: &str) -> NaiveDate {
+        value.parse().unwrap()
+    }
+
+    fn rub(minor: i64) -> Money {
+        Money::new(Amount::from_minor(minor), Currency::RUB)
+    }
+
+    /// The value, `months` after 2024-01-15, of an item bought that day for `price`.
+    fn value_after(method: DepreciationMethod, years: i32, price: i64, months: u32) -> i64 {
+        let depreciation = Depreciation {
+            method,
+            useful_life_years: years,
+        };
+        let acquired = date("2024-01-15");
+        let on = acquired
+            .checked_add_months(chrono::Months::new(months))
+            .unwrap();
+        depreciation
+            .book_value(rub(price), acquired, on)
+            .amount
+            .minor()
+    }
+
+    #[test]
+    fn div_round_rounds_half_away_from_zero() {
+        assert_eq!(div_round(4, 2), 2);
+        assert_eq!(div_round(5, 2), 3);
+        assert_eq!(div_round(-5, 2), -3);
+        assert_eq!(div_round(1, 3), 0);
+        assert_eq!(div_round(2, 3), 1);
+        assert_eq!(div_round(-2, 3), -1);
+        assert_eq!(div_round(0, 7), 0);
+    }
+
+    #[test]
+    fn full_months_counts_only_whole_months() {
+        let months = |from, to| full_months(date(from), date(to));
+        assert_eq!(months("2024-01-15", "2024-01-15"), 0);
+        assert_eq!(months("2024-01-15", "2024-02-14"), 0);
+        assert_eq!(months("2024-01-15", "2024-02-15"), 1);
+        assert_eq!(months("2023-12-10", "2024-01-10"), 1);
+        assert_eq!(months("2024-01-31", "2024-02-29"), 0);
+        assert_eq!(months("2024-01-31", "2024-03-01"), 1);
+        assert_eq!(months("2024-01-15", "2026-01-15"), 24);
+        // Before the item was bought.
+        assert_eq!(months("2024-01-15", "2023-06-01"), 0);
+    }
+
+    #[test]
+    fn straight_line_loses_the_same_each_month() {
+        let value = |months| value_after(DepreciationMethod::StraightLine, 1, 120_000, months);
+        assert_eq!(value(0), 120_000);
+        assert_eq!(value(1), 110_000);
+        assert_eq!(value(5), 70_000);
+        assert_eq!(value(11), 10_000);
+        assert_eq!(value(12), 0);
+        assert_eq!(value(40), 0);
+        // 1 000,00 over 36 months leaves 1/36 of it, rounded to the kopeck.
+        assert_eq!(
+            value_after(DepreciationMethod::StraightLine, 3, 100_000, 35),
+            2_778
+        );
+        assert_eq!(value_after(DepreciationMethod::StraightLine, 1, 1, 6), 1);
+    }
+
+    #[test]
+    fn declining_balance_switches_to_even_charges() {
+        let value = |months| value_after(DepreciationMethod::DecliningBalance, 1, 120_000, months);
+        assert_eq!(value(0), 120_000);
+        // Double the straight-line rate on what is left.
+        assert_eq!(value(1), 100_000);
+        assert_eq!(value(2), 83_333);
+        assert_eq!(value(6), 40_187);
+        // Month 7 charges the same either way; from month 8 the rest is spread evenly.
+        assert_eq!(value(7), 33_489);
+        assert_eq!(value(8), 26_791);
+        assert_eq!(value(11), 6_697);
+        assert_eq!(value(12), 0);
+        assert_eq!(value(30), 0);
+    }
+
+    #[test]
+    fn book_value_outside_the_useful_life() {
+        let acquired = date("2024-01-15");
+        for method in DepreciationMethod::ALL {
+            let depreciation = Depreciation {
+                method,
+                useful_life_years: 2,
+            };
+            let price = rub(50_000);
+            assert_eq!(
+                depreciation.book_value(price, acquired, date("2023-01-01")),
+                price
+            );
+            assert_eq!(
+                depreciation.book_value(price, acquired, date("2026-01-15")),
+                rub(0)
+            );
+            assert_eq!(
+                depreciation.book_value(price, acquired, date("2030-06-01")),
+                rub(0)
+            );
+        }
+        // A useful life below a year is taken as one year.
+        let depreciation = Depreciation {
+            method: DepreciationMethod::StraightLine,
+            useful_life_years: 0,
+        };
+        assert_eq!(
+            depreciation.book_value(rub(120_000), acquired, date("2024-02-15")),
+            rub(110_000)
+        );
+    }
+
+    #[test]
+    fn no_category_keeps_the_price() {
+        let price = rub(99_999);
+        let value = book_value(None, price, date("2020-01-01"), date("2030-01-01"));
+        assert_eq!(value, price);
+    }
+}
@@ -1,7 +1,7 @@
-use super::{tech::Tech, Error, PostgrePool, Row};
-use std::rc::Rc;
+use super::{tech::Tech, AppError, Db, Error, Row};
+use chrono::{DateTime, Utc};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Employee {
     id: Option<i32>,
     pub first_name: String,
@@ -11,6 +11,8 @@ pub struct Employee {
     pub is_accountable: bool,
     pub job_title: String,
     pub unit_id: i32,
+    /// Set once the employee has left; their past transfers still point at them.
+    pub archived_at: Option<DateTime<Utc>>,
 }
 
 impl Employee {
@@ -24,6 +26,7 @@ impl Employee {
             is_accountable: row.get(5),
             job_title: row.get(6),
             unit_id: row.get(7),
+            archived_at: row.get(8),
         })
     }
 
@@ -53,21 +56,74 @@ impl Employee {
             is_accountable,
             job_title: job_title.into(),
             unit_id,
+            archived_at: None,
         }
     }
+
+    pub fn is_archived(&self) -> bool {
+        self.archived_at.is_some()
+    }
+}
+
+pub(super) fn still_used(transfers: i64) -> AppError {
+    AppError::Conflict(format!(
+        "The employee appears in {} transfer(s); archive them to keep the history",
+        transfers
+    ))
+}
+
+pub(super) fn signed_write_offs(write_offs: i64) -> AppError {
+    AppError::Conflict(format!(
+        "The employee signed {} write-off act(s); archive the employee instead of deleting",
+        write_offs
+    ))
 }
 
 pub struct EmployeeDAOImpl {
-    pool: Rc<PostgrePool>,
+    db: Db,
+}
+
+pub trait EmployeeDao: Send + Sync {
+    fn insert(&self, employee: &mut Employee) -> Result<(), AppError>;
+    fn update(&self, employee: &Employee) -> Result<(), AppError>;
+    fn get_all_accountable(&self, unit_id: i32) -> Result<Vec<Employee>, AppError>;
+    /// Current employees, leaving out archived ones.
+    fn get_all(&self) -> Result<Vec<Employee>, AppError>;
+    fn get_archived(&self) -> Result<Vec<Employee>, AppError>;
+    fn get_id(&self, id: i32) -> Result<Employee, AppError>;
+    fn delete(&self, id: i32) -> Result<(), AppError>;
+    fn archive(&self, id: i32) -> Result<(), AppError>;
+    fn restore(&self, id: i32) -> Result<(), AppError>;
+    fn get_by_unit_id(&self, unit_id: i32) -> Result<Vec<Employee>, AppError>;
 }
 
 impl EmployeeDAOImpl {
-    pub fn new(pool: Rc<PostgrePool>) -> Self {
-        Self { pool }
+    pub fn new(db: Db) -> Self {
+        Self { db }
     }
 
-    pub fn insert(&self, employee: &mut Employee) -> Result<(), Error> {
-        let mut conn = self.pool.get().unwrap();
+    const INSERT: &'static str = "INSERT INTO employee (first_name, middle_name, last_name, is_supervisor, is_accountable, job_title, unit_id)
+VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING id;";
+    const UPDATE: &'static str = "UPDATE employee SET first_name = $1, middle_name = $2, last_name = $3, is_supervisor = $4, is_accountable = $5, job_title = $6, unit_id = $7 WHERE id = $8;";
+    const DELETE: &'static str = "DELETE FROM employee WHERE id = $1;";
+    const COUNT_TRANSFERS: &'static str = "SELECT COUNT(*) FROM transfer WHERE employee_id = $1;";
+    const COUNT_WRITE_OFFS: &'static str = "SELECT COUNT(*) FROM write_off WHERE employee_id = $1;";
+    const FIND_BY_UNIT_ID: &'static str =
+        "SELECT * FROM employee WHERE unit_id = $1 ORDER BY last_name, first_name;";
+    const FIND_ALL: &'static str = "SELECT * FROM employee WHERE archived_at IS NULL;";
+    const FIND_ARCHIVED: &'static str =
+        "SELECT * FROM employee WHERE archived_at IS NOT NULL ORDER BY archived_at DESC;";
+    const ARCHIVE: &'static str =
+        "UPDATE employee SET archived_at = COALESCE(archived_at, now()) WHERE id = $1;";
+    const RESTORE: &'static str = "UPDATE employee SET archived_at = NULL WHERE id = $1;";
+    const FIND_ID: &'static str = "SELECT * FROM employee WHERE id = $1;";
+    const FIND_ALL_ACCOUNTABLE_UNIT: &'static str =
+        "SELECT * FROM employee WHERE unit_id = $1 AND is_accountable = TRUE AND archived_at IS NULL;";
+}
+
+impl EmployeeDao for EmployeeDAOImpl {
+    fn insert(&self, employee: &mut Employee) -> Result<(), AppError> {
+        let mut conn = self.db.get()?;
 
         let id: i32 = conn
             .query_one(
@@ -88,8 +144,8 @@ impl EmployeeDAOImpl {
         Ok(())
     }
 
-    pub fn update(&self, employee: &Employee) -> Result<(), Error> {
-        let mut conn = self.pool.get().unwrap();
+    fn update(&self, employee: &Employee) -> Result<(), AppError> {
+        let mut conn = self.db.get()?;
         let rows_affected = conn.execute(
             Self::UPDATE,
             &[
@@ -103,12 +159,15 @@ impl EmployeeDAOImpl {
                 &employee.id,
             ],
         )?;
+        if rows_affected == 0 {
+            return Err(AppError::not_found("Employee"));
+        }
 
         Ok(())
     }
 
-    pub fn get_all_accountable(&self, unit_id: i32) -> Result<Vec<Employee>, Error> {
-        let mut conn = self.pool.get().unwrap();
+    fn get_all_accountable(&self, unit_id: i32) -> Result<Vec<Employee>, AppError> {
+        let mut conn = self.db.get()?;
         let mut all_employees = vec![];
 
         for row in conn.query(Self::FIND_ALL_ACCOUNTABLE_UNIT, &[&unit_id])? {
@@ -117,8 +176,8 @@ impl EmployeeDAOImpl {
         Ok(all_employees)
     }
 
-    pub fn get_all(&self) -> Result<Vec<Employee>, Error> {
-        let mut conn = self.pool.get().unwrap();
+    fn get_all(&self) -> Result<Vec<Employee>, AppError> {
+        let mut conn = self.db.get()?;
         let mut all_employee = vec![];
         for row in conn.query(Self::FIND_ALL, &[])? {
             all_employee.push(Employee::from_row(row)?);
@@ -126,24 +185,64 @@ impl EmployeeDAOImpl {
         Ok(all_employee)
     }
 
-    pub fn get_id(&self, id: i32) -> Result<Employee, Error> {
-        let mut conn = self.pool.get().unwrap();
-        let row = conn.query_one(Self::FIND_ID, &[&id])?;
+    fn get_archived(&self) -> Result<Vec<Employee>, AppError> {
+        let mut conn = self.db.get()?;
+        let mut archived = vec![];
+        for row in conn.query(Self::FIND_ARCHIVED, &[])? {
+            archived.push(Employee::from_row(row)?);
+        }
+        Ok(archived)
+    }
+
+    fn get_id(&self, id: i32) -> Result<Employee, AppError> {
+        let mut conn = self.db.get()?;
+        let row = conn
+            .query_opt(Self::FIND_ID, &[&id])?
+            .ok_or_else(|| AppError::not_found("Employee"))?;
         Ok(Employee::from_row(row)?)
     }
 
-    pub fn delete(&self, id: i32) -> Result<(), Error> {
-        let mut conn = self.pool.get().unwrap();
-        let rows_affected = conn.execute(Self::DELETE, &[&id])?;
+    fn delete(&self, id: i32) -> Result<(), AppError> {
+        self.db.transaction(|db| {
+            let mut conn = db.get()?;
+            let transfers: i64 = conn.query_one(Self::COUNT_TRANSFERS, &[&id])?.get(0);
+            if transfers > 0 {
+                return Err(still_used(transfers));
+            }
+            let write_offs: i64 = conn.query_one(Self::COUNT_WRITE_OFFS, &[&id])?.get(0);
+            if write_offs > 0 {
+                return Err(signed_write_offs(write_offs));
+            }
+            let rows_affected = conn.execute(Self::DELETE, &[&id])?;
+            if rows_affected == 0 {
+                return Err(AppError::not_found("Employee"));
+            }
+            Ok(())
+        })
+    }
+
+    fn archive(&self, id: i32) -> Result<(), AppError> {
+        let mut conn = self.db.get()?;
+        if conn.execute(Self::ARCHIVE, &[&id])? == 0 {
+            return Err(AppError::not_found("Employee"));
+        }
         Ok(())
     }
 
-    const INSERT: &'static str = "INSERT INTO employee (first_name, middle_name, last_name, is_supervisor, is_accountable, job_title, unit_id)
-VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING id;";
-    const UPDATE: &'static str = "UPDATE employee SET first_name = $1, middle_name = $2, last_name = $3, is_supervisor = $4, is_accountable = $5, job_title = $6, unit_id = $7 WHERE id = $8;";
-    const DELETE: &'static str = "DELETE FROM employee WHERE id = $1;";
-    const FIND_ALL: &'static str = "SELECT * FROM employee;";
-    const FIND_ID: &'static str = "SELECT * FROM employee WHERE id = $1;";
-    const FIND_ALL_ACCOUNTABLE_UNIT: &'static str =
-        "SELECT * FROM employee WHERE unit_id = $1 AND is_accountable = TRUE;";
+    fn restore(&self, id: i32) -> Result<(), AppError> {
+        let mut conn = self.db.get()?;
+        if conn.execute(Self::RESTORE, &[&id])? == 0 {
+            return Err(AppError::not_found("Employee"));
+        }
+        Ok(())
+    }
+
+    fn get_by_unit_id(&self, unit_id: i32) -> Result<Vec<Employee>, AppError> {
+        let mut conn = self.db.get()?;
+        let mut employees = vec![];
+        for row in conn.query(Self::FIND_BY_UNIT_ID, &[&unit_id])? {
+            employees.push(Employee::from_row(row)?);
+        }
+        Ok(employees)
+    }
 }
@@ -0,0 +1,1443 @@
+//! DAOs backed by plain vectors instead of Postgres. They enforce the same unique
+//! and foreign key constraints as the schema, so the UI behaves the same way
+//! without a database.
+use super::audit::{self, Audit, AuditDao, AuditItem, AuditScope, AuditSummary, Finding};
+use super::category::{self, CategoryDao, DepreciationMethod, TechCategory};
+use super::employee::{self, Employee, EmployeeDao};
+use super::room::{self, Room, RoomDao};
+use super::tech::{self, Tech, TechDao, TechEmployee, TechHolding, TechUnit};
+use super::transfer::{self, Transfer, TransferDao, TransferDetails, TransferUnit};
+use super::unit::{self, Unit, UnitDao};
+use super::write_off::{self, Disposal, WriteOff, WriteOffDao, WriteOffDetails};
+use super::AppError;
+use crate::model::money::{Amount, Currency, Money};
+use chrono::{Datelike, Days, Local, NaiveDate, Utc};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+trait Record: Clone {
+    const NAME: &'static str;
+
+    fn key(&self) -> i32;
+    fn set_key(&mut self, id: i32);
+}
+
+macro_rules! record {
+    ($type:ty, $name:expr) => {
+        impl Record for $type {
+            const NAME: &'static str = $name;
+
+            fn key(&self) -> i32 {
+                self.id()
+            }
+
+            fn set_key(&mut self, id: i32) {
+                self.set_id(id)
+            }
+        }
+    };
+}
+
+record!(Tech, "Tech");
+record!(Unit, "Unit");
+record!(Employee, "Employee");
+record!(Room, "Room");
+record!(Transfer, "Transfer");
+record!(WriteOff, "Write-off");
+record!(TechCategory, "Category");
+record!(Audit, "Audit");
+record!(AuditItem, "Audit item");
+
+#[derive(Clone)]
+struct Table<T> {
+    rows: Vec<T>,
+    next_id: i32,
+}
+
+impl<T> Default for Table<T> {
+    fn default() -> Self {
+        Self {
+            rows: vec![],
+            next_id: 0,
+        }
+    }
+}
+
+impl<T: Record> Table<T> {
+    fn insert(&mut self, row: &mut T) {
+        self.next_id += 1;
+        row.set_key(self.next_id);
+        self.rows.push(row.clone());
+    }
+
+    fn update(&mut self, row: &T) -> Result<(), AppError> {
+        let stored = self
+            .rows
+            .iter_mut()
+            .find(|x| x.key() == row.key())
+            .ok_or_else(|| AppError::not_found(T::NAME))?;
+        *stored = row.clone();
+        Ok(())
+    }
+
+    fn delete(&mut self, id: i32) -> Result<(), AppError> {
+        let index = self
+            .rows
+            .iter()
+            .position(|x| x.key() == id)
+            .ok_or_else(|| AppError::not_found(T::NAME))?;
+        self.rows.remove(index);
+        Ok(())
+    }
+
+    fn get_mut(&mut self, id: i32) -> Result<&mut T, AppError> {
+        self.rows
+            .iter_mut()
+            .find(|x| x.key() == id)
+            .ok_or_else(|| AppError::not_found(T::NAME))
+    }
+
+    fn find(&self, id: i32) -> Option<&T> {
+        self.rows.iter().find(|x| x.key() == id)
+    }
+
+    fn get(&self, id: i32) -> Result<T, AppError> {
+        self.find(id)
+            .cloned()
+            .ok_or_else(|| AppError::not_found(T::NAME))
+    }
+
+    fn exists(&self, id: i32) -> bool {
+        self.find(id).is_some()
+    }
+
+    fn all(&self) -> Vec<T> {
+        self.rows.clone()
+    }
+}
+
+fn unique_violation(constraint: &str) -> AppError {
+    AppError::Sql {
+        message: format!(
+            "duplicate key value violates unique constraint \"{}\"",
+            constraint
+        ),
+        constraint: Some(constraint.to_string()),
+    }
+}
+
+fn missing_reference(table: &str, constraint: &str) -> AppError {
+    AppError::Sql {
+        message: format!(
+            "insert or update on table \"{}\" violates foreign key constraint \"{}\"",
+            table, constraint
+        ),
+        constraint: Some(constraint.to_string()),
+    }
+}
+
+fn check_violation(table: &str, constraint: &str) -> AppError {
+    AppError::Sql {
+        message: format!(
+            "new row for relation \"{}\" violates check constraint \"{}\"",
+            table, constraint
+        ),
+        constraint: Some(constraint.to_string()),
+    }
+}
+
+#[derive(Clone, Default)]
+struct Tables {
+    tech: Table<Tech>,
+    unit: Table<Unit>,
+    employee: Table<Employee>,
+    room: Table<Room>,
+    transfer: Table<Transfer>,
+    write_off: Table<WriteOff>,
+    category: Table<TechCategory>,
+    audit: Table<Audit>,
+    audit_item: Table<AuditItem>,
+}
+
+impl Tables {
+    fn check_tech(&self, tech: &Tech) -> Result<(), AppError> {
+        let duplicate = self
+            .tech
+            .rows
+            .iter()
+            .any(|x| x.id() != tech.id() && x.inventory_number == tech.inventory_number);
+        if duplicate {
+            return Err(unique_violation("tech_inventory_number_key"));
+        }
+        if tech.category_id.is_some_and(|x| !self.category.exists(x)) {
+            return Err(missing_reference("tech", "tech_category_id_fkey"));
+        }
+        Ok(())
+    }
+
+    fn check_unit(&self, unit: &Unit) -> Result<(), AppError> {
+        let duplicate = self
+            .unit
+            .rows
+            .iter()
+            .any(|x| x.id() != unit.id() && x.unit_number == unit.unit_number);
+        if duplicate {
+            return Err(unique_violation("unit_unit_number_key"));
+        }
+        Ok(())
+    }
+
+    fn check_room(&self, room: &Room) -> Result<(), AppError> {
+        let duplicate = self
+            .room
+            .rows
+            .iter()
+            .any(|x| x.id() != room.id() && x.room_number == room.room_number);
+        if duplicate {
+            return Err(unique_violation("room_room_number_key"));
+        }
+        if room.squares <= 0 {
+            return Err(check_violation("room", "room_squares_check"));
+        }
+        Ok(())
+    }
+
+    fn check_employee(&self, employee: &Employee) -> Result<(), AppError> {
+        if employee.is_supervisor == employee.is_accountable {
+            return Err(check_violation("employee", "employee_role_check"));
+        }
+        if !self.unit.exists(employee.unit_id) {
+            return Err(missing_reference("employee", "employee_unit_id_fkey"));
+        }
+        Ok(())
+    }
+
+    fn check_transfer(&self, transfer: &Transfer) -> Result<(), AppError> {
+        if !self.tech.exists(transfer.tech_id) {
+            return Err(missing_reference("transfer", "transfer_tech_id_fkey"));
+        }
+        if !self.room.exists(transfer.room_id) {
+            return Err(missing_reference("transfer", "transfer_room_id_fkey"));
+        }
+        if !self.employee.exists(transfer.employee_id) {
+            return Err(missing_reference("transfer", "transfer_employee_id_fkey"));
+        }
+        Ok(())
+    }
+
+    fn check_category(&self, category: &TechCategory) -> Result<(), AppError> {
+        let duplicate = self
+            .category
+            .rows
+            .iter()
+            .any(|x| x.id() != category.id() && x.name == category.name);
+        if duplicate {
+            return Err(unique_violation("tech_category_name_key"));
+        }
+        if category.depreciation.useful_life_years <= 0 {
+            return Err(check_violation(
+                "tech_category",
+                "tech_category_useful_life_years_check",
+            ));
+        }
+        Ok(())
+    }
+
+    fn check_write_off(&self, write_off: &WriteOff) -> Result<(), AppError> {
+        if !self.tech.exists(write_off.tech_id) {
+            return Err(missing_reference("write_off", "write_off_tech_id_fkey"));
+        }
+        if !self.employee.exists(write_off.employee_id) {
+            return Err(missing_reference("write_off", "write_off_employee_id_fkey"));
+        }
+        if self.written_off(write_off.tech_id).is_some() {
+            return Err(unique_violation("write_off_tech_id_key"));
+        }
+        let duplicate = self
+            .write_off
+            .rows
+            .iter()
+            .any(|x| x.act_number == write_off.act_number);
+        if duplicate {
+            return Err(unique_violation("write_off_act_number_key"));
+        }
+        if write_off.residual_value.is_negative() {
+            return Err(check_violation(
+                "write_off",
+                "write_off_residual_value_check",
+            ));
+        }
+        Ok(())
+    }
+
+    fn check_audit(&self, audit: &Audit) -> Result<(), AppError> {
+        match audit.scope {
+            AuditScope::Unit(x) if !self.unit.exists(x) => {
+                Err(missing_reference("audit", "audit_unit_id_fkey"))
+            }
+            AuditScope::Room(x) if !self.room.exists(x) => {
+                Err(missing_reference("audit", "audit_room_id_fkey"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn check_audit_item(&self, item: &AuditItem) -> Result<(), AppError> {
+        if !self.audit.exists(item.audit_id) {
+            return Err(missing_reference("audit_item", "audit_item_audit_id_fkey"));
+        }
+        if item.tech_id.is_some_and(|x| !self.tech.exists(x)) {
+            return Err(missing_reference("audit_item", "audit_item_tech_id_fkey"));
+        }
+        let duplicate = self.audit_item.rows.iter().any(|x| {
+            x.id() != item.id()
+                && x.audit_id == item.audit_id
+                && x.inventory_number == item.inventory_number
+        });
+        if duplicate {
+            return Err(unique_violation("audit_item_inventory_number_key"));
+        }
+        Ok(())
+    }
+
+    /// Same as `AuditDAOImpl::lock_open`: the audit has to exist and be open.
+    fn open_audit(&self, id: i32) -> Result<&Audit, AppError> {
+        let audit = self
+            .audit
+            .find(id)
+            .ok_or_else(|| AppError::not_found("Audit"))?;
+        match audit.closed_at {
+            Some(closed_at) => Err(audit::closed(closed_at)),
+            None => Ok(audit),
+        }
+    }
+
+    fn audit_summary(&self, audit: &Audit) -> AuditSummary {
+        let scope_name = match audit.scope {
+            AuditScope::Unit(x) => self
+                .unit
+                .find(x)
+                .map(|x| x.full_name.clone())
+                .unwrap_or_default(),
+            AuditScope::Room(x) => self
+                .room
+                .find(x)
+                .map(|x| format!("Room {}", x.room_number))
+                .unwrap_or_default(),
+        };
+        let items: Vec<&AuditItem> = self
+            .audit_item
+            .rows
+            .iter()
+            .filter(|x| x.audit_id == audit.id())
+            .collect();
+        AuditSummary {
+            audit: audit.clone(),
+            scope_name,
+            items: items.len() as i64,
+            pending: items
+                .iter()
+                .filter(|x| x.finding == Finding::Pending)
+                .count() as i64,
+            discrepancies: items.iter().filter(|x| x.is_discrepancy()).count() as i64,
+        }
+    }
+
+    fn write_off_details(&self, write_off: &WriteOff) -> Option<WriteOffDetails> {
+        Some(WriteOffDetails {
+            write_off: write_off.clone(),
+            tech: self.tech.find(write_off.tech_id)?.clone(),
+            employee: self.employee.find(write_off.employee_id)?.clone(),
+        })
+    }
+
+    fn written_off(&self, tech_id: i32) -> Option<&WriteOff> {
+        self.write_off.rows.iter().find(|x| x.tech_id == tech_id)
+    }
+
+    /// Same rules as `TransferDAOImpl::check_scheduled`: one pending transfer per
+    /// tech and day, and none after the tech was written off.
+    fn check_scheduled(
+        &self,
+        tech_id: i32,
+        transfer_date: NaiveDate,
+        id: i32,
+    ) -> Result<(), AppError> {
+        if let Some(x) = self
+            .written_off(tech_id)
+            .filter(|x| x.write_off_date < transfer_date)
+        {
+            return Err(write_off::written_off(x.write_off_date));
+        }
+        let scheduled = self.transfer.rows.iter().any(|x| {
+            x.id() != id
+                && x.tech_id == tech_id
+                && x.transfer_date == transfer_date
+                && transfer::is_pending(x.transfer_date, today())
+        });
+        if scheduled {
+            return Err(transfer::already_scheduled());
+        }
+        Ok(())
+    }
+
+    fn details(&self, transfer: &Transfer) -> TransferDetails {
+        let employee = self.employee.find(transfer.employee_id).cloned();
+        let unit = employee
+            .as_ref()
+            .and_then(|x| self.unit.find(x.unit_id))
+            .cloned();
+        TransferDetails {
+            id: transfer.id(),
+            transfer_date: transfer.transfer_date,
+            tech: self.tech.find(transfer.tech_id).cloned(),
+            room: self.room.find(transfer.room_id).cloned(),
+            employee,
+            unit,
+        }
+    }
+
+    /// The transfer that places each piece of tech on `date`: its latest one dated on
+    /// or before that day, the later id winning a tie. Tech written off by then has
+    /// none. Newest transfers come first.
+    fn current_transfers(&self, date: NaiveDate) -> Vec<&Transfer> {
+        let mut latest: HashMap<i32, &Transfer> = HashMap::new();
+        for transfer in self.transfer.rows.iter().filter(|x| {
+            x.transfer_date <= date
+                && self
+                    .written_off(x.tech_id)
+                    .is_none_or(|w| w.write_off_date > date)
+        }) {
+            match latest.get(&transfer.tech_id) {
+                Some(x) if (x.transfer_date, x.id()) > (transfer.transfer_date, transfer.id()) => {}
+                _ => {
+                    latest.insert(transfer.tech_id, transfer);
+                }
+            }
+        }
+        let mut transfers: Vec<&Transfer> = latest.into_values().collect();
+        transfers.sort_by_key(|x| Reverse((x.transfer_date, x.id())));
+        transfers
+    }
+}
+
+fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+#[derive(Default)]
+pub struct MemoryStore {
+    tables: Mutex<Tables>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A store with a couple of units, rooms, employees and transfers, so every page
+    /// has something to show.
+    pub fn with_demo_data() -> Self {
+        let store = Self::new();
+        store.seed().expect("Demo data violates a constraint");
+        store
+    }
+
+    fn seed(&self) -> Result<(), AppError> {
+        let mut accounting = Unit::new(1, "Accounting department", "ACC");
+        let mut it = Unit::new(2, "IT department", "IT");
+        UnitDao::insert(self, &mut accounting)?;
+        UnitDao::insert(self, &mut it)?;
+
+        let mut rooms = [Room::new(101, 20), Room::new(204, 35)];
+        for room in rooms.iter_mut() {
+            RoomDao::insert(self, room)?;
+        }
+
+        let mut accountant = Employee::new(
+            "Anna",
+            "Petrovna",
+            "Ivanova",
+            false,
+            true,
+            "Accountant",
+            accounting.id(),
+        );
+        let mut admin = Employee::new(
+            "Oleg",
+            "Sergeevich",
+            "Smirnov",
+            false,
+            true,
+            "System administrator",
+            it.id(),
+        );
+        EmployeeDao::insert(self, &mut accountant)?;
+        EmployeeDao::insert(self, &mut admin)?;
+
+        let mut computers = TechCategory::new("Computers", DepreciationMethod::DecliningBalance, 3);
+        let mut office = TechCategory::new("Office equipment", DepreciationMethod::StraightLine, 5);
+        CategoryDao::insert(self, &mut computers)?;
+        CategoryDao::insert(self, &mut office)?;
+
+        let today = today();
+        let days_ago = |days| today.checked_sub_days(Days::new(days)).unwrap_or(today);
+        let days_ahead = |days| today.checked_add_days(Days::new(days)).unwrap_or(today);
+        let rub = |units: i64, kopecks: i64| {
+            Money::new(Amount::from_minor(units * 100 + kopecks), Currency::RUB)
+        };
+        let mut laptop = Tech::new_with_date(
+            1001,
+            "Laptop",
+            "ThinkPad T14",
+            days_ago(400),
+            rub(89_990, 50),
+        );
+        let mut printer = Tech::new_with_date(
+            1002,
+            "Printer",
+            "LaserJet M404",
+            days_ago(200),
+            rub(24_300, 0),
+        );
+        let mut monitor = Tech::new_with_date(
+            1003,
+            "Monitor",
+            "Dell P2217",
+            days_ago(2000),
+            rub(12_499, 99),
+        );
+        laptop.category_id = Some(computers.id());
+        printer.category_id = Some(office.id());
+        monitor.category_id = Some(office.id());
+        TechDao::insert(self, &mut laptop)?;
+        TechDao::insert(self, &mut printer)?;
+        TechDao::insert(self, &mut monitor)?;
+
+        let transfers = [
+            (days_ago(390), laptop.id(), rooms[0].id(), accountant.id()),
+            (days_ago(30), laptop.id(), rooms[1].id(), admin.id()),
+            (days_ago(190), printer.id(), rooms[0].id(), accountant.id()),
+            (days_ahead(14), printer.id(), rooms[1].id(), admin.id()),
+            (days_ago(1990), monitor.id(), rooms[0].id(), accountant.id()),
+        ];
+        for (date, tech_id, room_id, employee_id) in transfers {
+            TransferDao::insert(
+                self,
+                &mut Transfer::new_date(date, tech_id, room_id, employee_id),
+            )?;
+        }
+
+        WriteOffDao::insert(
+            self,
+            &mut WriteOff::new(
+                monitor.id(),
+                days_ago(60),
+                Disposal::Scrapped,
+                "Backlight failed, repair is not worth it",
+                "WO-1",
+                admin.id(),
+                Amount::ZERO,
+            ),
+        )?;
+        Ok(())
+    }
+
+    /// Runs `f` against a copy of the tables and keeps the copy only if `f`
+    /// succeeds. The store stays locked meanwhile, so transactions are serialized.
+    pub fn transaction<T, F>(&self, f: F) -> Result<T, AppError>
+    where
+        F: FnOnce(Arc<MemoryStore>) -> Result<T, AppError>,
+    {
+        let mut tables = self.lock();
+        let copy = Arc::new(MemoryStore {
+            tables: Mutex::new(tables.clone()),
+        });
+        let result = f(copy.clone())?;
+        *tables = std::mem::take(&mut *copy.lock());
+        Ok(result)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Tables> {
+        // A handler that panicked mid-request cannot leave a row half-written, so
+        // the data is still consistent even if the lock got poisoned.
+        self.tables.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl TechDao for MemoryStore {
+    fn insert(&self, tech: &mut Tech) -> Result<(), AppError> {
+        let mut tables = self.lock();
+        tables.check_tech(tech)?;
+        tables.tech.insert(tech);
+        Ok(())
+    }
+
+    fn update(&self, tech: &Tech) -> Result<(), AppError> {
+        let mut tables = self.lock();
+        tables.check_tech(tech)?;
+        let stored = tables.tech.get(tech.id())?;
+        let mut tech = tech.clone();
+        tech.archived_at = stored.archived_at;
+        tech.written_off_on = stored.written_off_on;
+        tables.tech.update(&tech)
+    }
+
+    fn get_all(&self) -> Result<Vec<Tech>, AppError> {
+        let mut all_tech = self.lock().tech.all();
+        all_tech.retain(|x| !x.is_archived());
+        Ok(all_tech)
+    }
+
+    fn get_archived(&self) -> Result<Vec<Tech>, AppError> {
+        let mut archived = self.lock().tech.all();
+        archived.retain(|x| x.is_archived());
+        archived.sort_by_key(|x| Reverse(x.archived_at));
+        Ok(archived)
+    }
+
+    fn get_in_service(&self) -> Result<Vec<Tech>, AppError> {
+        let mut in_service = self.lock().tech.all();
+        in_service.retain(|x| !x.is_archived() && !x.is_written_off());
+        Ok(in_service)
+    }
+
+    fn get_id(&self, id: i32) -> Result<Tech, AppError> {
+        self.lock().tech.get(id)
+    }
+
+    fn get_by_inventory_number(&self, inventory_number: i32) -> Result<Tech, AppError> {
+        self.lock()
+            .tech
+            .rows
+            .iter()
+            .find(|x| x.inventory_number == inventory_number)
+            .cloned()
+            .ok_or_else(|| AppError::not_found("Tech"))
+    }
+
+    fn delete(&self, id: i32) -> Result<(), AppError> {
+        let mut tables = self.lock();
+        if tables.written_off(id).is_some() {
+            return Err(tech::written_off());
+        }
+        let transfers = tables
+            .transfer
+            .rows
+            .iter()
+            .filter(|x| x.tech_id == id)
+            .count();
+        if transfers > 0 {
+            return Err(tech::still_used(transfers as i64));
+        }
+        tables.tech.delete(id)?;
+        for item in tables.audit_item.rows.iter_mut() {
+            if item.tech_id == Some(id) {
+                item.tech_id = None;
+            }
+        }
+        Ok(())
+    }
+
+    fn archive(&self, id: i32) -> Result<(), AppError> {
+        let mut tables = self.lock();
+        tables
+            .tech
+            .get_mut(id)?
+            .archived_at
+            .get_or_insert_with(Utc::now);
+        Ok(())
+    }
+
+    fn restore(&self, id: i32) -> Result<(), AppError> {
+        let mut tables = self.lock();
+        tables.tech.get_mut(id)?.archived_at = None;
+        Ok(())
+    }
+
+    fn get_by_unit_id(&self, unit_id: i32, date: NaiveDate) -> Result<Vec<TechUnit>, AppError> {
+        let tables = self.lock();
+        let mut all_unit = vec![];
+        for transfer in tables.current_transfers(date) {
+            let Some(employee) = tables.employee.find(transfer.employee_id) else {
+                continue;
+            };
+            let (Some(unit), Some(tech)) = (
+                tables.unit.find(employee.unit_id),
+                tables.tech.find(transfer.tech_id),
+            ) else {
+                continue;
+            };
+            if unit.id() != unit_id {
+                continue;
+            }
+            all_unit.push(TechUnit {
+                unit_name: unit.full_name.clone(),
+                date: transfer.transfer_date,
+                inventory_number: tech.inventory_number,
+                name: tech.name.clone(),
+                model: tech.model.clone(),
+                price: tech.price,
+            });
+        }
+        Ok(all_unit)
+    }
+
+    fn get_by_employee_id(
+        &self,
+        employee_id: i32,
+        date: NaiveDate,
+    ) -> Result<Vec<TechEmployee>, AppError> {
+        let tables = self.lock();
+        let mut all_employee = vec![];
+        for transfer in tables.current_transfers(date) {
+            if transfer.employee_id != employee_id {
+                continue;
+            }
+            let (Some(employee), Some(tech)) = (
+                tables.employee.find(transfer.employee_id),
+                tables.tech.find(transfer.tech_id),
+            ) else {
+                continue;
+            };
+            all_employee.push(TechEmployee {
+                date: transfer.transfer_date,
+                first_name: employee.first_name.clone(),
+                middle_name: employee.middle_name.clone(),
+                last_name: employee.last_name.clone(),
+                job_title: employee.job_title.clone(),
+                inventory_number: tech.inventory_number,
+                name: tech.name.clone(),
+                model: tech.model.clone(),
+                price: tech.price,
+            });
+        }
+        Ok(all_employee)
+    }
+
+    fn get_holdings(&self, date: NaiveDate) -> Result<Vec<TechHolding>, AppError> {
+        let tables = self.lock();
+        let mut holdings = vec![];
+        for transfer in tables.current_transfers(date) {
+            let (Some(tech), Some(room), Some(employee)) = (
+                tables.tech.find(transfer.tech_id),
+                tables.room.find(transfer.room_id),
+                tables.employee.find(transfer.employee_id),
+            ) else {
+                continue;
+            };
+            let Some(unit) = tables.unit.find(employee.unit_id) else {
+                continue;
+            };
+            holdings.push(TechHolding {
+                date: transfer.transfer_date,
+                inventory_number: tech.inventory_number,
+                name: tech.name.clone(),
+                model: tech.model.clone(),
+                room_number: room.room_number,
+                first_name: employee.first_name.clone(),
+                middle_name: employee.middle_name.clone(),
+                last_name: employee.last_name.clone(),
+                unit_name: unit.full_name.clone(),
+                tech_archived: tech.is_archived(),
+                employee_archived: employee.is_archived(),
+                unit_archived: unit.is_archived(),
+                unit_id: unit.id(),
+                acquisition_date: tech.acquisition_date,
+                price: tech.price,
+                depreciation: tech
+                    .category_id
+                    .and_then(|x| tables.category.find(x))
+                    .map(|x| x.depreciation),
+                tech_id: tech.id(),
+                room_id: room.id(),
+            });
+        }
+        holdings.sort_by(|a, b| {
+            (
+                &a.unit_name,
+                &a.last_name,
+                &a.first_name,
+                a.inventory_number,
+            )
+                .cmp(&(
+                    &b.unit_name,
+                    &b.last_name,
+                    &b.first_name,
+                    b.inventory_number,
+                ))
+        });
+        Ok(holdings)
+    }
+}
+
+impl UnitDao for MemoryStore {
+    fn insert(&self, unit: &mut Unit) -> Result<(), AppError> {
+        let mut tables = self.lock();
+        tables.check_unit(unit)?;
+        tables.unit.insert(unit);
+        Ok(())
+    }
+
+    fn update(&self, unit: &Unit) -> Result<(), AppError> {
+        let mut tables = self.lock();
+        tables.check_unit(unit)?;
+        let mut unit = unit.clone();
+        unit.archived_at = tables.unit.get(unit.id())?.archived_at;
+        tables.unit.update(&unit)
+    }
+
+    fn get_all(&self) -> Result<Vec<Unit>, AppError> {
+        let mut all_unit = self.lock().unit.all();
+        all_unit.retain(|x| !x.is_archived());
+        Ok(all_unit)
+    }
+
+    fn get_archived(&self) -> Result<Vec<Unit>, AppError> {
+        let mut archived = self.lock().unit.all();
+        archived.retain(|x| x.is_archived());
+        archived.sort_by_key(|x| Reverse(x.archived_at));
+        Ok(archived)
+    }
+
+    fn get_all_acountable(&self) -> Result<Vec<Unit>, AppError> {
+        let tables = self.lock();
+        Ok(tables
+            .unit
+            .rows
+            .iter()
+            .filter(|unit| {
+                !unit.is_archived()
+                    && tables
+                        .employee
+                        .rows
+                        .iter()
+                        .any(|x| x.unit_id == unit.id() && x.is_accountable && !x.is_archived())
+            })
+            .cloned()
+            .collect())
+    }
+
+    fn get_id(&self, id: i32) -> Result<Unit, AppError> {
+        self.lock().unit.get(id)
+    }
+
+    fn delete(&self, id: i32) -> Result<(), AppError> {
+        let mut tables = self.lock();
+        let employees = tables
+            .employee
+            .rows
+            .iter()
+            .filter(|x| x.unit_id == id)
+            .count();
+        if employees > 0 {
+            return Err(unit::still_used(employees as i64));
+        }
+        let audits = tables
+            .audit
+            .rows
+            .iter()
+            .filter(|x| x.scope == AuditScope::Unit(id))
+            .count();
+        if audits > 0 {
+            return Err(audit::audited("unit", audits as i64));
+        }
+        tables.unit.delete(id)
+    }
+
+    fn archive(&self, id: i32) -> Result<(), AppError> {
+        let mut tables = self.lock();
+        tables
+            .unit
+            .get_mut(id)?
+            .archived_at
+            .get_or_insert_with(Utc::now);
+        Ok(())
+    }
+
+    fn restore(&self, id: i32) -> Result<(), AppError> {
+        let mut tables = self.lock();
+        tables.unit.get_mut(id)?.archived_at = None;
+        Ok(())
+    }
+}
+
+impl EmployeeDao for MemoryStore {
+    fn insert(&self, employee: &mut Employee) -> Result<(), AppError> {
+        let mut tables = self.lock();
+        tables.check_employee(employee)?;
+        tables.employee.insert(employee);
+        Ok(())
+    }
+
+    fn update(&self, employee: &Employee) -> Result<(), AppError> {
+        let mut tables = self.lock();
+        tables.check_employee(employee)?;
+        let mut employee = employee.clone();
+        employee.archived_at = tables.employee.get(employee.id())?.archived_at;
+        tables.employee.update(&employee)
+    }
+
+    fn get_all_accountable(&self, unit_id: i32) -> Result<Vec<Employee>, AppError> {
+        Ok(self
+            .lock()
+            .employee
+            .rows
+            .iter()
+            .filter(|x| x.unit_id == unit_id && x.is_accountable && !x.is_archived())
+            .cloned()
+            .collect())
+    }
+
+    fn get_all(&self) -> Result<Vec<Employee>, AppError> {
+        let mut all_employee = self.lock().employee.all();
+        all_employee.retain(|x| !x.is_archived());
+        Ok(all_employee)
+    }
+
+    fn get_archived(&self) -> Result<Vec<Employee>, AppError> {
+        let mut archived = self.lock().employee.all();
+        archived.retain(|x| x.is_archived());
+        archived.sort_by_key(|x| Reverse(x.archived_at));
+        Ok(archived)
+    }
+
+    fn get_id(&self, id: i32) -> Result<Employee, AppError> {
+        self.lock().employee.get(id)
+    }
+
+    fn delete(&self, id: i32) -> Result<(), AppError> {
+        let mut tables = self.lock();
+        let transfers = tables
+            .transfer
+            .rows
+            .iter()
+            .filter(|x| x.employee_id == id)
+            .count();
+        if transfers > 0 {
+            return Err(employee::still_used(transfers as i64));
+        }
+        let write_offs = tables
+            .write_off
+            .rows
+            .iter()
+            .filter(|x| x.employee_id == id)
+            .count();
+        if write_offs > 0 {
+            return Err(employee::signed_write_offs(write_offs as i64));
+        }
+        tables.employee.delete(id)
+    }
+
+    fn archive(&self, id: i32) -> Result<(), AppError> {
+        let mut tables = self.lock();
+        tables
+            .employee
+            .get_mut(id)?
+            .archived_at
+            .get_or_insert_with(Utc::now);
+        Ok(())
+    }
+
+    fn restore(&self, id: i32) -> Result<(), AppError> {
+        let mut tables = self.lock();
+        tables.employee.get_mut(id)?.archived_at = None;
+        Ok(())
+    }
+
+    fn get_by_unit_id(&self, unit_id: i32) -> Result<Vec<Employee>, AppError> {
+        let tables = self.lock();
+        let mut employees: Vec<Employee> = tables
+            .employee
+            .rows
+            .iter()
+            .filter(|x| x.unit_id == unit_id)
+            .cloned()
+            .collect();
+        employees.sort_by(|a, b| (&a.last_name, &a.first_name).cmp(&(&b.last_name, &b.first_name)));
+        Ok(employees)
+    }
+}
+
+impl RoomDao for MemoryStore {
+    fn insert(&self, room: &mut Room) -> Result<(), AppError> {
+        let mut tables = self.lock();
+        tables.check_room(room)?;
+        tables.room.insert(room);
+        Ok(())
+    }
+
+    fn update(&self, room: &Room) -> Result<(), AppError> {
+        let mut tables = self.lock();
+        tables.check_room(room)?;
+        tables.room.update(room)
+    }
+
+    fn get_all(&self) -> Result<Vec<Room>, AppError> {
+        let mut rooms = self.lock().room.all();
+        rooms.sort_by_key(|x| x.room_number);
+        Ok(rooms)
+    }
+
+    fn get_id(&self, id: i32) -> Result<Room, AppError> {
+        self.lock().room.get(id)
+    }
+
+    fn delete(&self, id: i32) -> Result<(), AppError> {
+        let mut tables = self.lock();
+        let transfers = tables
+            .transfer
+            .rows
+            .iter()
+            .filter(|x| x.room_id == id)
+            .count();
+        if transfers > 0 {
+            return Err(room::still_used(transfers as i64));
+        }
+        let audits = tables
+            .audit
+            .rows
+            .iter()
+            .filter(|x| x.scope == AuditScope::Room(id))
+            .count();
+        if audits > 0 {
+            return Err(audit::audited("room", audits as i64));
+        }
+        tables.room.delete(id)
+    }
+}
+
+impl CategoryDao for MemoryStore {
+    fn insert(&self, category: &mut TechCategory) -> Result<(), AppError> {
+        let mut tables = self.lock();
+        tables.check_category(category)?;
+        tables.category.insert(category);
+        Ok(())
+    }
+
+    fn update(&self, category: &TechCategory) -> Result<(), AppError> {
+        let mut tables = self.lock();
+        tables.check_category(category)?;
+        tables.category.update(category)
+    }
+
+    fn get_all(&self) -> Result<Vec<TechCategory>, AppError> {
+        let mut categories = self.lock().category.all();
+        categories.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(categories)
+    }
+
+    fn get_id(&self, id: i32) -> Result<TechCategory, AppError> {
+        self.lock().category.get(id)
+    }
+
+    fn delete(&self, id: i32) -> Result<(), AppError> {
+        let mut tables = self.lock();
+        let tech = tables
+            .tech
+            .rows
+            .iter()
+            .filter(|x| x.category_id == Some(id))
+            .count();
+        if tech > 0 {
+            return Err(category::still_used(tech as i64));
+        }
+        tables.category.delete(id)
+    }
+}
+
+impl TransferDao for MemoryStore {
+    fn get_detailed_transfers(&self) -> Result<Vec<TransferDetails>, AppError> {
+        let tables = self.lock();
+        let transfers = tables.transfer.rows.iter();
+        Ok(transfers.map(|x| tables.details(x)).collect())
+    }
+
+    fn get_by_tech_id(&self, tech_id: i32) -> Result<Vec<TransferDetails>, AppError> {
+        let tables = self.lock();
+        let mut transfers: Vec<&Transfer> = tables
+            .transfer
+            .rows
+            .iter()
+            .filter(|x| x.tech_id == tech_id)
+            .collect();
+        transfers.sort_by_key(|x| (x.transfer_date, x.id()));
+        Ok(transfers.into_iter().map(|x| tables.details(x)).collect())
+    }
+
+    fn get_by_employee_id(&self, employee_id: i32) -> Result<Vec<TransferDetails>, AppError> {
+        let tables = self.lock();
+        let mut transfers: Vec<&Transfer> = tables
+            .transfer
+            .rows
+            .iter()
+            .filter(|x| x.employee_id == employee_id)
+            .collect();
+        transfers.sort_by_key(|x| (x.transfer_date, x.id()));
+        Ok(transfers.into_iter().map(|x| tables.details(x)).collect())
+    }
+
+    fn insert(&self, transfer: &mut Transfer) -> Result<(), AppError> {
+        let mut tables = self.lock();
+        tables.check_transfer(transfer)?;
+        tables.check_scheduled(transfer.tech_id, transfer.transfer_date, 0)?;
+        tables.transfer.insert(transfer);
+        Ok(())
+    }
+
+    fn update(&self, transfer: &Transfer) -> Result<(), AppError> {
+        let mut tables = self.lock();
+        tables.check_transfer(transfer)?;
+        tables.check_scheduled(transfer.tech_id, transfer.transfer_date, transfer.id())?;
+        tables.transfer.update(transfer)
+    }
+
+    fn get_id_with_unit(&self, id: i32) -> Result<TransferUnit, AppError> {
+        let tables = self.lock();
+        let transfer = tables.transfer.get(id)?;
+        let unit_id = tables
+            .employee
+            .find(transfer.employee_id)
+            .map(|x| x.unit_id)
+            .unwrap_or(0);
+        Ok(TransferUnit {
+            inner: transfer,
+            unit_id,
+        })
+    }
+
+    fn get_pending(&self) -> Result<Vec<TransferDetails>, AppError> {
+        let tables = self.lock();
+        let today = today();
+        let mut transfers: Vec<&Transfer> = tables
+            .transfer
+            .rows
+            .iter()
+            .filter(|x| transfer::is_pending(x.transfer_date, today))
+            .collect();
+        transfers.sort_by_key(|x| (x.transfer_date, x.id()));
+        Ok(transfers.into_iter().map(|x| tables.details(x)).collect())
+    }
+
+    fn cancel(&self, id: i32) -> Result<(), AppError> {
+        let mut tables = self.lock();
+        let transfer = tables.transfer.get(id)?;
+        if !transfer::is_pending(transfer.transfer_date, today()) {
+            return Err(transfer::not_pending());
+        }
+        tables.transfer.delete(id)
+    }
+
+    fn reschedule(&self, id: i32, transfer_date: NaiveDate) -> Result<(), AppError> {
+        let mut tables = self.lock();
+        let mut transfer = tables.transfer.get(id)?;
+        if !transfer::is_pending(transfer.transfer_date, today()) {
+            return Err(transfer::not_pending());
+        }
+        tables.check_scheduled(transfer.tech_id, transfer_date, id)?;
+        transfer.transfer_date = transfer_date;
+        tables.transfer.update(&transfer)
+    }
+}
+
+impl WriteOffDao for MemoryStore {
+    fn insert(&self, write_off: &mut WriteOff) -> Result<(), AppError> {
+        let mut tables = self.lock();
+        tables.check_write_off(write_off)?;
+        let later = tables
+            .transfer
+            .rows
+            .iter()
+            .filter(|x| {
+                x.tech_id == write_off.tech_id && x.transfer_date > write_off.write_off_date
+            })
+            .map(|x| x.transfer_date)
+            .max();
+        if let Some(date) = later {
+            return Err(write_off::moved_after(date));
+        }
+        tables.write_off.insert(write_off);
+        tables.tech.get_mut(write_off.tech_id)?.written_off_on = Some(write_off.write_off_date);
+        Ok(())
+    }
+
+    fn get_by_tech_id(&self, tech_id: i32) -> Result<Option<WriteOffDetails>, AppError> {
+        let tables = self.lock();
+        Ok(tables
+            .written_off(tech_id)
+            .and_then(|x| tables.write_off_details(x)))
+    }
+
+    fn get_by_year(&self, year: i32) -> Result<Vec<WriteOffDetails>, AppError> {
+        let tables = self.lock();
+        let mut register: Vec<WriteOffDetails> = tables
+            .write_off
+            .rows
+            .iter()
+            .filter(|x| x.write_off_date.year() == year)
+            .filter_map(|x| tables.write_off_details(x))
+            .collect();
+        register.sort_by_key(|x| (x.write_off.write_off_date, x.write_off.id()));
+        Ok(register)
+    }
+}
+
+impl AuditDao for MemoryStore {
+    fn open(&self, audit: &mut Audit, items: &mut [AuditItem]) -> Result<(), AppError> {
+        self.transaction(|store| {
+            let mut tables = store.lock();
+            tables.check_audit(audit)?;
+            tables.audit.insert(audit);
+            for item in items.iter_mut() {
+                item.audit_id = audit.id();
+                tables.check_audit_item(item)?;
+                tables.audit_item.insert(item);
+            }
+            Ok(())
+        })
+    }
+
+    fn get_all(&self) -> Result<Vec<AuditSummary>, AppError> {
+        let tables = self.lock();
+        let mut audits: Vec<AuditSummary> = tables
+            .audit
+            .rows
+            .iter()
+            .map(|x| tables.audit_summary(x))
+            .collect();
+        audits.sort_by_key(|x| Reverse((x.audit.opened_at, x.audit.id())));
+        Ok(audits)
+    }
+
+    fn get_id(&self, id: i32) -> Result<AuditSummary, AppError> {
+        let tables = self.lock();
+        let audit = tables
+            .audit
+            .find(id)
+            .ok_or_else(|| AppError::not_found("Audit"))?;
+        Ok(tables.audit_summary(audit))
+    }
+
+    fn get_items(&self, audit_id: i32) -> Result<Vec<AuditItem>, AppError> {
+        let mut items: Vec<AuditItem> = self
+            .lock()
+            .audit_item
+            .rows
+            .iter()
+            .filter(|x| x.audit_id == audit_id)
+            .cloned()
+            .collect();
+        items.sort_by_key(|x| (!x.expected, x.inventory_number));
+        Ok(items)
+    }
+
+    fn mark(
+        &self,
+        audit_id: i32,
+        item_id: i32,
+        finding: Finding,
+        location: &str,
+    ) -> Result<(), AppError> {
+        let mut tables = self.lock();
+        tables.open_audit(audit_id)?;
+        let item = tables
+            .audit_item
+            .rows
+            .iter_mut()
+            .find(|x| x.id() == item_id && x.audit_id == audit_id && x.expected)
+            .ok_or_else(|| AppError::not_found("Audit item"))?;
+        item.finding = finding;
+        item.location = location.into();
+        Ok(())
+    }
+
+    fn add_item(&self, item: &mut AuditItem) -> Result<(), AppError> {
+        let mut tables = self.lock();
+        tables.open_audit(item.audit_id)?;
+        tables.check_audit_item(item)?;
+        tables.audit_item.insert(item);
+        Ok(())
+    }
+
+    fn remove_item(&self, audit_id: i32, item_id: i32) -> Result<(), AppError> {
+        let mut tables = self.lock();
+        tables.open_audit(audit_id)?;
+        let unexpected = tables
+            .audit_item
+            .find(item_id)
+            .is_some_and(|x| x.audit_id == audit_id && !x.expected);
+        if !unexpected {
+            return Err(AppError::not_found("Audit item"));
+        }
+        tables.audit_item.delete(item_id)
+    }
+
+    fn close(&self, id: i32) -> Result<(), AppError> {
+        let mut tables = self.lock();
+        tables.open_audit(id)?;
+        let pending = tables
+            .audit_item
+            .rows
+            .iter()
+            .filter(|x| x.audit_id == id && x.finding == Finding::Pending)
+            .count();
+        if pending > 0 {
+            return Err(audit::unchecked(pending as i64));
+        }
+        tables.audit.get_mut(id)?.closed_at = Some(Utc::now());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    struct Fixture {
+        store: MemoryStore,
+        accounting: i32,
+        it: i32,
+        accountant: i32,
+        admin: i32,
+        room: i32,
+        laptop: i32,
+    }
+
+    /// Two units with an accountable employee each, one room and a laptop bought on
+    /// 2024-01-01 that nobody holds yet.
+    fn fixture() -> Fixture {
+        let store = MemoryStore::new();
+        let mut accounting = Unit::new(1, "Accounting department", "ACC");
+        let mut it = Unit::new(2, "IT department", "IT");
+        UnitDao::insert(&store, &mut accounting).unwrap();
+        UnitDao::insert(&store, &mut it).unwrap();
+        let mut room = Room::new(101, 20);
+        RoomDao::insert(&store, &mut room).unwrap();
+        let mut accountant = Employee::new(
+            "Anna",
+            "Petrovna",
+            "Ivanova",
+            false,
+            true,
+            "Accountant",
+            accounting.id(),
+        );
+        let mut admin = Employee::new("Oleg", "", "Smirnov", false, true, "Admin", it.id());
+        EmployeeDao::insert(&store, &mut accountant).unwrap();
+        EmployeeDao::insert(&store, &mut admin).unwrap();
+        let price = Money::new(Amount::from_minor(100_000), Currency::RUB);
+        let mut laptop = Tech::new_with_date(1001, "Laptop", "T14", date("2024-01-01"), price);
+        TechDao::insert(&store, &mut laptop).unwrap();
+        Fixture {
+            store,
+            accounting: accounting.id(),
+            it: it.id(),
+            accountant: accountant.id(),
+            admin: admin.id(),
+            room: room.id(),
+            laptop: laptop.id(),
+        }
+    }
+
+    impl Fixture {
+        fn transfer(&self, on: &str, employee_id: i32) -> i32 {
+            let mut transfer = Transfer::new_date(date(on), self.laptop, self.room, employee_id);
+            TransferDao::insert(&self.store, &mut transfer).unwrap();
+            transfer.id()
+        }
+
+        /// The last name of whoever holds the laptop on `on`.
+        fn holder(&self, on: &str) -> Option<String> {
+            let holdings = self.store.get_holdings(date(on)).unwrap();
+            assert!(holdings.len() <= 1);
+            holdings.first().map(|x| x.last_name.clone())
+        }
+    }
+
+    #[test]
+    fn holdings_follow_the_latest_transfer_on_or_before_the_date() {
+        let f = fixture();
+        f.transfer("2024-01-10", f.accountant);
+        f.transfer("2024-03-01", f.admin);
+
+        assert_eq!(f.holder("2024-01-09"), None);
+        assert_eq!(f.holder("2024-01-10"), Some("Ivanova".to_string()));
+        assert_eq!(f.holder("2024-02-29"), Some("Ivanova".to_string()));
+        assert_eq!(f.holder("2024-03-01"), Some("Smirnov".to_string()));
+        assert_eq!(f.holder("2030-01-01"), Some("Smirnov".to_string()));
+    }
+
+    #[test]
+    fn unit_and_employee_reports_agree_with_holdings() {
+        let f = fixture();
+        f.transfer("2024-01-10", f.accountant);
+        f.transfer("2024-03-01", f.admin);
+        let (before, after) = (date("2024-02-01"), date("2024-03-01"));
+
+        let by_unit = |unit_id, on| {
+            TechDao::get_by_unit_id(&f.store, unit_id, on)
+                .unwrap()
+                .len()
+        };
+        assert_eq!(by_unit(f.accounting, before), 1);
+        assert_eq!(by_unit(f.it, before), 0);
+        assert_eq!(by_unit(f.accounting, after), 0);
+        assert_eq!(by_unit(f.it, after), 1);
+
+        let by_employee = |id, on| TechDao::get_by_employee_id(&f.store, id, on).unwrap();
+        let held = by_employee(f.accountant, before);
+        assert_eq!(held.len(), 1);
+        assert_eq!(held[0].date, date("2024-01-10"));
+        assert!(by_employee(f.admin, before).is_empty());
+        assert!(by_employee(f.accountant, after).is_empty());
+        assert_eq!(by_employee(f.admin, after)[0].date, after);
+    }
+
+    #[test]
+    fn the_later_transfer_of_the_same_day_wins() {
+        let f = fixture();
+        f.transfer("2024-01-10", f.accountant);
+        f.transfer("2024-01-10", f.admin);
+
+        assert_eq!(f.holder("2024-01-10"), Some("Smirnov".to_string()));
+    }
+
+    #[test]
+    fn written_off_tech_leaves_the_holdings_on_its_write_off_date() {
+        let f = fixture();
+        f.transfer("2024-01-10", f.accountant);
+        let mut write_off = WriteOff::new(
+            f.laptop,
+            date("2024-04-01"),
+            Disposal::Scrapped,
+            "Broken",
+            "WO-1",
+            f.accountant,
+            Amount::ZERO,
+        );
+        WriteOffDao::insert(&f.store, &mut write_off).unwrap();
+
+        assert_eq!(f.holder("2024-03-31"), Some("Ivanova".to_string()));
+        assert_eq!(f.holder("2024-04-01"), None);
+        let held = TechDao::get_by_employee_id(&f.store, f.accountant, date("2024-04-01"));
+        assert!(held.unwrap().is_empty());
+    }
+
+    #[test]
+    fn the_timeline_lists_transfers_oldest_first() {
+        let f = fixture();
+        let second = f.transfer("2024-03-01", f.admin);
+        let first = f.transfer("2024-01-10", f.accountant);
+        let third = f.transfer("2024-03-01", f.accountant);
+
+        let timeline = TransferDao::get_by_tech_id(&f.store, f.laptop).unwrap();
+        let ids: Vec<i32> = timeline.iter().map(|x| x.id).collect();
+        assert_eq!(ids, [first, second, third]);
+        assert_eq!(f.holder("2024-03-01"), Some("Ivanova".to_string()));
+    }
+}
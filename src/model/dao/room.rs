@@ -1,7 +1,6 @@
-use super::{Error, PostgrePool, Row};
-use std::rc::Rc;
+use super::{audit, AppError, Db, Error, Row};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Room {
     id: Option<i32>,
     pub room_number: i32,
@@ -36,17 +35,64 @@ impl Room {
     }
 }
 
+pub(super) fn still_used(transfers: i64) -> AppError {
+    AppError::Conflict(format!(
+        "The room is used by {} transfer(s) and cannot be deleted",
+        transfers
+    ))
+}
+
 pub struct RoomDAOImpl {
-    pool: Rc<PostgrePool>,
+    db: Db,
+}
+
+pub trait RoomDao: Send + Sync {
+    fn insert(&self, room: &mut Room) -> Result<(), AppError>;
+    fn update(&self, room: &Room) -> Result<(), AppError>;
+    fn get_all(&self) -> Result<Vec<Room>, AppError>;
+    fn get_id(&self, id: i32) -> Result<Room, AppError>;
+    /// Refuses to delete a room that any transfer still points at.
+    fn delete(&self, id: i32) -> Result<(), AppError>;
 }
 
 impl RoomDAOImpl {
-    pub fn new(pool: Rc<PostgrePool>) -> Self {
-        Self { pool }
+    pub fn new(db: Db) -> Self {
+        Self { db }
+    }
+
+    const INSERT: &'static str =
+        "INSERT INTO room (room_number, squares) VALUES ($1, $2) RETURNING id;";
+    const UPDATE: &'static str = "UPDATE room SET room_number = $1, squares = $2 WHERE id = $3;";
+    const FIND_ALL: &'static str =
+        "SELECT id, room_number, squares FROM room ORDER BY room_number;";
+    const FIND_ID: &'static str = "SELECT id, room_number, squares FROM room WHERE id = $1;";
+    const COUNT_TRANSFERS: &'static str = "SELECT COUNT(*) FROM transfer WHERE room_id = $1;";
+    const COUNT_AUDITS: &'static str = "SELECT COUNT(*) FROM audit WHERE room_id = $1;";
+    const DELETE: &'static str = "DELETE FROM room WHERE id = $1;";
+}
+
+impl RoomDao for RoomDAOImpl {
+    fn insert(&self, room: &mut Room) -> Result<(), AppError> {
+        let mut conn = self.db.get()?;
+        let id: i32 = conn
+            .query_one(Self::INSERT, &[&room.room_number, &room.squares])
+            .map(|row| row.get(0))?;
+        room.id = Some(id);
+        Ok(())
     }
 
-    pub fn get_all(&self) -> Result<Vec<Room>, Error> {
-        let mut conn = self.pool.get().unwrap();
+    fn update(&self, room: &Room) -> Result<(), AppError> {
+        let mut conn = self.db.get()?;
+        let rows_affected =
+            conn.execute(Self::UPDATE, &[&room.room_number, &room.squares, &room.id])?;
+        if rows_affected == 0 {
+            return Err(AppError::not_found("Room"));
+        }
+        Ok(())
+    }
+
+    fn get_all(&self) -> Result<Vec<Room>, AppError> {
+        let mut conn = self.db.get()?;
         let mut all_rooms = vec![];
         for row in conn.query(Self::FIND_ALL, &[])? {
             all_rooms.push(Room::from_row(row)?);
@@ -54,5 +100,30 @@ impl RoomDAOImpl {
         Ok(all_rooms)
     }
 
-    const FIND_ALL: &'static str = "SELECT id, room_number, squares FROM room;";
+    fn get_id(&self, id: i32) -> Result<Room, AppError> {
+        let mut conn = self.db.get()?;
+        let row = conn
+            .query_opt(Self::FIND_ID, &[&id])?
+            .ok_or_else(|| AppError::not_found("Room"))?;
+        Ok(Room::from_row(row)?)
+    }
+
+    fn delete(&self, id: i32) -> Result<(), AppError> {
+        self.db.transaction(|db| {
+            let mut conn = db.get()?;
+            let transfers: i64 = conn.query_one(Self::COUNT_TRANSFERS, &[&id])?.get(0);
+            if transfers > 0 {
+                return Err(still_used(transfers));
+            }
+            let audits: i64 = conn.query_one(Self::COUNT_AUDITS, &[&id])?.get(0);
+            if audits > 0 {
+                return Err(audit::audited("room", audits));
+            }
+            let rows_affected = conn.execute(Self::DELETE, &[&id])?;
+            if rows_affected == 0 {
+                return Err(AppError::not_found("Room"));
+            }
+            Ok(())
+        })
+    }
 }
@@ -1,15 +1,22 @@
-use super::{Error, PostgrePool, Row};
-use chrono::NaiveDate;
-use std::rc::Rc;
+use super::category::{self, Depreciation, DepreciationMethod};
+use super::{AppError, Db, Error, Row};
+use crate::model::money::Money;
+use chrono::{DateTime, NaiveDate, Utc};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Tech {
     id: Option<i32>,
     pub inventory_number: i32,
     pub name: String,
     pub model: String,
     pub acquisition_date: NaiveDate,
-    pub price: i32,
+    pub price: Money,
+    /// Set once the item is archived: it leaves the lists and forms but keeps its history.
+    pub archived_at: Option<DateTime<Utc>>,
+    /// The day the item was written off, taken from its write-off record.
+    pub written_off_on: Option<NaiveDate>,
+    /// Decides how the item depreciates; without one it keeps its price.
+    pub category_id: Option<i32>,
 }
 
 #[derive(Debug)]
@@ -19,6 +26,7 @@ pub struct TechUnit {
     pub inventory_number: i32,
     pub name: String,
     pub model: String,
+    pub price: Money,
 }
 
 impl TechUnit {
@@ -29,8 +37,67 @@ impl TechUnit {
             inventory_number: row.get(2),
             name: row.get(3),
             model: row.get(4),
+            price: Money::new(row.get(5), row.get(6)),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct TechHolding {
+    pub date: NaiveDate,
+    pub inventory_number: i32,
+    pub name: String,
+    pub model: String,
+    pub room_number: i32,
+    pub first_name: String,
+    pub middle_name: String,
+    pub last_name: String,
+    pub unit_name: String,
+    pub tech_archived: bool,
+    pub employee_archived: bool,
+    pub unit_archived: bool,
+    pub unit_id: i32,
+    pub acquisition_date: NaiveDate,
+    pub price: Money,
+    pub depreciation: Option<Depreciation>,
+    pub tech_id: i32,
+    pub room_id: i32,
+}
+
+impl TechHolding {
+    fn from_row(row: Row) -> Result<Self, AppError> {
+        let depreciation = match row.get::<_, Option<&str>>(15) {
+            Some(method) => Some(Depreciation {
+                method: DepreciationMethod::parse(method)?,
+                useful_life_years: row.get(16),
+            }),
+            None => None,
+        };
+        Ok(Self {
+            date: row.get(0),
+            inventory_number: row.get(1),
+            name: row.get(2),
+            model: row.get(3),
+            room_number: row.get(4),
+            first_name: row.get(5),
+            middle_name: row.get(6),
+            last_name: row.get(7),
+            unit_name: row.get(8),
+            tech_archived: row.get(9),
+            employee_archived: row.get(10),
+            unit_archived: row.get(11),
+            unit_id: row.get(12),
+            acquisition_date: row.get(13),
+            price: Money::new(row.get(14), row.get(17)),
+            depreciation,
+            tech_id: row.get(18),
+            room_id: row.get(19),
         })
     }
+
+    pub fn book_value(&self, date: NaiveDate) -> Money {
+        category::book_value(self.depreciation, self.price, self.acquisition_date, date)
+    }
 }
 
 #[derive(Debug)]
@@ -43,6 +110,7 @@ pub struct TechEmployee {
     pub inventory_number: i32,
     pub name: String,
     pub model: String,
+    pub price: Money,
 }
 
 impl TechEmployee {
@@ -55,7 +123,8 @@ impl TechEmployee {
             job_title: row.get(4),
             inventory_number: row.get(5),
             name: row.get(6),
-            model: row.get(7)
+            model: row.get(7),
+            price: Money::new(row.get(8), row.get(9)),
         })
     }
 }
@@ -68,7 +137,10 @@ impl Tech {
             name: row.get(2),
             model: row.get(3),
             acquisition_date: row.get(4),
-            price: row.get(5),
+            price: Money::new(row.get(5), row.get(9)),
+            archived_at: row.get(6),
+            written_off_on: row.get(7),
+            category_id: row.get(8),
         })
     }
 
@@ -85,16 +157,18 @@ impl Tech {
         name: &str,
         model: &str,
         acquisition_date: &str,
-        price: i32,
-    ) -> Tech {
-        Tech {
-            id: None,
+        price: Money,
+    ) -> Result<Tech, AppError> {
+        let acquisition_date = acquisition_date
+            .parse()
+            .map_err(|_| AppError::validation("Invalid acquisition date"))?;
+        Ok(Self::new_with_date(
             inventory_number,
-            name: name.into(),
-            model: model.into(),
-            acquisition_date: acquisition_date.parse().expect("Invalid date"),
+            name,
+            model,
+            acquisition_date,
             price,
-        }
+        ))
     }
 
     pub fn new_with_date(
@@ -102,7 +176,7 @@ impl Tech {
         name: &str,
         model: &str,
         acquisition_date: NaiveDate,
-        price: i32,
+        price: Money,
     ) -> Tech {
         Tech {
             id: None,
@@ -111,21 +185,204 @@ impl Tech {
             model: model.into(),
             acquisition_date,
             price,
+            archived_at: None,
+            written_off_on: None,
+            category_id: None,
         }
     }
+
+    pub fn is_archived(&self) -> bool {
+        self.archived_at.is_some()
+    }
+
+    pub fn is_written_off(&self) -> bool {
+        self.written_off_on.is_some()
+    }
+}
+
+pub(super) fn still_used(transfers: i64) -> AppError {
+    AppError::Conflict(format!(
+        "The tech has {} transfer(s); archive it to keep its history",
+        transfers
+    ))
+}
+
+pub(super) fn written_off() -> AppError {
+    AppError::Conflict(
+        "The tech has been written off; its write-off record has to be kept".to_string(),
+    )
 }
 
 pub struct TechDaoImpl {
-    pool: Rc<PostgrePool>,
+    db: Db,
+}
+
+pub trait TechDao: Send + Sync {
+    fn insert(&self, tech: &mut Tech) -> Result<(), AppError>;
+    fn update(&self, tech: &Tech) -> Result<(), AppError>;
+    /// Tech that is in use, leaving out archived items.
+    fn get_all(&self) -> Result<Vec<Tech>, AppError>;
+    fn get_archived(&self) -> Result<Vec<Tech>, AppError>;
+    /// Tech that can still be transferred: neither archived nor written off.
+    fn get_in_service(&self) -> Result<Vec<Tech>, AppError>;
+    fn get_id(&self, id: i32) -> Result<Tech, AppError>;
+    /// Archived and written-off items are found too.
+    fn get_by_inventory_number(&self, inventory_number: i32) -> Result<Tech, AppError>;
+    fn delete(&self, id: i32) -> Result<(), AppError>;
+    fn archive(&self, id: i32) -> Result<(), AppError>;
+    fn restore(&self, id: i32) -> Result<(), AppError>;
+    /// Tech held by the unit's employees on `date`, judged by each item's latest
+    /// transfer on or before that day. Tech written off by then is left out, here and
+    /// in the other holdings queries.
+    fn get_by_unit_id(&self, unit_id: i32, date: NaiveDate) -> Result<Vec<TechUnit>, AppError>;
+    fn get_by_employee_id(
+        &self,
+        employee_id: i32,
+        date: NaiveDate,
+    ) -> Result<Vec<TechEmployee>, AppError>;
+    /// Where every piece of tech was on `date`, across the whole organisation.
+    fn get_holdings(&self, date: NaiveDate) -> Result<Vec<TechHolding>, AppError>;
 }
 
 impl TechDaoImpl {
-    pub fn new(pool: Rc<PostgrePool>) -> Self {
-        Self { pool }
+    pub fn new(db: Db) -> Self {
+        Self { db }
     }
 
-    pub fn insert(&self, tech: &mut Tech) -> Result<(), Error> {
-        let mut conn = self.pool.get().unwrap();
+    const INSERT: &'static str = "INSERT INTO tech (inventory_number, name, model, acquisition_date, price, currency, category_id) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING id";
+    const UPDATE: &'static str = "UPDATE tech SET inventory_number = $1, name = $2, model = $3, acquisition_date = $4, price = $5, currency = $6, category_id = $7 WHERE id = $8";
+    const FIND_ALL: &'static str = "SELECT t.id, t.inventory_number, t.name, t.model, t.acquisition_date, t.price, t.archived_at, w.write_off_date, t.category_id, t.currency FROM tech t LEFT JOIN write_off w ON w.tech_id = t.id WHERE t.archived_at IS NULL";
+    const FIND_ARCHIVED: &'static str = "SELECT t.id, t.inventory_number, t.name, t.model, t.acquisition_date, t.price, t.archived_at, w.write_off_date, t.category_id, t.currency FROM tech t LEFT JOIN write_off w ON w.tech_id = t.id WHERE t.archived_at IS NOT NULL ORDER BY t.archived_at DESC";
+    const FIND_IN_SERVICE: &'static str = "SELECT t.id, t.inventory_number, t.name, t.model, t.acquisition_date, t.price, t.archived_at, w.write_off_date, t.category_id, t.currency FROM tech t LEFT JOIN write_off w ON w.tech_id = t.id WHERE t.archived_at IS NULL AND w.id IS NULL";
+    const FIND_ID: &'static str = "SELECT t.id, t.inventory_number, t.name, t.model, t.acquisition_date, t.price, t.archived_at, w.write_off_date, t.category_id, t.currency FROM tech t LEFT JOIN write_off w ON w.tech_id = t.id WHERE t.id = $1";
+    const FIND_INVENTORY_NUMBER: &'static str = "SELECT t.id, t.inventory_number, t.name, t.model, t.acquisition_date, t.price, t.archived_at, w.write_off_date, t.category_id, t.currency FROM tech t LEFT JOIN write_off w ON w.tech_id = t.id WHERE t.inventory_number = $1";
+    const ARCHIVE: &'static str =
+        "UPDATE tech SET archived_at = COALESCE(archived_at, now()) WHERE id = $1";
+    const RESTORE: &'static str = "UPDATE tech SET archived_at = NULL WHERE id = $1";
+    const DELETE: &'static str = "DELETE FROM tech WHERE id = $1";
+    const COUNT_TRANSFERS: &'static str = "SELECT COUNT(*) FROM transfer WHERE tech_id = $1;";
+    const COUNT_WRITE_OFFS: &'static str = "SELECT COUNT(*) FROM write_off WHERE tech_id = $1;";
+
+    const FIND_BY_UNIT_ID: &'static str = r#"SELECT 
+        u.full_name AS unit_name,       
+        tt.transfer_date AS transfer_date,
+        t.inventory_number,          
+        t.name,                           
+        t.model,
+        t.price,
+        t.currency
+    FROM 
+        transfer tt
+    JOIN 
+        employee e ON tt.employee_id = e.id  -- Correct join to the employee table
+    JOIN 
+        unit u ON e.unit_id = u.id           -- Correct join to the unit table    
+    JOIN 
+        tech t ON tt.tech_id = t.id  
+    WHERE 
+        u.id = $1    
+        AND tt.transfer_date <= $2 
+        AND NOT EXISTS (
+            SELECT 1 FROM write_off w WHERE w.tech_id = t.id AND w.write_off_date <= $2
+        )
+        AND tt.id = (
+    	    SELECT id
+    	    FROM transfer
+    	    WHERE tech_id = tt.tech_id
+    	    AND transfer_date <= $2
+    	    ORDER BY transfer_date DESC, id DESC
+    	    LIMIT 1
+	    )
+    ORDER BY 
+        tt.transfer_date DESC;  "#;
+
+    const FIND_BY_EMPLOYEE_ID: &'static str = r#"SELECT 
+        tt.transfer_date,                                                           
+        e.first_name,
+	    e.middle_name,
+	    e.last_name AS employee_name,  
+        e.job_title,                                                                
+        t.inventory_number,      
+        t.name,                                                       
+        t.model,
+        t.price,
+        t.currency
+    FROM 
+        transfer tt
+    JOIN 
+        employee e ON tt.employee_id = e.id  
+    JOIN 
+        tech t ON tt.tech_id = t.id      
+    WHERE 
+        e.id = $1                    
+        AND tt.transfer_date <= $2
+        AND NOT EXISTS (
+            SELECT 1 FROM write_off w WHERE w.tech_id = t.id AND w.write_off_date <= $2
+        )
+        AND tt.id = (
+    	    SELECT id
+    	    FROM transfer
+    	    WHERE tech_id = tt.tech_id
+    	    AND transfer_date <= $2
+    	    ORDER BY transfer_date DESC, id DESC
+    	    LIMIT 1
+	    ) 
+    ORDER BY 
+        tt.transfer_date DESC;"#;
+
+    const FIND_HOLDINGS: &'static str = r#"SELECT
+        tt.transfer_date,
+        t.inventory_number,
+        t.name,
+        t.model,
+        r.room_number,
+        e.first_name,
+        e.middle_name,
+        e.last_name,
+        u.full_name,
+        t.archived_at IS NOT NULL,
+        e.archived_at IS NOT NULL,
+        u.archived_at IS NOT NULL,
+        u.id,
+        t.acquisition_date,
+        t.price,
+        c.method,
+        c.useful_life_years,
+        t.currency,
+        t.id,
+        r.id
+    FROM
+        transfer tt
+    JOIN
+        tech t ON tt.tech_id = t.id
+    LEFT JOIN
+        tech_category c ON t.category_id = c.id
+    JOIN
+        room r ON tt.room_id = r.id
+    JOIN
+        employee e ON tt.employee_id = e.id
+    JOIN
+        unit u ON e.unit_id = u.id
+    WHERE
+        tt.transfer_date <= $1
+        AND NOT EXISTS (
+            SELECT 1 FROM write_off w WHERE w.tech_id = t.id AND w.write_off_date <= $1
+        )
+        AND tt.id = (
+            SELECT id
+            FROM transfer
+            WHERE tech_id = tt.tech_id
+            AND transfer_date <= $1
+            ORDER BY transfer_date DESC, id DESC
+            LIMIT 1
+        )
+    ORDER BY
+        u.full_name, e.last_name, e.first_name, t.inventory_number;"#;
+}
+
+impl TechDao for TechDaoImpl {
+    fn insert(&self, tech: &mut Tech) -> Result<(), AppError> {
+        let mut conn = self.db.get()?;
 
         let id: i32 = conn
             .query_one(
@@ -135,7 +392,9 @@ impl TechDaoImpl {
                     &tech.name,
                     &tech.model,
                     &tech.acquisition_date,
-                    &tech.price,
+                    &tech.price.amount,
+                    &tech.price.currency,
+                    &tech.category_id,
                 ],
             )
             .map(|row| row.get(0))?;
@@ -145,8 +404,8 @@ impl TechDaoImpl {
         Ok(())
     }
 
-    pub fn update(&self, tech: &Tech) -> Result<(), Error> {
-        let mut conn = self.pool.get().unwrap();
+    fn update(&self, tech: &Tech) -> Result<(), AppError> {
+        let mut conn = self.db.get()?;
         let rows_affected = conn.execute(
             Self::UPDATE,
             &[
@@ -154,16 +413,21 @@ impl TechDaoImpl {
                 &tech.name,
                 &tech.model,
                 &tech.acquisition_date,
-                &tech.price,
+                &tech.price.amount,
+                &tech.price.currency,
+                &tech.category_id,
                 &tech.id,
             ],
         )?;
+        if rows_affected == 0 {
+            return Err(AppError::not_found("Tech"));
+        }
 
         Ok(())
     }
 
-    pub fn get_all(&self) -> Result<Vec<Tech>, Error> {
-        let mut conn = self.pool.get().unwrap();
+    fn get_all(&self) -> Result<Vec<Tech>, AppError> {
+        let mut conn = self.db.get()?;
         let mut all_tech = vec![];
         for row in conn.query(Self::FIND_ALL, &[])? {
             all_tech.push(Tech::from_row(row)?);
@@ -171,98 +435,103 @@ impl TechDaoImpl {
         Ok(all_tech)
     }
 
-    pub fn get_id(&self, id: i32) -> Result<Tech, Error> {
-        let mut conn = self.pool.get().unwrap();
-        let row = conn.query_one(Self::FIND_ID, &[&id])?;
+    fn get_archived(&self) -> Result<Vec<Tech>, AppError> {
+        let mut conn = self.db.get()?;
+        let mut archived = vec![];
+        for row in conn.query(Self::FIND_ARCHIVED, &[])? {
+            archived.push(Tech::from_row(row)?);
+        }
+        Ok(archived)
+    }
+
+    fn get_in_service(&self) -> Result<Vec<Tech>, AppError> {
+        let mut conn = self.db.get()?;
+        let mut in_service = vec![];
+        for row in conn.query(Self::FIND_IN_SERVICE, &[])? {
+            in_service.push(Tech::from_row(row)?);
+        }
+        Ok(in_service)
+    }
+
+    fn get_id(&self, id: i32) -> Result<Tech, AppError> {
+        let mut conn = self.db.get()?;
+        let row = conn
+            .query_opt(Self::FIND_ID, &[&id])?
+            .ok_or_else(|| AppError::not_found("Tech"))?;
+        Ok(Tech::from_row(row)?)
+    }
+
+    fn get_by_inventory_number(&self, inventory_number: i32) -> Result<Tech, AppError> {
+        let mut conn = self.db.get()?;
+        let row = conn
+            .query_opt(Self::FIND_INVENTORY_NUMBER, &[&inventory_number])?
+            .ok_or_else(|| AppError::not_found("Tech"))?;
         Ok(Tech::from_row(row)?)
     }
 
-    pub fn delete(&self, id: i32) -> Result<(), Error> {
-        let mut conn = self.pool.get().unwrap();
-        let rows_affected = conn.execute(Self::DELETE, &[&id])?;
+    fn delete(&self, id: i32) -> Result<(), AppError> {
+        self.db.transaction(|db| {
+            let mut conn = db.get()?;
+            let write_offs: i64 = conn.query_one(Self::COUNT_WRITE_OFFS, &[&id])?.get(0);
+            if write_offs > 0 {
+                return Err(written_off());
+            }
+            let transfers: i64 = conn.query_one(Self::COUNT_TRANSFERS, &[&id])?.get(0);
+            if transfers > 0 {
+                return Err(still_used(transfers));
+            }
+            let rows_affected = conn.execute(Self::DELETE, &[&id])?;
+            if rows_affected == 0 {
+                return Err(AppError::not_found("Tech"));
+            }
+            Ok(())
+        })
+    }
+
+    fn archive(&self, id: i32) -> Result<(), AppError> {
+        let mut conn = self.db.get()?;
+        if conn.execute(Self::ARCHIVE, &[&id])? == 0 {
+            return Err(AppError::not_found("Tech"));
+        }
         Ok(())
     }
 
-    pub fn get_by_unit_id(&self, unit_id: i32) -> Result<Vec<TechUnit>, Error> {
-        let mut conn = self.pool.get().unwrap();
+    fn restore(&self, id: i32) -> Result<(), AppError> {
+        let mut conn = self.db.get()?;
+        if conn.execute(Self::RESTORE, &[&id])? == 0 {
+            return Err(AppError::not_found("Tech"));
+        }
+        Ok(())
+    }
+
+    fn get_by_unit_id(&self, unit_id: i32, date: NaiveDate) -> Result<Vec<TechUnit>, AppError> {
+        let mut conn = self.db.get()?;
         let mut all_unit = vec![];
-        for row in conn.query(Self::FIND_BY_UNIT_ID, &[&unit_id])? {
+        for row in conn.query(Self::FIND_BY_UNIT_ID, &[&unit_id, &date])? {
             all_unit.push(TechUnit::from_row(row)?);
         }
         Ok(all_unit)
     }
 
-    pub fn get_by_employee_id(&self, employee_id: i32) -> Result<Vec<TechEmployee>, Error> {
-        let mut conn = self.pool.get().unwrap();
+    fn get_by_employee_id(
+        &self,
+        employee_id: i32,
+        date: NaiveDate,
+    ) -> Result<Vec<TechEmployee>, AppError> {
+        let mut conn = self.db.get()?;
         let mut all_unit = vec![];
-        for row in conn.query(Self::FIND_BY_EMPLOYEE_ID, &[&employee_id])? {
+        for row in conn.query(Self::FIND_BY_EMPLOYEE_ID, &[&employee_id, &date])? {
             all_unit.push(TechEmployee::from_row(row)?);
         }
         Ok(all_unit)
     }
 
-    const INSERT: &'static str = "INSERT INTO tech (inventory_number, name, model, acquisition_date, price) VALUES ($1, $2, $3, $4, $5) RETURNING id";
-    const UPDATE: &'static str = "UPDATE tech SET inventory_number = $1, name = $2, model = $3, acquisition_date = $4, price = $5 WHERE id = $6";
-    const FIND_ALL: &'static str =
-        "SELECT id, inventory_number, name, model, acquisition_date, price FROM tech";
-    const FIND_ID: &'static str =
-        "SELECT id, inventory_number, name, model, acquisition_date, price FROM tech WHERE id = $1";
-    const DELETE: &'static str = "DELETE FROM tech WHERE id = $1";
-
-    const FIND_BY_UNIT_ID: &'static str = r#"SELECT 
-        u.full_name AS unit_name,       
-        tt.transfer_date AS transfer_date,
-        t.inventory_number,          
-        t.name,                           
-        t.model
-    FROM 
-        transfer tt
-    JOIN 
-        employee e ON tt.employee_id = e.id  -- Correct join to the employee table
-    JOIN 
-        unit u ON e.unit_id = u.id           -- Correct join to the unit table    
-    JOIN 
-        tech t ON tt.tech_id = t.id  
-    WHERE 
-        u.id = $1    
-        AND tt.transfer_date <= CURRENT_DATE 
-        AND tt.id = (
-    	    SELECT id
-    	    FROM transfer
-    	    WHERE tech_id = tt.tech_id
-    	    AND transfer_date <= CURRENT_DATE
-    	    ORDER BY transfer_date DESC
-    	    LIMIT 1
-	    )
-    ORDER BY 
-        tt.transfer_date DESC;  "#;
-
-    const FIND_BY_EMPLOYEE_ID: &'static str = r#"SELECT 
-        tt.transfer_date,                                                           
-        e.first_name,
-	    e.middle_name,
-	    e.last_name AS employee_name,  
-        e.job_title,                                                                
-        t.inventory_number,      
-        t.name,                                                       
-        t.model                                                          
-    FROM 
-        transfer tt
-    JOIN 
-        employee e ON tt.employee_id = e.id  
-    JOIN 
-        tech t ON tt.tech_id = t.id      
-    WHERE 
-        e.id = $1                    
-        AND tt.transfer_date <= CURRENT_DATE
-        AND tt.id = (
-    	    SELECT id
-    	    FROM transfer
-    	    WHERE tech_id = tt.tech_id
-    	    AND transfer_date <= CURRENT_DATE
-    	    ORDER BY transfer_date DESC
-    	    LIMIT 1
-	    ) 
-    ORDER BY 
-        tt.transfer_date DESC;"#;
+    fn get_holdings(&self, date: NaiveDate) -> Result<Vec<TechHolding>, AppError> {
+        let mut conn = self.db.get()?;
+        let mut holdings = vec![];
+        for row in conn.query(Self::FIND_HOLDINGS, &[&date])? {
+            holdings.push(TechHolding::from_row(row)?);
+        }
+        Ok(holdings)
+    }
 }
@@ -1,10 +1,9 @@
-use super::{employee::Employee, room::Room, tech::Tech, unit::Unit};
-use super::{Error, PostgrePool, Row};
-use chrono::offset::Utc;
-use chrono::NaiveDate;
-use std::rc::Rc;
+use super::{employee::Employee, room::Room, tech::Tech, unit::Unit, write_off};
+use super::{AppError, Client, Db, Error, Row};
+use crate::model::money::Money;
+use chrono::{Local, NaiveDate};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Transfer {
     id: Option<i32>,
     pub transfer_date: NaiveDate,
@@ -29,7 +28,58 @@ pub struct TransferDetails {
     pub unit: Option<Unit>,
 }
 
-impl TransferDetails {}
+impl TransferDetails {
+    /// Maps a row of `FIND_ALL_DETAILED`, leaving out whatever the LEFT JOINs did not find.
+    fn from_row(row: &Row) -> TransferDetails {
+        TransferDetails {
+            id: row.get("transfer_id"),
+            transfer_date: row.get::<_, NaiveDate>("transfer_date"),
+            tech: row.try_get("tech_id").ok().map(|id: i32| {
+                let date = row.get::<_, NaiveDate>("acquisition_date");
+                let mut tech = Tech::new_with_date(
+                    row.get("inventory_number"),
+                    row.get("tech_name"),
+                    row.get("model"),
+                    date,
+                    Money::new(row.get("price"), row.get("currency")),
+                );
+                tech.set_id(id);
+                tech.archived_at = row.get("tech_archived_at");
+                tech.written_off_on = row.get("tech_written_off_on");
+                tech
+            }),
+            room: row.try_get("room_id").ok().map(|id: i32| {
+                let mut room = Room::new(row.get("room_number"), row.get("squares"));
+                room.set_id(id);
+                room
+            }),
+            employee: row.try_get("employee_id").ok().map(|id: i32| {
+                let mut employee = Employee::new(
+                    row.get("first_name"),
+                    row.get("middle_name"),
+                    row.get("last_name"),
+                    row.get("is_supervisor"),
+                    row.get("is_accountable"),
+                    row.get("job_title"),
+                    row.get("unit_id"),
+                );
+                employee.set_id(id);
+                employee.archived_at = row.get("employee_archived_at");
+                employee
+            }),
+            unit: row.try_get("unit_id").ok().map(|id: i32| {
+                let mut unit = Unit::new(
+                    row.get("unit_number"),
+                    row.get("full_name"),
+                    row.get("short_name"),
+                );
+                unit.set_id(id);
+                unit.archived_at = row.get("unit_archived_at");
+                unit
+            }),
+        }
+    }
+}
 
 impl Transfer {
     fn from_row(row: Row) -> Result<Transfer, Error> {
@@ -45,21 +95,11 @@ impl Transfer {
     pub fn id(&self) -> i32 {
         self.id.unwrap_or(0)
     }
-    
+
     pub fn set_id(&mut self, id: i32) {
         self.id = Some(id)
     }
 
-    pub fn new(transfer_date: &str, tech_id: i32, room_id: i32, employee_id: i32) -> Transfer {
-        Transfer {
-            id: None,
-            transfer_date: transfer_date.parse().expect("Invalid date"),
-            tech_id,
-            room_id,
-            employee_id,
-        }
-    }
-
     pub fn new_date(
         transfer_date: NaiveDate,
         tech_id: i32,
@@ -76,109 +116,48 @@ impl Transfer {
     }
 }
 
-pub struct TransferDAOImpl {
-    pool: Rc<PostgrePool>,
+/// A transfer dated after today is pending: it is listed as upcoming and does not
+/// move the tech until its day comes.
+pub fn is_pending(transfer_date: NaiveDate, today: NaiveDate) -> bool {
+    transfer_date > today
 }
 
-impl TransferDAOImpl {
-    pub fn new(pool: Rc<PostgrePool>) -> Self {
-        Self { pool }
-    }
-
-    pub fn get_detailed_transfers(
-        &self,
-    ) -> Result<Vec<TransferDetails>, Box<dyn std::error::Error>> {
-        let mut transfers = Vec::new();
-        let mut conn = self.pool.get().unwrap();
-
-        for row in conn.query(Self::FIND_ALL_DETAILED, &[])? {
-            let transfer = TransferDetails {
-                id: row.get("transfer_id"),
-                transfer_date: row.get::<_, NaiveDate>("transfer_date"),
-                tech: row.try_get("tech_id").ok().and_then(|id: i32| {
-                    let date = row.get::<_, NaiveDate>("acquisition_date");
-                    let mut tech = Tech::new_with_date(
-                        row.get("inventory_number"),
-                        row.get("tech_name"),
-                        row.get("model"),
-                        date,
-                        row.get("price"),
-                    );
-                    tech.set_id(id);
-                    Some(tech)
-                }),
-                room: row.try_get("room_id").ok().map(|id: i32| {
-                    let mut room = Room::new(row.get("room_number"), row.get("squares"));
-                    room.set_id(id);
-                    room
-                }),
-                employee: row.try_get("employee_id").ok().map(|id: i32| {
-                    let mut employee = Employee::new(
-                        row.get("first_name"),
-                        row.get("middle_name"),
-                        row.get("last_name"),
-                        row.get("is_supervisor"),
-                        row.get("is_accountable"),
-                        row.get("job_title"),
-                        row.get("unit_id"),
-                    );
-                    employee.set_id(id);
-                    employee
-                }),
-                unit: row.try_get("unit_id").ok().map(|id: i32| {
-                    let mut unit = Unit::new(
-                        row.get("unit_number"),
-                        row.get("full_name"),
-                        row.get("short_name"),
-                    );
-                    unit.set_id(id);
-                    unit
-                }),
-            };
-            transfers.push(transfer);
-        }
-        Ok(transfers)
-    }
-
-    pub fn insert(&self, transfer: &mut Transfer) -> Result<(), Error> {
-        let mut conn = self.pool.get().unwrap();
-
-        let id: i32 = conn
-            .query_one(
-                Self::INSERT,
-                &[
-                    &transfer.transfer_date,
-                    &transfer.tech_id,
-                    &transfer.room_id,
-                    &transfer.employee_id,
-                ],
-            )
-            .map(|row| row.get(0))?;
+pub(super) fn already_scheduled() -> AppError {
+    AppError::Conflict("This tech already has a transfer scheduled for that day".to_string())
+}
 
-        transfer.id = Some(id);
+pub(super) fn not_pending() -> AppError {
+    AppError::Conflict("Only pending transfers can be cancelled or rescheduled".to_string())
+}
 
-        Ok(())
-    }
+pub struct TransferDAOImpl {
+    db: Db,
+}
 
-    pub fn update(&self, transfer: &Transfer) -> Result<(), Error> {
-        let mut conn = self.pool.get().unwrap();
-        let rows_affected = conn.execute(
-            Self::UPDATE,
-            &[
-                &transfer.transfer_date,
-                &transfer.tech_id,
-                &transfer.room_id,
-                &transfer.employee_id,
-                &transfer.id,
-            ],
-        )?;
-        println!("Affected: {}", rows_affected);
+pub trait TransferDao: Send + Sync {
+    fn get_detailed_transfers(&self) -> Result<Vec<TransferDetails>, AppError>;
+    /// Every transfer of one tech item, oldest first.
+    fn get_by_tech_id(&self, tech_id: i32) -> Result<Vec<TransferDetails>, AppError>;
+    /// Every transfer to one employee, oldest first.
+    fn get_by_employee_id(&self, employee_id: i32) -> Result<Vec<TransferDetails>, AppError>;
+    fn insert(&self, transfer: &mut Transfer) -> Result<(), AppError>;
+    fn update(&self, transfer: &Transfer) -> Result<(), AppError>;
+    fn get_id_with_unit(&self, id: i32) -> Result<TransferUnit, AppError>;
+    /// Pending transfers, the nearest first.
+    fn get_pending(&self) -> Result<Vec<TransferDetails>, AppError>;
+    /// Deletes a transfer that has not taken effect yet.
+    fn cancel(&self, id: i32) -> Result<(), AppError>;
+    /// Moves a pending transfer to another day.
+    fn reschedule(&self, id: i32, transfer_date: NaiveDate) -> Result<(), AppError>;
+}
 
-        Ok(())
+impl TransferDAOImpl {
+    pub fn new(db: Db) -> Self {
+        Self { db }
     }
 
-    fn get_all(&self) -> Result<Vec<Transfer>, Error> {
-        let mut conn = self.pool.get().unwrap();
+    fn get_all(&self) -> Result<Vec<Transfer>, AppError> {
+        let mut conn = self.db.get()?;
         let mut all_transfers = vec![];
         for row in conn.query(Self::FIND_ALL, &[])? {
             all_transfers.push(Transfer::from_row(row)?);
@@ -186,27 +165,55 @@ impl TransferDAOImpl {
         Ok(all_transfers)
     }
 
-    fn get_id(&self, id: i32) -> Result<Transfer, Error> {
-        let mut conn = self.pool.get().unwrap();
-        let row = conn.query_one(Self::FIND_ID, &[&id])?;
+    fn get_id(&self, id: i32) -> Result<Transfer, AppError> {
+        let mut conn = self.db.get()?;
+        Self::find(&mut conn, id)
+    }
+
+    fn find(conn: &mut Client, id: i32) -> Result<Transfer, AppError> {
+        let row = conn
+            .query_opt(Self::FIND_ID, &[&id])?
+            .ok_or_else(|| AppError::not_found("Transfer"))?;
         Ok(Transfer::from_row(row)?)
     }
 
-    pub fn get_id_with_unit(&self, id: i32) -> Result<TransferUnit, Error> {
-        let mut conn = self.pool.get().unwrap();
-        let row = conn.query_one(Self::FIND_ID_UNIT, &[&id])?;
-        let unit_id = row.try_get(5).unwrap_or(0);
-        let transfer = Transfer::from_row(row)?;
-        Ok(TransferUnit {
-            inner: transfer,
-            unit_id,
-        })
+    /// Refuses a second pending transfer of the same tech on the same day, and any
+    /// transfer after the tech was written off. The tech row stays locked until the
+    /// transaction ends, so two requests cannot both pass the check.
+    fn check_scheduled(
+        conn: &mut Client,
+        tech_id: i32,
+        transfer_date: NaiveDate,
+        id: i32,
+    ) -> Result<(), AppError> {
+        conn.query(Self::LOCK_TECH, &[&tech_id])?;
+        if let Some(row) = conn.query_opt(Self::WRITTEN_OFF, &[&tech_id, &transfer_date])? {
+            return Err(write_off::written_off(row.get(0)));
+        }
+        let today = Local::now().date_naive();
+        let scheduled: i64 = conn
+            .query_one(
+                Self::COUNT_SCHEDULED,
+                &[&tech_id, &transfer_date, &today, &id],
+            )?
+            .get(0);
+        if scheduled > 0 {
+            return Err(already_scheduled());
+        }
+        Ok(())
     }
 
     const INSERT: &'static str = "INSERT INTO transfer (transfer_date, tech_id, room_id, employee_id) VALUES ($1, $2, $3, $4) RETURNING id;";
     const UPDATE: &'static str = "UPDATE transfer SET transfer_date = $1, tech_id = $2, room_id = $3, employee_id = $4 WHERE id = $5;";
     const FIND_ALL: &'static str = "SELECT * FROM transfer;";
     const FIND_ID: &'static str = "SELECT * FROM transfer WHERE id = $1;";
+    const LOCK_TECH: &'static str = "SELECT id FROM tech WHERE id = $1 FOR UPDATE;";
+    const WRITTEN_OFF: &'static str =
+        "SELECT write_off_date FROM write_off WHERE tech_id = $1 AND write_off_date < $2;";
+    const COUNT_SCHEDULED: &'static str = "SELECT COUNT(*) FROM transfer WHERE tech_id = $1 AND transfer_date = $2 AND transfer_date > $3 AND id <> $4;";
+    const CANCEL: &'static str = "DELETE FROM transfer WHERE id = $1 AND transfer_date > $2;";
+    const RESCHEDULE: &'static str =
+        "UPDATE transfer SET transfer_date = $1 WHERE id = $2 AND transfer_date > $3;";
     const FIND_ID_UNIT: &'static str = r#"SELECT transfer.*, employee.unit_id FROM transfer 
 LEFT JOIN employee ON transfer.employee_id = employee.id
 WHERE transfer.id = $1;"#;
@@ -219,6 +226,9 @@ WHERE transfer.id = $1;"#;
         tech.model,
         tech.acquisition_date,
         tech.price,
+        tech.currency,
+        tech.archived_at AS tech_archived_at,
+        write_off.write_off_date AS tech_written_off_on,
         room.id AS room_id,
         room.room_number,
         room.squares,
@@ -230,14 +240,157 @@ WHERE transfer.id = $1;"#;
         employee.is_accountable,
         employee.job_title,
         employee.unit_id,
+        employee.archived_at AS employee_archived_at,
         unit.id AS unit_id,
         unit.unit_number,
         unit.full_name,
-        unit.short_name
+        unit.short_name,
+        unit.archived_at AS unit_archived_at
     FROM 
         transfer
     LEFT JOIN tech ON transfer.tech_id = tech.id
+        LEFT JOIN write_off ON write_off.tech_id = tech.id
         LEFT JOIN room ON transfer.room_id = room.id
         LEFT JOIN employee ON transfer.employee_id = employee.id
-        LEFT JOIN unit ON employee.unit_id = unit.id;"#;
+        LEFT JOIN unit ON employee.unit_id = unit.id"#;
+}
+
+impl TransferDao for TransferDAOImpl {
+    fn get_detailed_transfers(&self) -> Result<Vec<TransferDetails>, AppError> {
+        let mut conn = self.db.get()?;
+        let mut transfers = vec![];
+        for row in conn.query(Self::FIND_ALL_DETAILED, &[])? {
+            transfers.push(TransferDetails::from_row(&row));
+        }
+        Ok(transfers)
+    }
+
+    fn get_by_tech_id(&self, tech_id: i32) -> Result<Vec<TransferDetails>, AppError> {
+        let mut conn = self.db.get()?;
+        let query = format!(
+            "{} WHERE transfer.tech_id = $1 ORDER BY transfer.transfer_date, transfer.id;",
+            Self::FIND_ALL_DETAILED
+        );
+        let mut transfers = vec![];
+        for row in conn.query(&query, &[&tech_id])? {
+            transfers.push(TransferDetails::from_row(&row));
+        }
+        Ok(transfers)
+    }
+
+    fn get_by_employee_id(&self, employee_id: i32) -> Result<Vec<TransferDetails>, AppError> {
+        let mut conn = self.db.get()?;
+        let query = format!(
+            "{} WHERE transfer.employee_id = $1 ORDER BY transfer.transfer_date, transfer.id;",
+            Self::FIND_ALL_DETAILED
+        );
+        let mut transfers = vec![];
+        for row in conn.query(&query, &[&employee_id])? {
+            transfers.push(TransferDetails::from_row(&row));
+        }
+        Ok(transfers)
+    }
+
+    fn insert(&self, transfer: &mut Transfer) -> Result<(), AppError> {
+        self.db.transaction(|db| {
+            let mut conn = db.get()?;
+            Self::check_scheduled(&mut conn, transfer.tech_id, transfer.transfer_date, 0)?;
+
+            let id: i32 = conn
+                .query_one(
+                    Self::INSERT,
+                    &[
+                        &transfer.transfer_date,
+                        &transfer.tech_id,
+                        &transfer.room_id,
+                        &transfer.employee_id,
+                    ],
+                )
+                .map(|row| row.get(0))?;
+
+            transfer.id = Some(id);
+
+            Ok(())
+        })
+    }
+
+    fn update(&self, transfer: &Transfer) -> Result<(), AppError> {
+        self.db.transaction(|db| {
+            let mut conn = db.get()?;
+            Self::check_scheduled(
+                &mut conn,
+                transfer.tech_id,
+                transfer.transfer_date,
+                transfer.id(),
+            )?;
+            let rows_affected = conn.execute(
+                Self::UPDATE,
+                &[
+                    &transfer.transfer_date,
+                    &transfer.tech_id,
+                    &transfer.room_id,
+                    &transfer.employee_id,
+                    &transfer.id,
+                ],
+            )?;
+            if rows_affected == 0 {
+                return Err(AppError::not_found("Transfer"));
+            }
+
+            Ok(())
+        })
+    }
+
+    fn get_id_with_unit(&self, id: i32) -> Result<TransferUnit, AppError> {
+        let mut conn = self.db.get()?;
+        let row = conn
+            .query_opt(Self::FIND_ID_UNIT, &[&id])?
+            .ok_or_else(|| AppError::not_found("Transfer"))?;
+        let unit_id = row.try_get(5).unwrap_or(0);
+        let transfer = Transfer::from_row(row)?;
+        Ok(TransferUnit {
+            inner: transfer,
+            unit_id,
+        })
+    }
+
+    fn get_pending(&self) -> Result<Vec<TransferDetails>, AppError> {
+        let mut conn = self.db.get()?;
+        let query = format!(
+            "{} WHERE transfer.transfer_date > $1 ORDER BY transfer.transfer_date, transfer.id;",
+            Self::FIND_ALL_DETAILED
+        );
+        let today = Local::now().date_naive();
+        let mut transfers = vec![];
+        for row in conn.query(&query, &[&today])? {
+            transfers.push(TransferDetails::from_row(&row));
+        }
+        Ok(transfers)
+    }
+
+    fn cancel(&self, id: i32) -> Result<(), AppError> {
+        let mut conn = self.db.get()?;
+        let today = Local::now().date_naive();
+        let rows_affected = conn.execute(Self::CANCEL, &[&id, &today])?;
+        if rows_affected == 0 {
+            // Tell a missing transfer apart from one that already took effect.
+            Self::find(&mut conn, id)?;
+            return Err(not_pending());
+        }
+        Ok(())
+    }
+
+    fn reschedule(&self, id: i32, transfer_date: NaiveDate) -> Result<(), AppError> {
+        self.db.transaction(|db| {
+            let mut conn = db.get()?;
+            let transfer = Self::find(&mut conn, id)?;
+            Self::check_scheduled(&mut conn, transfer.tech_id, transfer_date, id)?;
+            let today = Local::now().date_naive();
+            let rows_affected = conn.execute(Self::RESCHEDULE, &[&transfer_date, &id, &today])?;
+            if rows_affected == 0 {
+                return Err(not_pending());
+            }
+            Ok(())
+        })
+    }
 }
@@ -1,12 +1,14 @@
-use super::{tech::Tech, Error, PostgrePool, Row};
-use std::rc::Rc;
+use super::{audit, tech::Tech, AppError, Db, Error, Row};
+use chrono::{DateTime, Utc};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Unit {
     id: Option<i32>,
     pub unit_number: i32,
     pub full_name: String,
     pub short_name: String,
+    /// Set once the unit is reorganised away; its history stays intact.
+    pub archived_at: Option<DateTime<Utc>>,
 }
 
 impl Unit {
@@ -16,6 +18,7 @@ impl Unit {
             unit_number: row.get(1),
             full_name: row.get(2),
             short_name: row.get(3),
+            archived_at: row.get(4),
         })
     }
 
@@ -25,6 +28,7 @@ impl Unit {
             unit_number: row.get(1),
             full_name: row.get(2),
             short_name: row.get(3),
+            archived_at: row.get(4),
         }))
     }
 
@@ -34,9 +38,14 @@ impl Unit {
             unit_number,
             full_name: full_name.into(),
             short_name: short_name.into(),
+            archived_at: None,
         }
     }
 
+    pub fn is_archived(&self) -> bool {
+        self.archived_at.is_some()
+    }
+
     pub fn id(&self) -> i32 {
         self.id.unwrap_or(-1)
     } 
@@ -46,17 +55,62 @@ impl Unit {
     }
 }
 
+pub(super) fn still_used(employees: i64) -> AppError {
+    AppError::Conflict(format!(
+        "The unit has {} employee(s); archive it to keep its records",
+        employees
+    ))
+}
+
 pub struct UnitDAOImpl {
-    pool: Rc<PostgrePool>,
+    db: Db,
+}
+
+pub trait UnitDao: Send + Sync {
+    fn insert(&self, unit: &mut Unit) -> Result<(), AppError>;
+    fn update(&self, unit: &Unit) -> Result<(), AppError>;
+    /// Units in use, leaving out archived ones.
+    fn get_all(&self) -> Result<Vec<Unit>, AppError>;
+    fn get_archived(&self) -> Result<Vec<Unit>, AppError>;
+    fn get_all_acountable(&self) -> Result<Vec<Unit>, AppError>;
+    fn get_id(&self, id: i32) -> Result<Unit, AppError>;
+    fn delete(&self, id: i32) -> Result<(), AppError>;
+    fn archive(&self, id: i32) -> Result<(), AppError>;
+    fn restore(&self, id: i32) -> Result<(), AppError>;
 }
 
 impl UnitDAOImpl {
-    pub fn new(pool: Rc<PostgrePool>) -> Self {
-        Self { pool }
+    pub fn new(db: Db) -> Self {
+        Self { db }
     }
 
-    pub fn insert(&self, unit: &mut Unit) -> Result<(), Error> {
-        let mut conn = self.pool.get().unwrap();
+    const INSERT: &'static str =
+        "INSERT INTO unit (unit_number, full_name, short_name) VALUES ($1, $2, $3) RETURNING id";
+    const UPDATE: &'static str =
+        "UPDATE unit SET unit_number = $1, full_name = $2, short_name = $3 WHERE id = $4";
+    const FIND_ALL: &'static str = "SELECT id, unit_number, full_name, short_name, archived_at FROM unit WHERE archived_at IS NULL";
+    const FIND_ARCHIVED: &'static str = "SELECT id, unit_number, full_name, short_name, archived_at FROM unit WHERE archived_at IS NOT NULL ORDER BY archived_at DESC";
+    const FIND_ID: &'static str =
+        "SELECT id, unit_number, full_name, short_name, archived_at FROM unit WHERE id = $1";
+    const ARCHIVE: &'static str =
+        "UPDATE unit SET archived_at = COALESCE(archived_at, now()) WHERE id = $1";
+    const RESTORE: &'static str = "UPDATE unit SET archived_at = NULL WHERE id = $1";
+
+    const DELETE: &'static str = "DELETE FROM unit WHERE id = $1";
+    const COUNT_EMPLOYEES: &'static str = "SELECT COUNT(*) FROM employee WHERE unit_id = $1;";
+    const COUNT_AUDITS: &'static str = "SELECT COUNT(*) FROM audit WHERE unit_id = $1;";
+    const TECH_LIST: &'static str = "";
+    const FIND_ALL_ACCOUNTABLE: &'static str = r#"SELECT DISTINCT u.id, u.unit_number, u.full_name, u.short_name, u.archived_at
+    FROM unit u
+    JOIN employee e ON u.id = e.unit_id
+    WHERE e.is_accountable = true
+    AND e.archived_at IS NULL
+    AND u.archived_at IS NULL;"#;
+}
+
+impl UnitDao for UnitDAOImpl {
+    fn insert(&self, unit: &mut Unit) -> Result<(), AppError> {
+        let mut conn = self.db.get()?;
 
         let id: i32 = conn
             .query_one(
@@ -73,8 +127,8 @@ impl UnitDAOImpl {
         Ok(())
     }
 
-    pub fn update(&self, unit: &Unit) -> Result<(), Error> {
-        let mut conn = self.pool.get().unwrap();
+    fn update(&self, unit: &Unit) -> Result<(), AppError> {
+        let mut conn = self.db.get()?;
         let rows_affected = conn.execute(
             Self::UPDATE,
             &[
@@ -84,12 +138,15 @@ impl UnitDAOImpl {
                 &unit.id
             ],
         )?;
+        if rows_affected == 0 {
+            return Err(AppError::not_found("Unit"));
+        }
 
         Ok(())
     }
 
-    pub fn get_all(&self) -> Result<Vec<Unit>, Error> {
-        let mut conn = self.pool.get().unwrap();
+    fn get_all(&self) -> Result<Vec<Unit>, AppError> {
+        let mut conn = self.db.get()?;
         let mut all_unit = vec![];
         for row in conn.query(Self::FIND_ALL, &[])? {
             all_unit.push(Unit::from_row(row)?);
@@ -97,8 +154,17 @@ impl UnitDAOImpl {
         Ok(all_unit)
     }
 
-    pub fn get_all_acountable(&self) -> Result<Vec<Unit>, Error> {
-        let mut conn = self.pool.get().unwrap();
+    fn get_archived(&self) -> Result<Vec<Unit>, AppError> {
+        let mut conn = self.db.get()?;
+        let mut archived = vec![];
+        for row in conn.query(Self::FIND_ARCHIVED, &[])? {
+            archived.push(Unit::from_row(row)?);
+        }
+        Ok(archived)
+    }
+
+    fn get_all_acountable(&self) -> Result<Vec<Unit>, AppError> {
+        let mut conn = self.db.get()?;
         let mut all_unit = vec![];
         for row in conn.query(Self::FIND_ALL_ACCOUNTABLE, &[])? {
             all_unit.push(Unit::from_row(row)?);
@@ -106,31 +172,46 @@ impl UnitDAOImpl {
         Ok(all_unit)
     }
 
-    pub fn get_id(&self, id: i32) -> Result<Unit, Error> {
-        let mut conn = self.pool.get().unwrap();
-        let row = conn.query_one(Self::FIND_ID, &[&id])?;
+    fn get_id(&self, id: i32) -> Result<Unit, AppError> {
+        let mut conn = self.db.get()?;
+        let row = conn
+            .query_opt(Self::FIND_ID, &[&id])?
+            .ok_or_else(|| AppError::not_found("Unit"))?;
         Ok(Unit::from_row(row)?)
     }
 
-    pub fn delete(&self, id: i32) -> Result<(), Error> {
-        let mut conn = self.pool.get().unwrap();
-        let rows_affected = conn.execute(Self::DELETE, &[&id])?;
-        Ok(())
+    fn delete(&self, id: i32) -> Result<(), AppError> {
+        self.db.transaction(|db| {
+            let mut conn = db.get()?;
+            let employees: i64 = conn.query_one(Self::COUNT_EMPLOYEES, &[&id])?.get(0);
+            if employees > 0 {
+                return Err(still_used(employees));
+            }
+            let audits: i64 = conn.query_one(Self::COUNT_AUDITS, &[&id])?.get(0);
+            if audits > 0 {
+                return Err(audit::audited("unit", audits));
+            }
+            let rows_affected = conn.execute(Self::DELETE, &[&id])?;
+            if rows_affected == 0 {
+                return Err(AppError::not_found("Unit"));
+            }
+            Ok(())
+        })
     }
 
+    fn archive(&self, id: i32) -> Result<(), AppError> {
+        let mut conn = self.db.get()?;
+        if conn.execute(Self::ARCHIVE, &[&id])? == 0 {
+            return Err(AppError::not_found("Unit"));
+        }
+        Ok(())
+    }
 
-    const INSERT: &'static str =
-        "INSERT INTO unit (unit_number, full_name, short_name) VALUES ($1, $2, $3) RETURNING id";
-    const UPDATE: &'static str =
-        "UPDATE unit SET unit_number = $1, full_name = $2, short_name = $3 WHERE id = $4";
-    const FIND_ALL: &'static str = "SELECT id, unit_number, full_name, short_name FROM unit";
-    const FIND_ID: &'static str =
-        "SELECT id, unit_number, full_name, short_name FROM unit WHERE id = $1";
-
-    const DELETE: &'static str = "DELETE FROM unit WHERE id = $1";
-    const TECH_LIST: &'static str = "";
-    const FIND_ALL_ACCOUNTABLE: &'static str = r#"SELECT DISTINCT u.id, u.unit_number, u.full_name, u.short_name
-    FROM unit u
-    JOIN employee e ON u.id = e.unit_id
-    WHERE e.is_accountable = true;"#;
+    fn restore(&self, id: i32) -> Result<(), AppError> {
+        let mut conn = self.db.get()?;
+        if conn.execute(Self::RESTORE, &[&id])? == 0 {
+            return Err(AppError::not_found("Unit"));
+        }
+        Ok(())
+    }
 }
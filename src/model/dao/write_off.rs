@@ -0,0 +1,263 @@
+use super::{employee::Employee, tech::Tech};
+use super::{AppError, Db, Row};
+use crate::model::money::{Amount, Money};
+use chrono::NaiveDate;
+
+/// How an item left the books.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Disposal {
+    WrittenOff,
+    Sold,
+    Scrapped,
+}
+
+impl Disposal {
+    pub const ALL: [Disposal; 3] = [Disposal::WrittenOff, Disposal::Sold, Disposal::Scrapped];
+
+    /// The value stored in `write_off.disposal`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Disposal::WrittenOff => "written_off",
+            Disposal::Sold => "sold",
+            Disposal::Scrapped => "scrapped",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Disposal::WrittenOff => "Written off",
+            Disposal::Sold => "Sold",
+            Disposal::Scrapped => "Scrapped",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Disposal, AppError> {
+        Disposal::ALL
+            .into_iter()
+            .find(|x| x.as_str() == value)
+            .ok_or_else(|| AppError::validation("Unknown kind of disposal"))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WriteOff {
+    id: Option<i32>,
+    pub tech_id: i32,
+    pub write_off_date: NaiveDate,
+    pub disposal: Disposal,
+    pub reason: String,
+    pub act_number: String,
+    /// The employee who signed the act for the commission.
+    pub employee_id: i32,
+    /// In the currency of the tech.
+    pub residual_value: Amount,
+}
+
+impl WriteOff {
+    fn from_row(row: &Row) -> Result<WriteOff, AppError> {
+        Ok(WriteOff {
+            id: row.get("id"),
+            tech_id: row.get("tech_id"),
+            write_off_date: row.get("write_off_date"),
+            disposal: Disposal::parse(row.get("disposal"))?,
+            reason: row.get("reason"),
+            act_number: row.get("act_number"),
+            employee_id: row.get("employee_id"),
+            residual_value: row.get("residual_value"),
+        })
+    }
+
+    pub fn new(
+        tech_id: i32,
+        write_off_date: NaiveDate,
+        disposal: Disposal,
+        reason: &str,
+        act_number: &str,
+        employee_id: i32,
+        residual_value: Amount,
+    ) -> WriteOff {
+        WriteOff {
+            id: None,
+            tech_id,
+            write_off_date,
+            disposal,
+            reason: reason.into(),
+            act_number: act_number.into(),
+            employee_id,
+            residual_value,
+        }
+    }
+
+    pub fn id(&self) -> i32 {
+        self.id.unwrap_or(0)
+    }
+
+    pub fn set_id(&mut self, id: i32) {
+        self.id = Some(id)
+    }
+}
+
+/// A line of the write-off register.
+#[derive(Debug)]
+pub struct WriteOffDetails {
+    pub write_off: WriteOff,
+    pub tech: Tech,
+    pub employee: Employee,
+}
+
+impl WriteOffDetails {
+    pub fn residual_value(&self) -> Money {
+        Money::new(self.write_off.residual_value, self.tech.price.currency)
+    }
+
+    /// Maps a row of `FIND_DETAILED`.
+    fn from_row(row: &Row) -> Result<WriteOffDetails, AppError> {
+        let write_off = WriteOff::from_row(row)?;
+
+        let mut tech = Tech::new_with_date(
+            row.get("inventory_number"),
+            row.get("tech_name"),
+            row.get("model"),
+            row.get("acquisition_date"),
+            Money::new(row.get("price"), row.get("currency")),
+        );
+        tech.set_id(write_off.tech_id);
+        tech.archived_at = row.get("tech_archived_at");
+        tech.written_off_on = Some(write_off.write_off_date);
+
+        let mut employee = Employee::new(
+            row.get("first_name"),
+            row.get("middle_name"),
+            row.get("last_name"),
+            row.get("is_supervisor"),
+            row.get("is_accountable"),
+            row.get("job_title"),
+            row.get("unit_id"),
+        );
+        employee.set_id(write_off.employee_id);
+        employee.archived_at = row.get("employee_archived_at");
+
+        Ok(WriteOffDetails {
+            write_off,
+            tech,
+            employee,
+        })
+    }
+}
+
+pub(super) fn moved_after(transfer_date: NaiveDate) -> AppError {
+    AppError::Conflict(format!(
+        "The tech is transferred on {}; cancel or move that transfer before writing it off",
+        transfer_date
+    ))
+}
+
+pub(super) fn written_off(write_off_date: NaiveDate) -> AppError {
+    AppError::Conflict(format!(
+        "The tech was written off on {} and cannot be transferred after that",
+        write_off_date
+    ))
+}
+
+pub struct WriteOffDAOImpl {
+    db: Db,
+}
+
+pub trait WriteOffDao: Send + Sync {
+    /// Records a write-off, refusing it while the tech is still transferred after
+    /// that day.
+    fn insert(&self, write_off: &mut WriteOff) -> Result<(), AppError>;
+    fn get_by_tech_id(&self, tech_id: i32) -> Result<Option<WriteOffDetails>, AppError>;
+    /// The register for one calendar year, oldest act first.
+    fn get_by_year(&self, year: i32) -> Result<Vec<WriteOffDetails>, AppError>;
+}
+
+impl WriteOffDAOImpl {
+    pub fn new(db: Db) -> Self {
+        Self { db }
+    }
+
+    const INSERT: &'static str = "INSERT INTO write_off (tech_id, write_off_date, disposal, reason, act_number, employee_id, residual_value) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING id;";
+    const LOCK_TECH: &'static str = "SELECT id FROM tech WHERE id = $1 FOR UPDATE;";
+    const LAST_TRANSFER_AFTER: &'static str =
+        "SELECT MAX(transfer_date) FROM transfer WHERE tech_id = $1 AND transfer_date > $2;";
+    const FIND_DETAILED: &'static str = r#"SELECT
+        w.*,
+        t.inventory_number,
+        t.name AS tech_name,
+        t.model,
+        t.acquisition_date,
+        t.price,
+        t.currency,
+        t.archived_at AS tech_archived_at,
+        e.first_name,
+        e.middle_name,
+        e.last_name,
+        e.is_supervisor,
+        e.is_accountable,
+        e.job_title,
+        e.unit_id,
+        e.archived_at AS employee_archived_at
+    FROM
+        write_off w
+    JOIN tech t ON w.tech_id = t.id
+    JOIN employee e ON w.employee_id = e.id"#;
+}
+
+impl WriteOffDao for WriteOffDAOImpl {
+    fn insert(&self, write_off: &mut WriteOff) -> Result<(), AppError> {
+        self.db.transaction(|db| {
+            let mut conn = db.get()?;
+            // Transfers lock the same row, so none can slip in after this check.
+            conn.query(Self::LOCK_TECH, &[&write_off.tech_id])?;
+            let later: Option<NaiveDate> = conn
+                .query_one(
+                    Self::LAST_TRANSFER_AFTER,
+                    &[&write_off.tech_id, &write_off.write_off_date],
+                )?
+                .get(0);
+            if let Some(date) = later {
+                return Err(moved_after(date));
+            }
+
+            let id: i32 = conn
+                .query_one(
+                    Self::INSERT,
+                    &[
+                        &write_off.tech_id,
+                        &write_off.write_off_date,
+                        &write_off.disposal.as_str(),
+                        &write_off.reason,
+                        &write_off.act_number,
+                        &write_off.employee_id,
+                        &write_off.residual_value,
+                    ],
+                )
+                .map(|row| row.get(0))?;
+            write_off.id = Some(id);
+            Ok(())
+        })
+    }
+
+    fn get_by_tech_id(&self, tech_id: i32) -> Result<Option<WriteOffDetails>, AppError> {
+        let mut conn = self.db.get()?;
+        let query = format!("{} WHERE w.tech_id = $1;", Self::FIND_DETAILED);
+        match conn.query_opt(&query, &[&tech_id])? {
+            Some(row) => Ok(Some(WriteOffDetails::from_row(&row)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn get_by_year(&self, year: i32) -> Result<Vec<WriteOffDetails>, AppError> {
+        let mut conn = self.db.get()?;
+        let query = format!(
+            "{} WHERE w.write_off_date >= make_date($1, 1, 1) AND w.write_off_date < make_date($1 + 1, 1, 1) ORDER BY w.write_off_date, w.id;",
+            Self::FIND_DETAILED
+        );
+        let mut register = vec![];
+        for row in conn.query(&query, &[&year])? {
+            register.push(WriteOffDetails::from_row(&row)?);
+        }
+        Ok(register)
+    }
+}
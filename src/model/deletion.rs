@@ -0,0 +1,115 @@
+//! Deletes that take their dependents along. The DAOs refuse to delete a row that
+//! is still referenced; these run the handover and the delete as one unit of work,
+//! so either both happen or neither does. Units and tech have nothing to hand over
+//! without rewriting history, so their deletes stay blocked and they get archived.
+use super::context::AppContext;
+use super::dao::transfer::{is_pending, Transfer, TransferDetails, TransferUnit};
+use super::error::AppError;
+use super::validation::TransferValidator;
+use chrono::{Local, NaiveDate};
+
+/// A reassignment target that does not exist is the user's mistake, not a 404.
+fn target<T>(result: Result<T, AppError>, message: &str) -> Result<T, AppError> {
+    match result {
+        Err(AppError::NotFound(_)) => Err(AppError::validation(message)),
+        other => other,
+    }
+}
+
+/// What became of an employee after their tech was handed over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Removal {
+    Deleted,
+    /// Past transfers still name the employee, so they stay in the records.
+    Archived,
+}
+
+/// Whether `transfer` is the one that put its tech where it is on `date`.
+fn is_current(
+    ctx: &AppContext,
+    transfer: &TransferDetails,
+    date: NaiveDate,
+) -> Result<bool, AppError> {
+    let Some(tech) = &transfer.tech else {
+        return Ok(false);
+    };
+    if tech.written_off_on.is_some_and(|x| x <= date) {
+        return Ok(false);
+    }
+    let history = ctx.transfer.get_by_tech_id(tech.id())?;
+    let current = history
+        .iter()
+        .rev()
+        .find(|x| !is_pending(x.transfer_date, date));
+    Ok(current.is_some_and(|x| x.id == transfer.id))
+}
+
+/// Deletes an employee, first handing the tech they hold to `hand_over_to` if given.
+/// Each item goes over with a new transfer dated today, checked like any other;
+/// their past transfers stay as they were, and since those still name the employee,
+/// the employee is archived rather than deleted.
+pub fn delete_employee(
+    ctx: &AppContext,
+    id: i32,
+    hand_over_to: Option<i32>,
+) -> Result<Removal, AppError> {
+    ctx.with_transaction(|tx| {
+        let Some(to) = hand_over_to else {
+            tx.employee.delete(id)?;
+            return Ok(Removal::Deleted);
+        };
+        if to == id {
+            return Err(AppError::validation(
+                "Pick another employee to take over the tech",
+            ));
+        }
+        let employee = tx.employee.get_id(id)?;
+        target(tx.employee.get_id(to), "The chosen employee does not exist")?;
+
+        let today = Local::now().date_naive();
+        let transfers = tx.transfer.get_by_employee_id(id)?;
+        if transfers.iter().any(|x| is_pending(x.transfer_date, today)) {
+            return Err(AppError::validation(
+                "The employee has pending transfers, cancel or reschedule them first",
+            ));
+        }
+
+        let validator = TransferValidator::new(tx);
+        let mut handed = 0;
+        for transfer in &transfers {
+            let (Some(tech), Some(room)) = (&transfer.tech, &transfer.room) else {
+                continue;
+            };
+            if !is_current(tx, transfer, today)? {
+                continue;
+            }
+            let mut handover = TransferUnit {
+                inner: Transfer::new_date(today, tech.id(), room.id(), to),
+                unit_id: employee.unit_id,
+            };
+            let errors = validator.check(&handover)?;
+            if !errors.is_empty() {
+                return Err(AppError::Validation(format!(
+                    "Tech {} cannot be handed over: {}",
+                    tech.inventory_number,
+                    errors.messages().collect::<Vec<_>>().join("; ")
+                )));
+            }
+            tx.transfer.insert(&mut handover.inner)?;
+            handed += 1;
+        }
+        info!(
+            "Handed {} item(s) of tech from employee {} to {}",
+            handed, id, to
+        );
+
+        if !transfers.is_empty() {
+            if !employee.is_archived() {
+                tx.employee.archive(id)?;
+            }
+            return Ok(Removal::Archived);
+        }
+        tx.employee.delete(id)?;
+        Ok(Removal::Deleted)
+    })
+}
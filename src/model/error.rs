@@ -0,0 +1,60 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum AppError {
+    PoolTimeout(String),
+    Sql {
+        message: String,
+        constraint: Option<String>,
+    },
+    NotFound(String),
+    Conflict(String),
+    Validation(String),
+    BadRequest(String),
+}
+
+impl AppError {
+    pub fn not_found(what: &str) -> AppError {
+        AppError::NotFound(format!("{} not found", what))
+    }
+
+    pub fn validation(message: &str) -> AppError {
+        AppError::Validation(message.to_string())
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::PoolTimeout(e) => write!(f, "Database is unavailable: {}", e),
+            AppError::Sql { message, .. } => write!(f, "{}", message),
+            AppError::NotFound(e) => write!(f, "{}", e),
+            AppError::Conflict(e) => write!(f, "{}", e),
+            AppError::Validation(e) => write!(f, "{}", e),
+            AppError::BadRequest(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<postgres::Error> for AppError {
+    fn from(e: postgres::Error) -> Self {
+        match e.as_db_error() {
+            Some(db_error) => AppError::Sql {
+                message: db_error.message().to_string(),
+                constraint: db_error.constraint().map(|x| x.to_string()),
+            },
+            None => AppError::Sql {
+                message: e.to_string(),
+                constraint: None,
+            },
+        }
+    }
+}
+
+impl From<r2d2::Error> for AppError {
+    fn from(e: r2d2::Error) -> Self {
+        AppError::PoolTimeout(e.to_string())
+    }
+}
@@ -0,0 +1,656 @@
+//! Bulk import of tech, employees and units from CSV. A file is read into rows,
+//! its columns are mapped onto the fields of a record, and every row is checked
+//! before anything is saved: the import either saves all of the rows or none.
+use super::context::AppContext;
+use super::dao::employee::Employee;
+use super::dao::tech::Tech;
+use super::dao::unit::Unit;
+use super::error::AppError;
+use super::money::{Amount, Currency, Money};
+use chrono::NaiveDate;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportKind {
+    Tech,
+    Employee,
+    Unit,
+}
+
+pub struct Field {
+    pub key: &'static str,
+    pub label: &'static str,
+    pub required: bool,
+}
+
+const fn field(key: &'static str, label: &'static str, required: bool) -> Field {
+    Field {
+        key,
+        label,
+        required,
+    }
+}
+
+/// Labels match the columns of the CSV downloads, so an exported list imports back.
+const TECH_FIELDS: &[Field] = &[
+    field("inventory_number", "Inventory number", true),
+    field("name", "Name", true),
+    field("model", "Model", false),
+    field("acquisition_date", "Acquisition date", true),
+    field("price", "Price", true),
+    field("currency", "Currency", false),
+    field("category", "Category", false),
+];
+
+const EMPLOYEE_FIELDS: &[Field] = &[
+    field("last_name", "Last name", true),
+    field("first_name", "First name", true),
+    field("middle_name", "Middle name", false),
+    field("job_title", "Job title", true),
+    field("unit_number", "Unit number", true),
+    field("is_supervisor", "Is supervisor", false),
+    field("is_accountable", "Is accountable", false),
+];
+
+const UNIT_FIELDS: &[Field] = &[
+    field("unit_number", "Unit number", true),
+    field("full_name", "Full name", true),
+    field("short_name", "Short name", true),
+];
+
+impl ImportKind {
+    pub const ALL: [ImportKind; 3] = [ImportKind::Tech, ImportKind::Employee, ImportKind::Unit];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportKind::Tech => "tech",
+            ImportKind::Employee => "employee",
+            ImportKind::Unit => "unit",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ImportKind::Tech => "Tech",
+            ImportKind::Employee => "Employees",
+            ImportKind::Unit => "Units",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<ImportKind, AppError> {
+        ImportKind::ALL
+            .into_iter()
+            .find(|x| x.as_str() == value)
+            .ok_or_else(|| AppError::validation("Choose what to import"))
+    }
+
+    pub fn fields(&self) -> &'static [Field] {
+        match self {
+            ImportKind::Tech => TECH_FIELDS,
+            ImportKind::Employee => EMPLOYEE_FIELDS,
+            ImportKind::Unit => UNIT_FIELDS,
+        }
+    }
+}
+
+/// The rows of a CSV file under its header row.
+#[derive(Debug)]
+pub struct CsvTable {
+    pub headers: Vec<String>,
+    /// Each row with the line of the file it starts on.
+    pub rows: Vec<(usize, Vec<String>)>,
+}
+
+/// Reads CSV as spreadsheets write it: comma, semicolon or tab separated (whichever
+/// the header row uses most), fields optionally quoted with `""` for a quote inside,
+/// and a byte order mark skipped. Blank lines are left out.
+pub fn read_csv(text: &str) -> Result<CsvTable, AppError> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let header_line = text.lines().next().unwrap_or("");
+    let separator = [',', ';', '\t']
+        .into_iter()
+        .max_by_key(|x| header_line.matches(*x).count())
+        .unwrap_or(',');
+
+    let mut records: Vec<(usize, Vec<String>)> = vec![];
+    let mut record = vec![];
+    let mut value = String::new();
+    let mut line = 1;
+    let mut start = 1;
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    value.push('"');
+                }
+                '"' => quoted = false,
+                _ => {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    value.push(c);
+                }
+            }
+            continue;
+        }
+        match c {
+            '"' if value.is_empty() => quoted = true,
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' | '\r' => {
+                record.push(std::mem::take(&mut value));
+                records.push((start, std::mem::take(&mut record)));
+                line += 1;
+                start = line;
+            }
+            _ if c == separator => record.push(std::mem::take(&mut value)),
+            _ => value.push(c),
+        }
+    }
+    if quoted {
+        return Err(AppError::validation(&format!(
+            "The quoted field on line {} is never closed",
+            start
+        )));
+    }
+    if !value.is_empty() || !record.is_empty() {
+        record.push(value);
+        records.push((start, record));
+    }
+
+    let mut records = records
+        .into_iter()
+        .filter(|(_, x)| x.iter().any(|value| !value.trim().is_empty()));
+    let headers = match records.next() {
+        Some((_, headers)) => headers.iter().map(|x| x.trim().to_string()).collect(),
+        None => return Err(AppError::validation("The file is empty")),
+    };
+    Ok(CsvTable {
+        headers,
+        rows: records.collect(),
+    })
+}
+
+/// For each field of the import, the column it is read from.
+pub type Mapping = Vec<Option<usize>>;
+
+/// Maps each field onto the column headed with its label or key, ignoring case.
+pub fn auto_mapping(kind: ImportKind, headers: &[String]) -> Mapping {
+    kind.fields()
+        .iter()
+        .map(|field| {
+            headers.iter().position(|header| {
+                header.eq_ignore_ascii_case(field.label) || header.eq_ignore_ascii_case(field.key)
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+pub enum Record {
+    Tech(Tech),
+    Employee(Employee),
+    Unit(Unit),
+}
+
+/// One row of the file as it would be imported.
+#[derive(Debug)]
+pub struct RowCheck {
+    pub line: usize,
+    /// The value of every field, in the order of `ImportKind::fields`.
+    pub values: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Preview {
+    pub kind: ImportKind,
+    pub rows: Vec<RowCheck>,
+    /// Problems with the mapping itself, which no row can fix.
+    pub errors: Vec<String>,
+    records: Vec<Record>,
+}
+
+impl Preview {
+    pub fn error_count(&self) -> usize {
+        self.errors.len() + self.rows.iter().map(|x| x.errors.len()).sum::<usize>()
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.error_count() == 0 && !self.rows.is_empty()
+    }
+}
+
+/// Reads a date the way it is written in forms (2024-03-01) or in Russian
+/// spreadsheets (01.03.2024).
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(value, "%d.%m.%Y"))
+        .ok()
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "" | "false" | "no" | "n" | "0" | "нет" => Some(false),
+        "true" | "yes" | "y" | "1" | "да" => Some(true),
+        _ => None,
+    }
+}
+
+/// Everything in the store the rows are checked against.
+struct Known {
+    inventory_numbers: HashSet<i32>,
+    /// Unit numbers with the unit they belong to.
+    units: HashMap<i32, Unit>,
+    /// Category names in lower case with their ids.
+    categories: HashMap<String, i32>,
+}
+
+impl Known {
+    fn load(ctx: &AppContext, kind: ImportKind) -> Result<Known, AppError> {
+        let mut known = Known {
+            inventory_numbers: HashSet::new(),
+            units: HashMap::new(),
+            categories: HashMap::new(),
+        };
+        match kind {
+            ImportKind::Tech => {
+                for tech in ctx
+                    .tech
+                    .get_all()?
+                    .into_iter()
+                    .chain(ctx.tech.get_archived()?)
+                {
+                    known.inventory_numbers.insert(tech.inventory_number);
+                }
+                for category in ctx.category.get_all()? {
+                    known
+                        .categories
+                        .insert(category.name.to_lowercase(), category.id());
+                }
+            }
+            ImportKind::Employee | ImportKind::Unit => {
+                for unit in ctx
+                    .unit
+                    .get_all()?
+                    .into_iter()
+                    .chain(ctx.unit.get_archived()?)
+                {
+                    known.units.insert(unit.unit_number, unit);
+                }
+            }
+        }
+        Ok(known)
+    }
+}
+
+/// The values of one row, looked up by field key, with the errors found reading them.
+struct Row<'a> {
+    kind: ImportKind,
+    values: &'a [String],
+    errors: Vec<String>,
+}
+
+impl Row<'_> {
+    fn text(&mut self, key: &str) -> String {
+        let index = self
+            .kind
+            .fields()
+            .iter()
+            .position(|x| x.key == key)
+            .expect("Every field read is declared");
+        let field = &self.kind.fields()[index];
+        let value = self.values[index].clone();
+        if field.required && value.is_empty() {
+            self.errors.push(format!("{} is empty", field.label));
+        }
+        value
+    }
+
+    /// The value parsed with `parse`, or `None` with an error when it is empty or
+    /// does not parse.
+    fn parsed<T>(&mut self, key: &str, what: &str, parse: impl Fn(&str) -> Option<T>) -> Option<T> {
+        let value = self.text(key);
+        if value.is_empty() {
+            return None;
+        }
+        let parsed = parse(&value);
+        if parsed.is_none() {
+            self.errors.push(format!("\"{}\" is not {}", value, what));
+        }
+        parsed
+    }
+}
+
+/// What was seen in earlier rows of the file, to catch a value given twice.
+#[derive(Default)]
+struct Seen {
+    numbers: HashMap<i32, usize>,
+}
+
+impl Seen {
+    fn check(&mut self, number: i32, line: usize, what: &str, errors: &mut Vec<String>) {
+        match self.numbers.get(&number) {
+            Some(first) => errors.push(format!("{} {} is also on line {}", what, number, first)),
+            None => {
+                self.numbers.insert(number, line);
+            }
+        }
+    }
+}
+
+fn check_tech(row: &mut Row, known: &Known, seen: &mut Seen, line: usize) -> Option<Record> {
+    let inventory_number = row.parsed("inventory_number", "a whole number", |x| x.parse().ok());
+    let name = row.text("name");
+    let model = row.text("model");
+    let acquisition_date = row.parsed("acquisition_date", "a date", parse_date);
+    let price = row.parsed("price", "an amount", |x| x.parse::<Amount>().ok());
+    let currency = row
+        .parsed("currency", "a three-letter currency code", |x| {
+            x.parse::<Currency>().ok()
+        })
+        .unwrap_or_default();
+    let category = row.text("category");
+
+    if let Some(number) = inventory_number {
+        if known.inventory_numbers.contains(&number) {
+            row.errors
+                .push(format!("Inventory number {} is already taken", number));
+        }
+        seen.check(number, line, "Inventory number", &mut row.errors);
+    }
+    if price.is_some_and(|x| x.is_negative()) {
+        row.errors.push("The price cannot be negative".to_string());
+    }
+    let category_id = match category.is_empty() {
+        true => None,
+        false => match known.categories.get(&category.to_lowercase()) {
+            Some(id) => Some(*id),
+            None => {
+                row.errors
+                    .push(format!("There is no category \"{}\"", category));
+                None
+            }
+        },
+    };
+
+    let mut tech = Tech::new_with_date(
+        inventory_number?,
+        &name,
+        &model,
+        acquisition_date?,
+        Money::new(price?, currency),
+    );
+    tech.category_id = category_id;
+    Some(Record::Tech(tech))
+}
+
+fn check_employee(row: &mut Row, known: &Known) -> Option<Record> {
+    let last_name = row.text("last_name");
+    let first_name = row.text("first_name");
+    let middle_name = row.text("middle_name");
+    let job_title = row.text("job_title");
+    let unit_number: Option<i32> = row.parsed("unit_number", "a whole number", |x| x.parse().ok());
+    let is_supervisor = row.parsed("is_supervisor", "yes or no", parse_bool);
+    let is_accountable = row.parsed("is_accountable", "yes or no", parse_bool);
+
+    let unit = unit_number.and_then(|number| match known.units.get(&number) {
+        None => {
+            row.errors
+                .push(format!("There is no unit number {}", number));
+            None
+        }
+        Some(unit) if unit.is_archived() => {
+            row.errors.push(format!("Unit {} is archived", number));
+            None
+        }
+        Some(unit) => Some(unit),
+    });
+    let is_supervisor = is_supervisor.unwrap_or(false);
+    let is_accountable = is_accountable.unwrap_or(false);
+    if is_supervisor == is_accountable {
+        row.errors
+            .push("The employee has to be either a supervisor or an accountable".to_string());
+    }
+
+    Some(Record::Employee(Employee::new(
+        &first_name,
+        &middle_name,
+        &last_name,
+        is_supervisor,
+        is_accountable,
+        &job_title,
+        unit?.id(),
+    )))
+}
+
+fn check_unit(row: &mut Row, known: &Known, seen: &mut Seen, line: usize) -> Option<Record> {
+    let unit_number = row.parsed("unit_number", "a whole number", |x| x.parse().ok());
+    let full_name = row.text("full_name");
+    let short_name = row.text("short_name");
+
+    if let Some(number) = unit_number {
+        if known.units.contains_key(&number) {
+            row.errors
+                .push(format!("Unit number {} is already taken", number));
+        }
+        seen.check(number, line, "Unit number", &mut row.errors);
+    }
+    Some(Record::Unit(Unit::new(
+        unit_number?,
+        &full_name,
+        &short_name,
+    )))
+}
+
+/// Checks every row of `table` as it would be imported, without saving anything.
+pub fn dry_run(
+    ctx: &AppContext,
+    kind: ImportKind,
+    table: &CsvTable,
+    mapping: &Mapping,
+) -> Result<Preview, AppError> {
+    let mut preview = Preview {
+        kind,
+        rows: vec![],
+        errors: vec![],
+        records: vec![],
+    };
+    for (field, column) in kind.fields().iter().zip(mapping) {
+        if field.required && column.is_none() {
+            preview
+                .errors
+                .push(format!("Choose the column {} is read from", field.label));
+        }
+    }
+    if !preview.errors.is_empty() {
+        return Ok(preview);
+    }
+
+    let known = Known::load(ctx, kind)?;
+    let mut seen = Seen::default();
+    for (line, cells) in &table.rows {
+        let values: Vec<String> = mapping
+            .iter()
+            .map(|column| {
+                column
+                    .and_then(|x| cells.get(x))
+                    .map_or(String::new(), |x| x.trim().to_string())
+            })
+            .collect();
+        let mut row = Row {
+            kind,
+            values: &values,
+            errors: vec![],
+        };
+        let record = match kind {
+            ImportKind::Tech => check_tech(&mut row, &known, &mut seen, *line),
+            ImportKind::Employee => check_employee(&mut row, &known),
+            ImportKind::Unit => check_unit(&mut row, &known, &mut seen, *line),
+        };
+        let errors = row.errors;
+        if let (Some(record), true) = (record, errors.is_empty()) {
+            preview.records.push(record);
+        }
+        preview.rows.push(RowCheck {
+            line: *line,
+            values,
+            errors,
+        });
+    }
+    Ok(preview)
+}
+
+/// Checks the rows again and saves them all in one unit of work, returning how many
+/// were imported. Nothing is saved if any row has an error.
+pub fn import(
+    ctx: &AppContext,
+    kind: ImportKind,
+    table: &CsvTable,
+    mapping: &Mapping,
+) -> Result<usize, AppError> {
+    ctx.with_transaction(|tx| {
+        let preview = dry_run(tx, kind, table, mapping)?;
+        if preview.rows.is_empty() {
+            return Err(AppError::validation(
+                "The file has no rows below its header",
+            ));
+        }
+        if !preview.is_valid() {
+            return Err(AppError::validation(&format!(
+                "{} problem(s) found; nothing was imported",
+                preview.error_count()
+            )));
+        }
+        let count = preview.records.len();
+        for record in preview.records {
+            match record {
+                Record::Tech(mut tech) => tx.tech.insert(&mut tech)?,
+                Record::Employee(mut employee) => tx.employee.insert(&mut employee)?,
+                Record::Unit(mut unit) => tx.unit.insert(&mut unit)?,
+            }
+        }
+        Ok(count)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::dao::memory::MemoryStore;
+    use crate::model::dao::unit::UnitDao;
+    use std::sync::Arc;
+
+    /// A store with unit 1 and nothing else.
+    fn context() -> AppContext {
+        let store = MemoryStore::new();
+        let mut unit = Unit::new(1, "Accounting department", "ACC");
+        UnitDao::insert(&store, &mut unit).unwrap();
+        AppContext::in_memory(Arc::new(store))
+    }
+
+    fn rows(table: &CsvTable) -> Vec<(usize, Vec<&str>)> {
+        table
+            .rows
+            .iter()
+            .map(|(line, x)| (*line, x.iter().map(String::as_str).collect()))
+            .collect()
+    }
+
+    fn check(kind: ImportKind, csv: &str) -> Preview {
+        let table = read_csv(csv).unwrap();
+        let mapping = auto_mapping(kind, &table.headers);
+        dry_run(&context(), kind, &table, &mapping).unwrap()
+    }
+
+    #[test]
+    fn reads_quoted_fields() {
+        let table = read_csv("a,b\n\"say \"\"hi\"\"\",\"x, y\"\n\"\",plain\n").unwrap();
+        assert_eq!(table.headers, ["a", "b"]);
+        assert_eq!(
+            rows(&table),
+            [(2, vec!["say \"hi\"", "x, y"]), (3, vec!["", "plain"])]
+        );
+    }
+
+    #[test]
+    fn keeps_newlines_inside_quotes() {
+        let table = read_csv("a,b\r\n\"one\r\ntwo\",1\r\n\r\nlast,2").unwrap();
+        // The quoted field spans lines 2 and 3, the blank line 4 is left out.
+        assert_eq!(
+            rows(&table),
+            [(2, vec!["one\r\ntwo", "1"]), (5, vec!["last", "2"])]
+        );
+    }
+
+    #[test]
+    fn picks_the_separator_from_the_header() {
+        let semicolons = read_csv("a;b;c\n1,5;2;3\n").unwrap();
+        assert_eq!(semicolons.headers, ["a", "b", "c"]);
+        assert_eq!(rows(&semicolons), [(2, vec!["1,5", "2", "3"])]);
+
+        let tabs = read_csv("a\tb\n1;2\t3\n").unwrap();
+        assert_eq!(rows(&tabs), [(2, vec!["1;2", "3"])]);
+
+        let single = read_csv("a\n1\n").unwrap();
+        assert_eq!(single.headers, ["a"]);
+    }
+
+    #[test]
+    fn skips_the_byte_order_mark() {
+        let table = read_csv("\u{feff}Name,Model\nPrinter,X1\n").unwrap();
+        assert_eq!(table.headers, ["Name", "Model"]);
+    }
+
+    #[test]
+    fn refuses_unclosed_quotes_and_empty_files() {
+        let error = read_csv("a,b\n1,2\n3,\"open\n4,5\n").unwrap_err();
+        assert!(error.to_string().contains("line 3"), "{}", error);
+        assert!(read_csv("").is_err());
+        assert!(read_csv("\n  \n\n").is_err());
+    }
+
+    #[test]
+    fn flags_inventory_numbers_repeated_in_the_file() {
+        let preview = check(
+            ImportKind::Tech,
+            "Inventory number,Name,Acquisition date,Price\n\
+             7,Laptop,2024-03-01,1000\n\
+             8,Monitor,01.03.2024,\"250,50\"\n\
+             7,Printer,2024-03-01,300\n",
+        );
+        assert!(preview.errors.is_empty());
+        assert!(preview.rows[0].errors.is_empty());
+        assert!(preview.rows[1].errors.is_empty());
+        assert_eq!(
+            preview.rows[2].errors,
+            ["Inventory number 7 is also on line 2"]
+        );
+        assert!(!preview.is_valid());
+    }
+
+    #[test]
+    fn flags_unknown_units() {
+        let preview = check(
+            ImportKind::Employee,
+            "Last name,First name,Job title,Unit number,Is accountable\n\
+             Ivanova,Anna,Accountant,1,yes\n\
+             Smirnov,Oleg,Admin,9,yes\n",
+        );
+        assert!(preview.rows[0].errors.is_empty());
+        assert_eq!(preview.rows[1].errors, ["There is no unit number 9"]);
+        assert_eq!(preview.error_count(), 1);
+    }
+
+    #[test]
+    fn asks_for_unmapped_required_columns() {
+        let preview = check(ImportKind::Unit, "Unit number,Full name\n2,IT department\n");
+        assert_eq!(
+            preview.errors,
+            ["Choose the column Short name is read from"]
+        );
+        assert!(preview.rows.is_empty());
+    }
+}
@@ -0,0 +1,109 @@
+use super::error::AppError;
+use super::pool::PostgrePool;
+
+struct Migration {
+    version: i32,
+    name: &'static str,
+    sql: &'static str,
+}
+
+/// Applied in order; a migration that has shipped must never be edited, add a new one instead.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "create_tables",
+        sql: include_str!("../../migrations/0001_create_tables.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "add_constraints",
+        sql: include_str!("../../migrations/0002_add_constraints.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "room_squares_check",
+        sql: include_str!("../../migrations/0003_room_squares_check.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "archive",
+        sql: include_str!("../../migrations/0004_archive.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "write_off",
+        sql: include_str!("../../migrations/0005_write_off.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "depreciation",
+        sql: include_str!("../../migrations/0006_depreciation.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "money",
+        sql: include_str!("../../migrations/0007_money.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "audit",
+        sql: include_str!("../../migrations/0008_audit.sql"),
+    },
+];
+
+/// Arbitrary key for the advisory lock that keeps two starting servers from
+/// migrating the same database at once.
+const LOCK_KEY: i64 = 0x5042_5a32;
+
+const CREATE_TABLE: &str = r#"CREATE TABLE IF NOT EXISTS schema_migrations (
+    version INTEGER PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    applied_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);"#;
+const LOCK: &str = "SELECT pg_advisory_xact_lock($1);";
+const FIND_APPLIED: &str = "SELECT version FROM schema_migrations;";
+const INSERT: &str = "INSERT INTO schema_migrations (version, name) VALUES ($1, $2);";
+
+/// Applies every migration the database has not seen yet, all in one transaction.
+/// Returns the number of migrations applied.
+pub fn run(pool: &PostgrePool) -> Result<usize, AppError> {
+    let mut conn = pool.get()?;
+    let mut transaction = conn.transaction()?;
+    transaction.execute(LOCK, &[&LOCK_KEY])?;
+    transaction.batch_execute(CREATE_TABLE)?;
+
+    let applied: Vec<i32> = transaction
+        .query(FIND_APPLIED, &[])?
+        .iter()
+        .map(|row| row.get(0))
+        .collect();
+
+    let mut count = 0;
+    for migration in MIGRATIONS {
+        if applied.contains(&migration.version) {
+            continue;
+        }
+        transaction
+            .batch_execute(migration.sql)
+            .map_err(|e| failed(migration, e.into()))?;
+        transaction.execute(INSERT, &[&migration.version, &migration.name])?;
+        info!(
+            "Applied migration {:04}_{}",
+            migration.version, migration.name
+        );
+        count += 1;
+    }
+
+    transaction.commit()?;
+    Ok(count)
+}
+
+fn failed(migration: &Migration, e: AppError) -> AppError {
+    AppError::Sql {
+        message: format!(
+            "Migration {:04}_{} failed: {}",
+            migration.version, migration.name, e
+        ),
+        constraint: None,
+    }
+}
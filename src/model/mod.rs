@@ -1,15 +1,30 @@
 use postgres::{Client, Error, NoTls, Row};
 
 pub mod dao {
-    use super::pool::PostgrePool;
-    use super::{Error, Row};
+    use super::error::AppError;
+    use super::transaction::Db;
+    use super::{Client, Error, Row};
 
+    pub mod audit;
+    pub mod category;
     pub mod employee;
+    pub mod memory;
     // pub mod room;
     pub mod tech;
     pub mod transfer;
     pub mod unit;
     pub mod room;
+    pub mod write_off;
 }
 
+pub mod acts;
+pub mod context;
+pub mod deletion;
+pub mod error;
+pub mod import;
+pub mod migrations;
+pub mod money;
 pub mod pool;
+pub mod stocktaking;
+pub mod transaction;
+pub mod validation;
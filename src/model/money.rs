@@ -0,0 +1,447 @@
+//! Exact amounts of money. Prices are kept in minor units (kopecks, cents) and
+//! stored as `NUMERIC(14, 2)`, so nothing is lost to floating point on the way.
+use bytes::{BufMut, BytesMut};
+use postgres::types::{to_sql_checked, FromSql, IsNull, ToSql, Type};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// How amounts are written on the pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    /// 1,234.56
+    En = 1,
+    /// 1 234,56
+    Ru = 2,
+    /// 1.234,56
+    De = 3,
+}
+
+impl Locale {
+    /// The digit group and decimal separators.
+    fn separators(&self) -> (char, char) {
+        match self {
+            Locale::En => (',', '.'),
+            Locale::Ru => ('\u{a0}', ','),
+            Locale::De => ('.', ','),
+        }
+    }
+}
+
+impl FromStr for Locale {
+    type Err = ();
+
+    /// Takes a language code, also as part of a full locale name like `ru_RU.UTF-8`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let language = s.split(['_', '-', '.']).next().unwrap_or("");
+        match language.to_lowercase().as_str() {
+            "en" => Ok(Locale::En),
+            "ru" => Ok(Locale::Ru),
+            "de" => Ok(Locale::De),
+            _ => Err(()),
+        }
+    }
+}
+
+static LOCALE: AtomicU8 = AtomicU8::new(Locale::Ru as u8);
+
+pub fn set_locale(locale: Locale) {
+    LOCALE.store(locale as u8, Ordering::Relaxed);
+}
+
+fn locale() -> Locale {
+    match LOCALE.load(Ordering::Relaxed) {
+        1 => Locale::En,
+        3 => Locale::De,
+        _ => Locale::Ru,
+    }
+}
+
+/// Writes `minor` units with the locale's separators, e.g. `1 234,56`.
+fn format_minor(minor: i128) -> String {
+    let (group, decimal) = locale().separators();
+    let units = (minor.unsigned_abs() / 100).to_string();
+    let mut text = String::new();
+    if minor < 0 {
+        text.push('-');
+    }
+    for (index, digit) in units.chars().enumerate() {
+        if index > 0 && (units.len() - index).is_multiple_of(3) {
+            text.push(group);
+        }
+        text.push(digit);
+    }
+    format!("{}{}{:02}", text, decimal, minor.unsigned_abs() % 100)
+}
+
+/// An amount with two decimal places.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Amount(i64);
+
+impl Amount {
+    pub const ZERO: Amount = Amount(0);
+    /// The largest amount a `NUMERIC(14, 2)` column holds.
+    pub const MAX: Amount = Amount(99_999_999_999_999);
+
+    pub fn from_minor(minor: i64) -> Amount {
+        Amount(minor)
+    }
+
+    /// The amount in kopecks or cents.
+    pub fn minor(&self) -> i64 {
+        self.0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.0 < 0
+    }
+}
+
+/// Accepts both `.` and `,` as the decimal separator and ignores spaces between
+/// digit groups, so amounts can be pasted from an invoice. Amounts too large to
+/// store are refused.
+impl FromStr for Amount {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let text: String = s.chars().filter(|x| !x.is_whitespace()).collect();
+        let (negative, text) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text.as_str()),
+        };
+        let (units, fraction) = match text.split_once(['.', ',']) {
+            Some((units, fraction)) => (units, fraction),
+            None => (text, ""),
+        };
+        let digits = |x: &str| x.chars().all(|c| c.is_ascii_digit());
+        if units.is_empty() || !digits(units) || !digits(fraction) || fraction.len() > 2 {
+            return Err(());
+        }
+        let units: i64 = units.parse().map_err(|_| ())?;
+        let cents: i64 = format!("{:0<2}", fraction).parse().map_err(|_| ())?;
+        let minor = units
+            .checked_mul(100)
+            .and_then(|x| x.checked_add(cents))
+            .filter(|x| *x <= Amount::MAX.0)
+            .ok_or(())?;
+        Ok(Amount(if negative { -minor } else { minor }))
+    }
+}
+
+/// The plain form used in form fields: `1234.56`.
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let minor = self.0.unsigned_abs();
+        write!(f, "{}{}.{:02}", sign, minor / 100, minor % 100)
+    }
+}
+
+/// Postgres sends `NUMERIC` as base 10000 digits, most significant first, with the
+/// weight of the first digit and the sign in a header.
+const NUMERIC_NEGATIVE: u16 = 0x4000;
+
+impl ToSql for Amount {
+    fn to_sql(&self, _: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        // In ten-thousandths, the last base 10000 digit is the fraction.
+        let mut rest = self.0.unsigned_abs() as u128 * 100;
+        let mut digits = vec![];
+        while rest > 0 {
+            digits.push((rest % 10000) as i16);
+            rest /= 10000;
+        }
+        let weight = digits.len() as i16 - 2;
+        let trailing_zeros = digits.iter().take_while(|x| **x == 0).count();
+        digits.drain(..trailing_zeros);
+        digits.reverse();
+
+        out.put_i16(digits.len() as i16);
+        out.put_i16(if digits.is_empty() { 0 } else { weight });
+        out.put_u16(if self.0 < 0 { NUMERIC_NEGATIVE } else { 0 });
+        out.put_u16(2);
+        for digit in digits {
+            out.put_i16(digit);
+        }
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::NUMERIC
+    }
+
+    to_sql_checked!();
+}
+
+impl<'a> FromSql<'a> for Amount {
+    fn from_sql(_: &Type, raw: &'a [u8]) -> Result<Amount, Box<dyn Error + Sync + Send>> {
+        let word = |index: usize| {
+            raw.get(index * 2..index * 2 + 2)
+                .map(|x| u16::from_be_bytes([x[0], x[1]]))
+                .ok_or("Truncated numeric value")
+        };
+        let count = word(0)? as usize;
+        let weight = word(1)? as i16 as i32;
+        let sign = word(2)?;
+        if sign != 0 && sign != NUMERIC_NEGATIVE {
+            return Err("Not a finite amount of money".into());
+        }
+        let digits = (0..count)
+            .map(|index| word(4 + index))
+            .collect::<Result<Vec<u16>, _>>()?;
+        if digits
+            .iter()
+            .skip((weight + 2).max(0) as usize)
+            .any(|x| *x != 0)
+        {
+            return Err("More than two decimal places in an amount of money".into());
+        }
+
+        // Ten-thousandths again, from the first digit down to the fraction.
+        let mut value: i128 = 0;
+        for position in (-1..=weight).rev() {
+            let digit = digits
+                .get((weight - position) as usize)
+                .copied()
+                .unwrap_or(0);
+            value = value
+                .checked_mul(10000)
+                .and_then(|x| x.checked_add(digit as i128))
+                .ok_or("Amount of money out of range")?;
+        }
+        if value % 100 != 0 {
+            return Err("More than two decimal places in an amount of money".into());
+        }
+        let minor = i64::try_from(value / 100).map_err(|_| "Amount of money out of range")?;
+        Ok(Amount(if sign == NUMERIC_NEGATIVE {
+            -minor
+        } else {
+            minor
+        }))
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::NUMERIC
+    }
+}
+
+/// An ISO 4217 currency code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Currency([u8; 3]);
+
+impl Currency {
+    pub const RUB: Currency = Currency(*b"RUB");
+
+    pub fn as_str(&self) -> &str {
+        std::str::from_utf8(&self.0).unwrap_or("???")
+    }
+}
+
+impl Default for Currency {
+    fn default() -> Self {
+        Currency::RUB
+    }
+}
+
+impl FromStr for Currency {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let code: [u8; 3] = s.trim().as_bytes().try_into().map_err(|_| ())?;
+        if !code.iter().all(|x| x.is_ascii_alphabetic()) {
+            return Err(());
+        }
+        Ok(Currency(code.map(|x| x.to_ascii_uppercase())))
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl ToSql for Currency {
+    fn to_sql(
+        &self,
+        ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        self.as_str().to_sql(ty, out)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        <&str as ToSql>::accepts(ty)
+    }
+
+    to_sql_checked!();
+}
+
+impl<'a> FromSql<'a> for Currency {
+    fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<Currency, Box<dyn Error + Sync + Send>> {
+        let code = <&str as FromSql>::from_sql(ty, raw)?;
+        code.parse()
+            .map_err(|_| format!("Invalid currency code {:?}", code).into())
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        <&str as FromSql>::accepts(ty)
+    }
+}
+
+/// An amount in a given currency, shown the way the locale writes it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Money {
+    pub amount: Amount,
+    pub currency: Currency,
+}
+
+impl Money {
+    pub fn new(amount: Amount, currency: Currency) -> Money {
+        Money { amount, currency }
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}",
+            format_minor(self.amount.0 as i128),
+            self.currency
+        )
+    }
+}
+
+/// A sum of money in every currency it was given. The sums are wide enough that
+/// adding up any number of stored prices cannot overflow.
+#[derive(Debug, Clone, Default)]
+pub struct Totals {
+    sums: BTreeMap<Currency, i128>,
+}
+
+impl Totals {
+    pub fn add(&mut self, money: Money) {
+        *self.sums.entry(money.currency).or_insert(0) += money.amount.0 as i128;
+    }
+
+    /// The sum in minor units for each currency, in currency order.
+    pub fn iter(&self) -> impl Iterator<Item = (Currency, i128)> + '_ {
+        self.sums.iter().map(|(currency, sum)| (*currency, *sum))
+    }
+}
+
+impl FromIterator<Money> for Totals {
+    fn from_iter<I: IntoIterator<Item = Money>>(iter: I) -> Self {
+        let mut totals = Totals::default();
+        for money in iter {
+            totals.add(money);
+        }
+        totals
+    }
+}
+
+impl fmt::Display for Totals {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.sums.is_empty() {
+            return f.write_str(&format_minor(0));
+        }
+        for (index, (currency, sum)) in self.sums.iter().enumerate() {
+            if index > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{} {}", format_minor(*sum), currency)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(amount: Amount) -> Vec<u8> {
+        let mut out = BytesMut::new();
+        amount.to_sql(&Type::NUMERIC, &mut out).unwrap();
+        out.to_vec()
+    }
+
+    fn decode(raw: &[u8]) -> Result<Amount, Box<dyn Error + Sync + Send>> {
+        Amount::from_sql(&Type::NUMERIC, raw)
+    }
+
+    /// A `NUMERIC` as Postgres sends it: digit count, weight, sign, scale, digits.
+    fn numeric(weight: i16, sign: u16, scale: u16, digits: &[u16]) -> Vec<u8> {
+        let mut out = BytesMut::new();
+        out.put_i16(digits.len() as i16);
+        out.put_i16(weight);
+        out.put_u16(sign);
+        out.put_u16(scale);
+        for digit in digits {
+            out.put_u16(*digit);
+        }
+        out.to_vec()
+    }
+
+    fn round_trip(minor: i64) {
+        let amount = Amount::from_minor(minor);
+        assert_eq!(decode(&encode(amount)).unwrap(), amount, "{}", amount);
+    }
+
+    #[test]
+    fn encodes_base_10000_digits() {
+        let amount = |minor| encode(Amount::from_minor(minor));
+        assert_eq!(amount(12_345), numeric(0, 0, 2, &[123, 4500]));
+        assert_eq!(amount(1_000_000), numeric(1, 0, 2, &[1]));
+        assert_eq!(amount(50), numeric(-1, 0, 2, &[5000]));
+        assert_eq!(amount(0), numeric(0, 0, 2, &[]));
+        assert_eq!(amount(-50), numeric(-1, NUMERIC_NEGATIVE, 2, &[5000]));
+    }
+
+    #[test]
+    fn round_trips_through_numeric() {
+        for minor in [0, 1, 50, 12_345, 1_000_000, 100_000_001] {
+            round_trip(minor);
+            round_trip(-minor);
+        }
+        round_trip(Amount::MAX.minor());
+        round_trip(-Amount::MAX.minor());
+    }
+
+    #[test]
+    fn decodes_what_postgres_sends() {
+        // Postgres keeps the column's scale, 10000.00 comes as a single digit.
+        assert_eq!(decode(&numeric(1, 0, 2, &[1])).unwrap().minor(), 1_000_000);
+        assert_eq!(decode(&numeric(0, 0, 0, &[])).unwrap(), Amount::ZERO);
+        let negative = numeric(0, NUMERIC_NEGATIVE, 2, &[123, 4500]);
+        assert_eq!(decode(&negative).unwrap().minor(), -12_345);
+    }
+
+    #[test]
+    fn refuses_more_than_two_decimal_places() {
+        // 1.005 and 1.00001
+        assert!(decode(&numeric(0, 0, 3, &[1, 50])).is_err());
+        assert!(decode(&numeric(0, 0, 5, &[1, 0, 1000])).is_err());
+        // NaN
+        assert!(decode(&numeric(0, 0xC000, 0, &[])).is_err());
+        assert!(decode(&[0, 1]).is_err());
+    }
+
+    #[test]
+    fn parses_amounts_from_forms() {
+        let parse = |text: &str| text.parse::<Amount>().map(|x| x.minor());
+        assert_eq!(parse("1 234,56"), Ok(123_456));
+        assert_eq!(parse("1234.56"), Ok(123_456));
+        assert_eq!(parse("-0,5"), Ok(-50));
+        assert_eq!(parse("7"), Ok(700));
+        assert_eq!(parse("999999999999.99"), Ok(Amount::MAX.minor()));
+        // With a comma between groups the decimal separator is ambiguous, so the
+        // amount is refused rather than misread.
+        assert_eq!(parse("1,234.56"), Err(()));
+        assert_eq!(parse("1000000000000.00"), Err(()));
+        assert_eq!(parse("1.005"), Err(()));
+        assert_eq!(parse(""), Err(()));
+        assert_eq!(parse("12a"), Err(()));
+    }
+}
@@ -1,24 +1,63 @@
-use postgres::{Config, NoTls};
-use r2d2::{Pool, PooledConnection};
+use crate::config::DatabaseConfig;
+use native_tls::TlsConnector;
+use postgres::Config;
+use postgres_native_tls::MakeTlsConnector;
+use r2d2::Pool;
 use r2d2_postgres::PostgresConnectionManager;
-use super::dao::*;
+use std::fmt;
 
-pub type PostgrePool = Pool<PostgresConnectionManager<NoTls>>;
+pub type PostgrePool = Pool<PostgresConnectionManager<MakeTlsConnector>>;
 
-pub fn create_pool() -> PostgrePool {
+/// Why the pool could not be opened at startup.
+#[derive(Debug)]
+pub enum PoolError {
+    /// The TLS backend could not be set up, before any connection was tried.
+    Tls(native_tls::Error),
+    Connect {
+        target: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for PoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PoolError::Tls(e) => write!(f, "Cannot set up TLS for the database: {}", e),
+            PoolError::Connect { target, message } => write!(
+                f,
+                "Database is unavailable: cannot connect to {}: {}",
+                target, message
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PoolError {}
+
+/// Opens the pool and checks that the database is reachable, so a bad host or
+/// password shows up at startup rather than on the first request.
+pub fn create_pool(database: &DatabaseConfig) -> Result<PostgrePool, PoolError> {
     let mut config = Config::new();
-    let user = std::env::var("POSTGRES_USER").expect("POSTGRES_USER must be set");
-    let password = std::env::var("POSTGRES_PASSWORD").expect("POSTGRES_PASSWORD must be set");
-    let dbname = std::env::var("POSTGRES_DB").expect("POSTGRES_DB must be set");
     config
-        .host("db")
-        .user(&user)
-        .password(&password)
-        .dbname(&dbname);
-    let manager = PostgresConnectionManager::new(config, NoTls);
+        .host(&database.host)
+        .port(database.port)
+        .user(&database.user)
+        .password(&database.password)
+        .dbname(&database.name)
+        .ssl_mode(database.ssl_mode)
+        .connect_timeout(database.connect_timeout);
+
+    // Certificates are checked against the system roots whenever TLS is used.
+    let connector = TlsConnector::new().map_err(PoolError::Tls)?;
+    let manager = PostgresConnectionManager::new(config, MakeTlsConnector::new(connector));
 
     Pool::builder()
-        .max_size(15)
+        .max_size(database.pool_size)
+        .min_idle(Some(1))
+        .connection_timeout(database.connect_timeout)
         .build(manager)
-        .expect("Failed to create a pool")
+        .map_err(|e| PoolError::Connect {
+            target: format!("{}:{}/{}", database.host, database.port, database.name),
+            message: e.to_string(),
+        })
 }
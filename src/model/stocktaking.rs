@@ -0,0 +1,105 @@
+//! Stocktaking audits. An audit lists what the books place in a unit or room on the
+//! day it is opened; the auditors then mark each item and add whatever else they
+//! come across, and closing the audit fixes the discrepancy report.
+use super::context::AppContext;
+use super::dao::audit::{Audit, AuditItem, AuditScope, Finding};
+use super::error::AppError;
+use chrono::NaiveDate;
+
+/// Opens an audit of `scope`, listing the tech the books place there on `date`.
+pub fn open_audit(ctx: &AppContext, scope: AuditScope, date: NaiveDate) -> Result<Audit, AppError> {
+    ctx.with_transaction(|tx| {
+        match scope {
+            AuditScope::Unit(id) => {
+                let unit = match tx.unit.get_id(id) {
+                    Err(AppError::NotFound(_)) => {
+                        return Err(AppError::validation("The chosen unit does not exist"))
+                    }
+                    other => other?,
+                };
+                if unit.is_archived() {
+                    return Err(AppError::validation("An archived unit cannot be audited"));
+                }
+            }
+            AuditScope::Room(id) => {
+                if let Err(AppError::NotFound(_)) = tx.room.get_id(id) {
+                    return Err(AppError::validation("The chosen room does not exist"));
+                }
+            }
+        }
+
+        let mut items: Vec<AuditItem> = tx
+            .tech
+            .get_holdings(date)?
+            .iter()
+            .filter(|x| scope.covers(x))
+            .map(AuditItem::expected)
+            .collect();
+        let mut audit = Audit::new(scope, date);
+        tx.audit.open(&mut audit, &mut items)?;
+        Ok(audit)
+    })
+}
+
+/// Adds an item the auditors found that is not on the list, noting where the books
+/// place it, if anywhere.
+pub fn add_unexpected(
+    ctx: &AppContext,
+    audit_id: i32,
+    inventory_number: i32,
+    location: &str,
+) -> Result<AuditItem, AppError> {
+    ctx.with_transaction(|tx| {
+        let audit = tx.audit.get_id(audit_id)?.audit;
+        let listed = tx
+            .audit
+            .get_items(audit_id)?
+            .iter()
+            .any(|x| x.inventory_number == inventory_number);
+        if listed {
+            return Err(AppError::validation(&format!(
+                "Item {} is already on the list",
+                inventory_number
+            )));
+        }
+
+        let tech = match tx.tech.get_by_inventory_number(inventory_number) {
+            Ok(x) => Some(x),
+            Err(AppError::NotFound(_)) => None,
+            Err(e) => return Err(e),
+        };
+        let holding = match &tech {
+            Some(tech) => tx
+                .tech
+                .get_holdings(audit.audit_date)?
+                .into_iter()
+                .find(|x| x.tech_id == tech.id()),
+            None => None,
+        };
+        let mut item =
+            AuditItem::unexpected(inventory_number, tech.as_ref(), holding.as_ref(), location);
+        item.audit_id = audit_id;
+        tx.audit.add_item(&mut item)?;
+        Ok(item)
+    })
+}
+
+/// Saves the findings for the expected items of an audit, all or none of them.
+pub fn save_findings(ctx: &AppContext, audit_id: i32, items: &[AuditItem]) -> Result<(), AppError> {
+    let expected = items.iter().filter(|x| x.expected);
+    for item in expected.clone() {
+        if item.finding == Finding::FoundElsewhere && item.location.trim().is_empty() {
+            return Err(AppError::validation(&format!(
+                "Say where item {} was found",
+                item.inventory_number
+            )));
+        }
+    }
+    ctx.with_transaction(|tx| {
+        for item in expected {
+            tx.audit
+                .mark(audit_id, item.id(), item.finding, item.location.trim())?;
+        }
+        Ok(())
+    })
+}
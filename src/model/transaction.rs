@@ -0,0 +1,127 @@
+//! Connections for the Postgres DAOs. A DAO either takes a fresh connection from
+//! the pool for every call or, inside a unit of work, shares one connection that
+//! has a transaction open, so several calls commit or roll back together.
+use super::error::AppError;
+use super::pool::PostgrePool;
+use postgres::Client;
+use postgres_native_tls::MakeTlsConnector;
+use r2d2::PooledConnection;
+use r2d2_postgres::PostgresConnectionManager;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+type Pooled = PooledConnection<PostgresConnectionManager<MakeTlsConnector>>;
+
+/// A pooled connection with an open transaction. If it is dropped before
+/// `commit`, for example because a handler panicked, the transaction is rolled
+/// back so the connection goes back to the pool clean.
+pub struct Transaction {
+    conn: Mutex<Pooled>,
+    finished: AtomicBool,
+}
+
+impl Transaction {
+    fn begin(pool: &PostgrePool) -> Result<Transaction, AppError> {
+        let mut conn = pool.get()?;
+        conn.batch_execute("BEGIN")?;
+        Ok(Transaction {
+            conn: Mutex::new(conn),
+            finished: AtomicBool::new(false),
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Pooled> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn commit(&self) -> Result<(), AppError> {
+        self.finished.store(true, Ordering::Relaxed);
+        self.lock().batch_execute("COMMIT")?;
+        Ok(())
+    }
+
+    fn rollback(&self) {
+        self.finished.store(true, Ordering::Relaxed);
+        if let Err(e) = self.lock().batch_execute("ROLLBACK") {
+            error!("Cannot roll back a transaction: {}", e);
+        }
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        if !self.finished.load(Ordering::Relaxed) {
+            self.rollback();
+        }
+    }
+}
+
+/// Where a Postgres DAO gets its connection from.
+#[derive(Clone)]
+pub enum Db {
+    Pool(Arc<PostgrePool>),
+    Transaction(Arc<Transaction>),
+}
+
+/// A connection borrowed from a `Db`, usable as a `Client`.
+pub enum Conn<'a> {
+    Pooled(Box<Pooled>),
+    Shared(MutexGuard<'a, Pooled>),
+}
+
+impl Deref for Conn<'_> {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        match self {
+            Conn::Pooled(conn) => conn,
+            Conn::Shared(conn) => conn,
+        }
+    }
+}
+
+impl DerefMut for Conn<'_> {
+    fn deref_mut(&mut self) -> &mut Client {
+        match self {
+            Conn::Pooled(conn) => conn,
+            Conn::Shared(conn) => conn,
+        }
+    }
+}
+
+impl Db {
+    /// A connection for one DAO call. Inside a transaction every call gets the same
+    /// connection, so drop it before making the next call.
+    pub fn get(&self) -> Result<Conn<'_>, AppError> {
+        match self {
+            Db::Pool(pool) => Ok(Conn::Pooled(Box::new(pool.get()?))),
+            Db::Transaction(transaction) => Ok(Conn::Shared(transaction.lock())),
+        }
+    }
+
+    /// Runs `f` in a transaction that commits if it returns `Ok` and rolls back
+    /// otherwise. A `Db` that is already in a transaction joins it instead.
+    pub fn transaction<T, F>(&self, f: F) -> Result<T, AppError>
+    where
+        F: FnOnce(&Db) -> Result<T, AppError>,
+    {
+        let pool = match self {
+            Db::Pool(pool) => pool,
+            Db::Transaction(_) => return f(self),
+        };
+
+        let transaction = Arc::new(Transaction::begin(pool)?);
+        let result = f(&Db::Transaction(transaction.clone()));
+        match result {
+            Ok(x) => {
+                transaction.commit()?;
+                Ok(x)
+            }
+            Err(e) => {
+                transaction.rollback();
+                Err(e)
+            }
+        }
+    }
+}
@@ -0,0 +1,237 @@
+//! Business rules a transfer or write-off has to satisfy before it is saved. The
+//! schema only guarantees that the referenced rows exist; everything that needs more
+//! than one row to decide lives here, so every form checks the same things.
+use super::context::AppContext;
+use super::dao::tech::Tech;
+use super::dao::transfer::TransferUnit;
+use super::dao::write_off::WriteOff;
+use super::error::AppError;
+use chrono::Local;
+
+/// Messages keyed by the form field they belong to.
+#[derive(Debug, Default)]
+pub struct FieldErrors {
+    errors: Vec<(&'static str, String)>,
+}
+
+impl FieldErrors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, field: &'static str, message: &str) {
+        self.errors.push((field, message.to_string()));
+    }
+
+    /// The first message for `field`, if it has any.
+    pub fn get(&self, field: &str) -> Option<&str> {
+        self.errors
+            .iter()
+            .find(|(name, _)| *name == field)
+            .map(|(_, message)| message.as_str())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn messages(&self) -> impl Iterator<Item = &str> {
+        self.errors.iter().map(|(_, message)| message.as_str())
+    }
+}
+
+/// A missing row is the user's mistake and becomes a field error; anything else is not.
+fn found<T>(result: Result<T, AppError>) -> Result<Option<T>, AppError> {
+    match result {
+        Ok(x) => Ok(Some(x)),
+        Err(AppError::NotFound(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+pub struct TransferValidator<'a> {
+    ctx: &'a AppContext,
+}
+
+impl<'a> TransferValidator<'a> {
+    pub fn new(ctx: &'a AppContext) -> Self {
+        Self { ctx }
+    }
+
+    /// Checks what the first step of the transfer form asks for: the date, tech,
+    /// unit and room. A transfer without an id is a new one.
+    pub fn check_placement(&self, transfer: &TransferUnit) -> Result<FieldErrors, AppError> {
+        let mut errors = FieldErrors::new();
+        let inner = &transfer.inner;
+
+        match found(self.ctx.tech.get_id(inner.tech_id))? {
+            Some(tech) if tech.is_archived() => {
+                errors.add("tech", "This tech is archived, restore it first")
+            }
+            Some(Tech {
+                written_off_on: Some(date),
+                ..
+            }) if inner.transfer_date > date => errors.add(
+                "date",
+                &format!(
+                    "The tech was written off on {}, a transfer cannot be later",
+                    date
+                ),
+            ),
+            Some(tech) if inner.transfer_date < tech.acquisition_date => errors.add(
+                "date",
+                &format!(
+                    "The tech was acquired on {}, a transfer cannot be earlier",
+                    tech.acquisition_date
+                ),
+            ),
+            Some(_) => self.check_order(transfer, &mut errors)?,
+            None => errors.add("tech", "This tech does not exist"),
+        }
+        match found(self.ctx.unit.get_id(transfer.unit_id))? {
+            Some(unit) if unit.is_archived() => {
+                errors.add("unit", "This unit is archived, restore it first")
+            }
+            Some(_) => {}
+            None => errors.add("unit", "This unit does not exist"),
+        }
+        if found(self.ctx.room.get_id(inner.room_id))?.is_none() {
+            errors.add("room", "This room does not exist");
+        }
+
+        Ok(errors)
+    }
+
+    /// Checks a complete transfer, employee included.
+    pub fn check(&self, transfer: &TransferUnit) -> Result<FieldErrors, AppError> {
+        let mut errors = self.check_placement(transfer)?;
+        for (field, message) in self.check_employee(transfer)?.errors {
+            errors.add(field, &message);
+        }
+        Ok(errors)
+    }
+
+    /// Checks that the transfer goes to an accountable employee of the chosen unit.
+    pub fn check_employee(&self, transfer: &TransferUnit) -> Result<FieldErrors, AppError> {
+        let mut errors = FieldErrors::new();
+
+        match found(self.ctx.employee.get_id(transfer.inner.employee_id))? {
+            Some(employee) if employee.is_archived() => {
+                errors.add("employee", "This employee is archived, restore them first")
+            }
+            Some(employee) if !employee.is_accountable => {
+                errors.add("employee", "This employee is not accountable for tech")
+            }
+            Some(employee) if employee.unit_id != transfer.unit_id => {
+                errors.add("employee", "This employee does not work in the chosen unit")
+            }
+            Some(_) => {}
+            None => errors.add("employee", "This employee does not exist"),
+        }
+
+        Ok(errors)
+    }
+
+    /// A transfer keeps its place in the tech's history: a new one cannot predate
+    /// the latest transfer, an edited one cannot move past its neighbours.
+    fn check_order(
+        &self,
+        transfer: &TransferUnit,
+        errors: &mut FieldErrors,
+    ) -> Result<(), AppError> {
+        let history = self.ctx.transfer.get_by_tech_id(transfer.inner.tech_id)?;
+        let date = transfer.inner.transfer_date;
+
+        let (previous, next) = match history.iter().position(|x| x.id == transfer.inner.id()) {
+            Some(index) => (
+                index.checked_sub(1).map(|x| &history[x]),
+                history.get(index + 1),
+            ),
+            None => (history.last(), None),
+        };
+
+        if let Some(previous) = previous.filter(|x| date < x.transfer_date) {
+            errors.add(
+                "date",
+                &format!(
+                    "The tech already has a transfer on {}, pick that day or later",
+                    previous.transfer_date
+                ),
+            );
+        }
+        if let Some(next) = next.filter(|x| date > x.transfer_date) {
+            errors.add(
+                "date",
+                &format!(
+                    "The tech is transferred again on {}, pick that day or earlier",
+                    next.transfer_date
+                ),
+            );
+        }
+        Ok(())
+    }
+}
+
+pub struct WriteOffValidator<'a> {
+    ctx: &'a AppContext,
+}
+
+impl<'a> WriteOffValidator<'a> {
+    pub fn new(ctx: &'a AppContext) -> Self {
+        Self { ctx }
+    }
+
+    /// Checks a write-off against the tech's history and the employee signing it.
+    pub fn check(&self, write_off: &WriteOff) -> Result<FieldErrors, AppError> {
+        let mut errors = FieldErrors::new();
+        let date = write_off.write_off_date;
+
+        let tech = self.ctx.tech.get_id(write_off.tech_id)?;
+        if let Some(written_off_on) = tech.written_off_on {
+            errors.add(
+                "date",
+                &format!("The tech was already written off on {}", written_off_on),
+            );
+        } else if date > Local::now().date_naive() {
+            errors.add("date", "A write-off cannot be dated in the future");
+        } else if date < tech.acquisition_date {
+            errors.add(
+                "date",
+                &format!(
+                    "The tech was acquired on {}, it cannot be written off earlier",
+                    tech.acquisition_date
+                ),
+            );
+        } else {
+            let history = self.ctx.transfer.get_by_tech_id(write_off.tech_id)?;
+            if let Some(last) = history.last().filter(|x| x.transfer_date > date) {
+                errors.add(
+                    "date",
+                    &format!(
+                        "The tech is transferred on {}, pick that day or later",
+                        last.transfer_date
+                    ),
+                );
+            }
+        }
+
+        match found(self.ctx.employee.get_id(write_off.employee_id))? {
+            Some(employee) if employee.is_archived() => {
+                errors.add("employee", "This employee is archived, restore them first")
+            }
+            Some(_) => {}
+            None => errors.add("employee", "This employee does not exist"),
+        }
+        if write_off.act_number.trim().is_empty() {
+            errors.add("act_number", "Enter the number of the write-off act");
+        }
+        if write_off.reason.trim().is_empty() {
+            errors.add("reason", "Give the reason for the write-off");
+        }
+        if write_off.residual_value.is_negative() {
+            errors.add("residual_value", "The residual value cannot be negative");
+        }
+
+        Ok(errors)
+    }
+}